@@ -0,0 +1,146 @@
+// Desempenho do núcleo: ciclos por segundo numa mistura sintética de instruções, custo do Dxyn e
+// quadros inteiros do IBM Logo sem janela, do jeito que o --headless roda. Usa o criterion:
+// `cargo bench --bench core` (com `--features jit` compara também o JIT)
+
+use chip8::audio::{Beeper, DEFAULT_SAMPLE_RATE, ToneSettings};
+use chip8::cpu::DEFAULT_INSTRUCTIONS_PER_FRAME;
+use chip8::flicker::{FlickerFilter, FlickerMode};
+use chip8::instruction::Instruction;
+#[cfg(feature = "jit")]
+use chip8::jit::Jit;
+use chip8::palette::Palette;
+use chip8::recompiler::Recompiler;
+use chip8::{Chip8, VIDEO_HEIGHT, VIDEO_WIDTH};
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use std::hint::black_box;
+
+// Laço com um pouco de tudo: aritmética, saltos condicionais, subrotina, memória e desenho
+const INSTRUCTION_MIX: [u8; 28] = [
+    0x60, 0x00, // 0x200: LD V0, 0
+    0x61, 0x01, // 0x202: LD V1, 1
+    0x80, 0x14, // 0x204: ADD V0, V1
+    0x82, 0x06, // 0x206: SHR V2, V0
+    0x30, 0x80, // 0x208: SE V0, 0x80
+    0x22, 0x14, // 0x20A: CALL 0x214
+    0xA3, 0x00, // 0x20C: LD I, 0x300
+    0xF2, 0x33, // 0x20E: LD B, V2
+    0xF2, 0x65, // 0x210: LD V0..V2, [I]
+    0x12, 0x04, // 0x212: JP 0x204
+    0xA0, 0x50, // 0x214: LD I, 0x050
+    0xD1, 0x25, // 0x216: DRW V1, V2, 5
+    0x73, 0x01, // 0x218: ADD V3, 1
+    0x00, 0xEE, // 0x21A: RET
+];
+
+const CYCLES: u64 = 10_000;
+// Um segundo de emulação
+const FRAMES: u64 = 60;
+
+fn load(rom: &[u8]) -> Chip8 {
+    let mut chip8 = Chip8::new();
+    chip8.trace = false;
+    chip8.load_bytes(rom).unwrap();
+    chip8
+}
+
+fn cycle(c: &mut Criterion) {
+    let mut group = c.benchmark_group("cycle");
+    group.throughput(Throughput::Elements(CYCLES));
+    for (name, decode_cache) in [("cached", true), ("uncached", false)] {
+        let mut chip8 = load(&INSTRUCTION_MIX);
+        chip8.decode_cache = decode_cache;
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                for _ in 0..CYCLES {
+                    chip8.cycle().unwrap();
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+fn draw(c: &mut Criterion) {
+    let mut group = c.benchmark_group("draw");
+    // Sprite dentro da tela e um que passa das duas bordas (cortado ou dando a volta, conforme a
+    // quirk clip_sprites)
+    for (name, x, y) in [("inside", 8, 8), ("edge", 60, 28)] {
+        for height in [1u8, 8, 15] {
+            let mut chip8 = load(&[]);
+            chip8.v[0] = x;
+            chip8.v[1] = y;
+            chip8.i = 0;
+            group.bench_with_input(BenchmarkId::new(name, height), &height, |b, &height| {
+                b.iter(|| {
+                    chip8.pc = 0x200;
+                    chip8.execute(black_box(Instruction::Draw(0, 1, height)))
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+#[derive(Clone, Copy)]
+enum Engine {
+    Interpreter,
+    Recompiler,
+    #[cfg(feature = "jit")]
+    Jit,
+}
+
+// FRAMES quadros a partir do boot: a CPU e o que o frontend faz no vblank (som, conversão dos
+// pixels e filtro de piscada)
+fn run_frames(rom: &[u8], engine: Engine, instructions_per_frame: usize) {
+    let mut chip8 = load(rom);
+    let mut recompiler = Recompiler::new(chip8.memory.len());
+    #[cfg(feature = "jit")]
+    let mut jit = Jit::new(chip8.memory.len());
+    let mut beeper = Beeper::new(ToneSettings::default(), DEFAULT_SAMPLE_RATE);
+    let mut flicker = FlickerFilter::new(FlickerMode::Off);
+    let palette = Palette::default();
+    let mut pixels = vec![0u8; VIDEO_WIDTH * VIDEO_HEIGHT];
+    let mut buffer = vec![0u32; VIDEO_WIDTH * VIDEO_HEIGHT];
+    for _ in 0..FRAMES {
+        match engine {
+            Engine::Interpreter => chip8.run_frame(instructions_per_frame),
+            Engine::Recompiler => recompiler.run_frame(&mut chip8, instructions_per_frame),
+            #[cfg(feature = "jit")]
+            Engine::Jit => jit.run_frame(&mut chip8, instructions_per_frame),
+        }
+        .unwrap();
+        black_box(beeper.render(&chip8.audio_state()));
+        for (dst, index) in pixels.iter_mut().zip(chip8.pixel_indices()) {
+            *dst = index;
+        }
+        flicker.process(&pixels, &palette, &mut buffer);
+    }
+    black_box(&buffer);
+}
+
+fn ibm_logo(c: &mut Criterion) {
+    let rom = std::fs::read("roms/IBM Logo.ch8").expect("roms/IBM Logo.ch8");
+    let engines = [
+        ("interpreter", Engine::Interpreter),
+        ("recompiler", Engine::Recompiler),
+        #[cfg(feature = "jit")]
+        ("jit", Engine::Jit),
+    ];
+
+    let mut group = c.benchmark_group("ibm_logo");
+    // Na velocidade normal pesa mais o vblank; com 1000 instruções por quadro, a CPU
+    for instructions_per_frame in [DEFAULT_INSTRUCTIONS_PER_FRAME, 1000] {
+        group.throughput(Throughput::Elements(FRAMES * instructions_per_frame as u64));
+        for &(name, engine) in &engines {
+            group.bench_with_input(
+                BenchmarkId::new(name, instructions_per_frame),
+                &instructions_per_frame,
+                |b, &ipf| b.iter(|| run_frames(&rom, engine, ipf)),
+            );
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, cycle, draw, ibm_logo);
+criterion_main!(benches);
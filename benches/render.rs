@@ -0,0 +1,61 @@
+// Custo de ampliar a tela de 64x32 para a janela em cada estilo de pixel, alocando um Vec novo a
+// cada quadro (scale_buffer) ou reaproveitando o mesmo (scale_buffer_into, o que o frontend usa).
+// `cargo bench --bench render`
+
+use chip8::palette::Palette;
+use chip8::render::{PixelStyle, scale_buffer, scale_buffer_into};
+use chip8::{VIDEO_HEIGHT, VIDEO_WIDTH};
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use std::hint::black_box;
+
+fn scale(c: &mut Criterion) {
+    let palette = Palette::default();
+    // Metade dos pixels acesa, em xadrez
+    let screen: Vec<u32> = (0..VIDEO_WIDTH * VIDEO_HEIGHT)
+        .map(|n| palette.color(((n + n / VIDEO_WIDTH) % 2) as u8))
+        .collect();
+
+    let mut group = c.benchmark_group("scale_buffer");
+    for scale in [1, 4, 10, 20] {
+        group.throughput(Throughput::Elements(
+            (VIDEO_WIDTH * VIDEO_HEIGHT * scale * scale) as u64,
+        ));
+        for style in [PixelStyle::Solid, PixelStyle::Grid, PixelStyle::Rounded] {
+            let name = format!("{:?}", style).to_lowercase();
+            group.bench_with_input(BenchmarkId::new(&name, scale), &scale, |b, &scale| {
+                b.iter(|| {
+                    scale_buffer(
+                        black_box(&screen),
+                        VIDEO_WIDTH,
+                        VIDEO_HEIGHT,
+                        scale,
+                        style,
+                        &palette,
+                    )
+                })
+            });
+            let mut scaled = Vec::new();
+            group.bench_with_input(
+                BenchmarkId::new(format!("{}_into", name), scale),
+                &scale,
+                |b, &scale| {
+                    b.iter(|| {
+                        scale_buffer_into(
+                            black_box(&screen),
+                            VIDEO_WIDTH,
+                            VIDEO_HEIGHT,
+                            scale,
+                            style,
+                            &palette,
+                            &mut scaled,
+                        )
+                    })
+                },
+            );
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, scale);
+criterion_main!(benches);
@@ -0,0 +1,44 @@
+#![no_main]
+
+// Carrega bytes arbitrários como ROM e executa um número limitado de ciclos. Qualquer panic é um
+// bug: erros de ROM precisam voltar como `Chip8Error`.
+//
+// cargo +nightly fuzz run cycle
+
+use chip8::platform::Platform;
+use libfuzzer_sys::fuzz_target;
+
+const MAX_CYCLES: usize = 10_000;
+
+fuzz_target!(|data: &[u8]| {
+    // O primeiro byte escolhe a plataforma (cada variante tem a própria tabela de opcodes e o
+    // próprio modelo de tela), os dois seguintes viram o estado do teclado e o resto é a ROM
+    let (platform, keys, rom) = match data {
+        [platform, high, low, rom @ ..] => (
+            Platform::ALL[*platform as usize % Platform::ALL.len()],
+            u16::from_be_bytes([*high, *low]),
+            rom,
+        ),
+        _ => return,
+    };
+
+    let mut chip8 = platform.machine();
+    if chip8.load_bytes_at(rom, platform.start_address()).is_err() {
+        return;
+    }
+    for key in 0..16 {
+        chip8.keypad[key] = keys & (1 << key) != 0;
+        // O segundo teclado do CHIP-8X recebe as mesmas teclas ao contrário
+        chip8.second_keypad[15 - key] = chip8.keypad[key];
+    }
+
+    for cycle in 0..MAX_CYCLES {
+        if chip8.cycle().is_err() {
+            break;
+        }
+        // Timers a cada 10 ciclos, mais ou menos como no frontend
+        if cycle % 10 == 0 {
+            chip8.tick_timers();
+        }
+    }
+});
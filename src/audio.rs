@@ -0,0 +1,405 @@
+use crate::cpu::{AUDIO_PATTERN_SIZE, AudioState};
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+// Os timers do CHIP-8 rodam a 60Hz, então o áudio é gerado em blocos de 1/60s
+pub const FRAME_RATE: u32 = 60;
+pub const DEFAULT_SAMPLE_RATE: u32 = 44_100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Waveform {
+    Square,
+    Sine,
+    Triangle,
+}
+
+impl FromStr for Waveform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "square" => Ok(Waveform::Square),
+            "sine" => Ok(Waveform::Sine),
+            "triangle" => Ok(Waveform::Triangle),
+            _ => Err(format!(
+                "unknown waveform '{}' (expected square, sine or triangle)",
+                s
+            )),
+        }
+    }
+}
+
+impl Waveform {
+    // Valor da onda em [-1, 1] para uma fase em [0, 1)
+    fn sample(self, phase: f32) -> f32 {
+        match self {
+            Waveform::Square => {
+                if phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Waveform::Sine => (phase * std::f32::consts::TAU).sin(),
+            Waveform::Triangle => 4.0 * (phase - 0.5).abs() - 1.0,
+        }
+    }
+}
+
+// Configuração do beep tocado enquanto o sound_timer é maior que zero
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ToneSettings {
+    pub frequency: f32,
+    pub waveform: Waveform,
+    // Volume entre 0.0 e 1.0
+    pub volume: f32,
+    pub muted: bool,
+}
+
+impl Default for ToneSettings {
+    fn default() -> Self {
+        ToneSettings {
+            frequency: 440.0,
+            waveform: Waveform::Square,
+            volume: 0.25,
+            muted: false,
+        }
+    }
+}
+
+// Destino das amostras geradas: caixa de som, arquivo WAV ou nada
+pub trait AudioSink {
+    fn sample_rate(&self) -> u32;
+
+    // Amostras mono em [-1, 1]
+    fn write(&mut self, samples: &[f32]) -> io::Result<()>;
+
+    // Chamado ao encerrar a emulação, para sinks que precisam fechar o arquivo
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Descarta o áudio, usado quando não há backend de som disponível
+pub struct NullSink {
+    sample_rate: u32,
+}
+
+impl NullSink {
+    pub fn new(sample_rate: u32) -> Self {
+        NullSink { sample_rate }
+    }
+}
+
+impl AudioSink for NullSink {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn write(&mut self, _samples: &[f32]) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+const PATTERN_BITS: f64 = (AUDIO_PATTERN_SIZE * 8) as f64;
+
+// Toca o padrão de 1 bit do XO-CHIP. A posição é contada em bits e continua de um quadro para o
+// outro, então a reprodução não depende de quantas amostras cabem em cada quadro
+#[derive(Default)]
+pub struct PatternPlayer {
+    position: f64,
+}
+
+impl PatternPlayer {
+    // Taxa de reprodução em bits por segundo: 4000 * 2^((pitch - 64) / 48)
+    pub fn playback_rate(pitch: u8) -> f64 {
+        4000.0 * 2f64.powf((pitch as f64 - 64.0) / 48.0)
+    }
+
+    // Gera `count` amostras. Cada amostra de saída é a média do sinal de 1 bit no intervalo que
+    // ela cobre (filtro de caixa), o que evita o aliasing de simplesmente pegar o bit mais próximo
+    // quando a taxa do padrão não bate com a do host
+    fn render(
+        &mut self,
+        pattern: &[u8; AUDIO_PATTERN_SIZE],
+        pitch: u8,
+        sample_rate: u32,
+        amplitude: f32,
+        count: usize,
+        out: &mut Vec<f32>,
+    ) {
+        let step = Self::playback_rate(pitch) / sample_rate as f64;
+        let bit = |index: usize| pattern[index / 8] & (0x80 >> (index % 8)) != 0;
+
+        for _ in 0..count {
+            let mut remaining = step;
+            let mut high = 0.0;
+            while remaining > 0.0 {
+                let index = self.position as usize;
+                let span = (index as f64 + 1.0 - self.position).min(remaining);
+                if bit(index) {
+                    high += span;
+                }
+                self.position += span;
+                remaining -= span;
+                if self.position >= PATTERN_BITS {
+                    self.position -= PATTERN_BITS;
+                }
+            }
+            let level = (2.0 * high / step - 1.0) as f32;
+            out.push(level * amplitude);
+        }
+    }
+}
+
+// Gera o som quadro a quadro: o beep configurável ou, se a ROM carregou um padrão com F002, o
+// padrão do XO-CHIP. A quantidade de amostras por quadro é calculada a partir do total
+// acumulado, então taxas que não dividem por 60 (ex: 22050Hz) não perdem amostras com o tempo
+pub struct Beeper {
+    pub tone: ToneSettings,
+    sample_rate: u32,
+    phase: f32,
+    pattern: PatternPlayer,
+    frames: u64,
+    buffer: Vec<f32>,
+}
+
+impl Beeper {
+    pub fn new(tone: ToneSettings, sample_rate: u32) -> Self {
+        Beeper {
+            tone,
+            sample_rate,
+            phase: 0.0,
+            pattern: PatternPlayer::default(),
+            frames: 0,
+            buffer: Vec::new(),
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    // Quantas amostras o próximo quadro deve ter
+    pub fn samples_in_next_frame(&self) -> usize {
+        let rate = self.sample_rate as u64;
+        let frame_rate = FRAME_RATE as u64;
+        ((self.frames + 1) * rate / frame_rate - self.frames * rate / frame_rate) as usize
+    }
+
+    fn amplitude(&self, active: bool) -> f32 {
+        if active && !self.tone.muted {
+            self.tone.volume.clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+
+    // Gera 1/60s do beep. `active` normalmente é `chip8.sound_active()` no início do quadro
+    pub fn render_frame(&mut self, active: bool) -> &[f32] {
+        let count = self.samples_in_next_frame();
+        self.frames += 1;
+
+        self.buffer.clear();
+        let step = self.tone.frequency / self.sample_rate as f32;
+        let amplitude = self.amplitude(active);
+        for _ in 0..count {
+            self.buffer
+                .push(self.tone.waveform.sample(self.phase) * amplitude);
+            self.phase = (self.phase + step).fract();
+        }
+
+        &self.buffer
+    }
+
+    // Gera 1/60s de áudio a partir de `chip8.audio_state()`, tocando o padrão do XO-CHIP quando
+    // houver um carregado
+    pub fn render(&mut self, state: &AudioState) -> &[f32] {
+        let Some(pattern) = &state.pattern else {
+            return self.render_frame(state.active);
+        };
+
+        let count = self.samples_in_next_frame();
+        self.frames += 1;
+
+        self.buffer.clear();
+        if state.active {
+            let amplitude = self.amplitude(true);
+            self.pattern.render(
+                pattern,
+                state.pitch,
+                self.sample_rate,
+                amplitude,
+                count,
+                &mut self.buffer,
+            );
+        } else {
+            self.buffer.resize(count, 0.0);
+        }
+
+        &self.buffer
+    }
+}
+
+// Grava o áudio num arquivo WAV PCM de 16 bits mono. Útil para conferir o som em testes sem
+// hardware de áudio. O cabeçalho só tem os tamanhos corretos depois de chamar `finish()`
+pub struct WavSink<W: Write + Seek> {
+    writer: W,
+    sample_rate: u32,
+    samples_written: u32,
+}
+
+impl WavSink<BufWriter<File>> {
+    pub fn create<P: AsRef<Path>>(path: P, sample_rate: u32) -> io::Result<Self> {
+        WavSink::new(BufWriter::new(File::create(path)?), sample_rate)
+    }
+}
+
+impl<W: Write + Seek> WavSink<W> {
+    pub fn new(mut writer: W, sample_rate: u32) -> io::Result<Self> {
+        write_wav_header(&mut writer, sample_rate, 0)?;
+        Ok(WavSink {
+            writer,
+            sample_rate,
+            samples_written: 0,
+        })
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write + Seek> AudioSink for WavSink<W> {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn write(&mut self, samples: &[f32]) -> io::Result<()> {
+        for &sample in samples {
+            let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+            self.writer.write_all(&value.to_le_bytes())?;
+        }
+        self.samples_written += samples.len() as u32;
+        Ok(())
+    }
+
+    // Reescreve o cabeçalho com os tamanhos atuais. Pode ser chamado mais de uma vez
+    fn finish(&mut self) -> io::Result<()> {
+        self.writer.seek(SeekFrom::Start(0))?;
+        write_wav_header(&mut self.writer, self.sample_rate, self.samples_written)?;
+        self.writer.seek(SeekFrom::End(0))?;
+        self.writer.flush()
+    }
+}
+
+fn write_wav_header<W: Write>(writer: &mut W, sample_rate: u32, samples: u32) -> io::Result<()> {
+    const CHANNELS: u16 = 1;
+    const BITS_PER_SAMPLE: u16 = 16;
+    let block_align = CHANNELS * BITS_PER_SAMPLE / 8;
+    let data_size = samples * block_align as u32;
+
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(36 + data_size).to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&16u32.to_le_bytes())?;
+    writer.write_all(&1u16.to_le_bytes())?; // PCM
+    writer.write_all(&CHANNELS.to_le_bytes())?;
+    writer.write_all(&sample_rate.to_le_bytes())?;
+    writer.write_all(&(sample_rate * block_align as u32).to_le_bytes())?;
+    writer.write_all(&block_align.to_le_bytes())?;
+    writer.write_all(&BITS_PER_SAMPLE.to_le_bytes())?;
+    writer.write_all(b"data")?;
+    writer.write_all(&data_size.to_le_bytes())?;
+    Ok(())
+}
+
+#[cfg(feature = "audio")]
+pub use self::device::CpalSink;
+
+// Saída na placa de som via cpal. Fica atrás da feature `audio` porque no Linux ela depende dos
+// headers da ALSA, que nem toda máquina tem instalados
+#[cfg(feature = "audio")]
+mod device {
+    use super::AudioSink;
+    use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+    use std::collections::VecDeque;
+    use std::io;
+    use std::sync::{Arc, Mutex};
+
+    pub struct CpalSink {
+        queue: Arc<Mutex<VecDeque<f32>>>,
+        sample_rate: u32,
+        // O stream para de tocar quando é dropado
+        _stream: cpal::Stream,
+    }
+
+    fn to_io_error<E: std::fmt::Display>(e: E) -> io::Error {
+        io::Error::other(e.to_string())
+    }
+
+    impl CpalSink {
+        pub fn open() -> io::Result<Self> {
+            let host = cpal::default_host();
+            let device = host
+                .default_output_device()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no audio output device"))?;
+            let supported = device.default_output_config().map_err(to_io_error)?;
+            if supported.sample_format() != cpal::SampleFormat::F32 {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!("unsupported sample format {}", supported.sample_format()),
+                ));
+            }
+            let config: cpal::StreamConfig = supported.into();
+            let channels = config.channels as usize;
+            let sample_rate = config.sample_rate.0;
+
+            let queue = Arc::new(Mutex::new(VecDeque::new()));
+            let consumer = Arc::clone(&queue);
+            let stream = device
+                .build_output_stream(
+                    &config,
+                    move |data: &mut [f32], _| {
+                        let mut queue = consumer.lock().unwrap();
+                        for frame in data.chunks_mut(channels) {
+                            let sample = queue.pop_front().unwrap_or(0.0);
+                            frame.iter_mut().for_each(|s| *s = sample);
+                        }
+                    },
+                    |e| eprintln!("Audio stream error: {}", e),
+                    None,
+                )
+                .map_err(to_io_error)?;
+            stream.play().map_err(to_io_error)?;
+
+            Ok(CpalSink {
+                queue,
+                sample_rate,
+                _stream: stream,
+            })
+        }
+    }
+
+    impl AudioSink for CpalSink {
+        fn sample_rate(&self) -> u32 {
+            self.sample_rate
+        }
+
+        fn write(&mut self, samples: &[f32]) -> io::Result<()> {
+            let mut queue = self.queue.lock().unwrap();
+            queue.extend(samples);
+            // Se o emulador estiver adiantado em relação à placa de som, descarta o excesso para
+            // a latência não crescer (no máximo 100ms na fila)
+            let max = self.sample_rate as usize / 10;
+            if queue.len() > max {
+                let excess = queue.len() - max;
+                queue.drain(..excess);
+            }
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,338 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+// Screenshots em PNG, animações em GIF ou APNG e dump de vídeo em RGB cru ou y4m. Os quadros
+// chegam como buffers 0x00RRGGBB (os mesmos que vão para a janela) e cada quadro enviado vale 1/60 s
+
+// Um quadro que se repete vira um só quadro mais longo, mas sem passar disso (o atraso do GIF e
+// do APNG é um u16)
+const MAX_REPEAT: u32 = 600;
+
+fn to_rgb(frame: &[u32]) -> Vec<u8> {
+    frame
+        .iter()
+        .flat_map(|&c| [(c >> 16) as u8, (c >> 8) as u8, c as u8])
+        .collect()
+}
+
+pub fn write_png<W: Write>(
+    writer: W,
+    frame: &[u32],
+    width: usize,
+    height: usize,
+) -> io::Result<()> {
+    let mut encoder = png::Encoder::new(writer, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&to_rgb(frame))?;
+    writer.finish()?;
+    Ok(())
+}
+
+pub fn save_png<P: AsRef<Path>>(
+    path: P,
+    frame: &[u32],
+    width: usize,
+    height: usize,
+) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    write_png(&mut file, frame, width, height)?;
+    file.flush()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordingFormat {
+    Gif,
+    Apng,
+}
+
+impl RecordingFormat {
+    // Decide pela extensão: .gif, ou .png / .apng para APNG
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<RecordingFormat> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "gif" => Some(RecordingFormat::Gif),
+            "png" | "apng" => Some(RecordingFormat::Apng),
+            _ => None,
+        }
+    }
+}
+
+enum Encoder<W: Write> {
+    // O GIF é escrito conforme os quadros chegam
+    Gif(gif::Encoder<W>),
+    // O APNG precisa do número de quadros no cabeçalho, então os quadros ficam na memória até o
+    // finish (em RGB, já sem as repetições)
+    Apng {
+        writer: W,
+        frames: Vec<(Vec<u8>, u32)>,
+    },
+}
+
+pub struct Recorder<W: Write> {
+    width: usize,
+    height: usize,
+    encoder: Encoder<W>,
+    // Último quadro recebido e por quantos quadros de 60Hz ele ficou na tela
+    pending: Option<(Vec<u32>, u32)>,
+    // Tempo já escrito no GIF, em quadros de 60Hz e em centésimos de segundo
+    elapsed_frames: u64,
+    elapsed_centis: u64,
+}
+
+impl Recorder<BufWriter<File>> {
+    pub fn create<P: AsRef<Path>>(path: P, width: usize, height: usize) -> io::Result<Self> {
+        let format = RecordingFormat::from_path(&path).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "recordings must end in .gif, .png or .apng",
+            )
+        })?;
+        let file = BufWriter::new(File::create(path)?);
+        Recorder::new(file, format, width, height)
+    }
+}
+
+impl<W: Write> Recorder<W> {
+    pub fn new(
+        writer: W,
+        format: RecordingFormat,
+        width: usize,
+        height: usize,
+    ) -> io::Result<Self> {
+        let encoder = match format {
+            RecordingFormat::Gif => {
+                let mut encoder = gif::Encoder::new(writer, width as u16, height as u16, &[])
+                    .map_err(io::Error::other)?;
+                encoder
+                    .set_repeat(gif::Repeat::Infinite)
+                    .map_err(io::Error::other)?;
+                Encoder::Gif(encoder)
+            }
+            RecordingFormat::Apng => Encoder::Apng {
+                writer,
+                frames: Vec::new(),
+            },
+        };
+        Ok(Recorder {
+            width,
+            height,
+            encoder,
+            pending: None,
+            elapsed_frames: 0,
+            elapsed_centis: 0,
+        })
+    }
+
+    pub fn push(&mut self, frame: &[u32]) -> io::Result<()> {
+        if let Some((last, count)) = &mut self.pending
+            && last.as_slice() == frame
+            && *count < MAX_REPEAT
+        {
+            *count += 1;
+            return Ok(());
+        }
+        self.flush_pending()?;
+        self.pending = Some((frame.to_vec(), 1));
+        Ok(())
+    }
+
+    // Escreve o último quadro e o final do arquivo, devolvendo o writer
+    pub fn finish(mut self) -> io::Result<W> {
+        self.flush_pending()?;
+        match self.encoder {
+            Encoder::Gif(encoder) => encoder.into_inner(),
+            Encoder::Apng { mut writer, frames } => {
+                if !frames.is_empty() {
+                    let mut encoder =
+                        png::Encoder::new(&mut writer, self.width as u32, self.height as u32);
+                    encoder.set_color(png::ColorType::Rgb);
+                    encoder.set_depth(png::BitDepth::Eight);
+                    encoder.set_animated(frames.len() as u32, 0)?;
+                    let mut png = encoder.write_header()?;
+                    for (rgb, count) in &frames {
+                        // O APNG aceita frações, então 60Hz fica exato
+                        png.set_frame_delay(*count as u16, 60)?;
+                        png.write_image_data(rgb)?;
+                    }
+                    png.finish()?;
+                }
+                Ok(writer)
+            }
+        }
+    }
+
+    fn flush_pending(&mut self) -> io::Result<()> {
+        let Some((frame, count)) = self.pending.take() else {
+            return Ok(());
+        };
+        match &mut self.encoder {
+            Encoder::Gif(encoder) => {
+                // O GIF só tem centésimos de segundo: os atrasos alternam entre 1 e 2 para que o
+                // tempo total acompanhe os 60Hz sem acumular erro
+                self.elapsed_frames += count as u64;
+                let end = (self.elapsed_frames * 100 + 30) / 60;
+                let delay = (end - self.elapsed_centis) as u16;
+                self.elapsed_centis = end;
+
+                let mut gif_frame = gif_frame(&frame, self.width, self.height);
+                gif_frame.delay = delay;
+                encoder.write_frame(&gif_frame).map_err(io::Error::other)
+            }
+            Encoder::Apng { frames, .. } => {
+                frames.push((to_rgb(&frame), count));
+                Ok(())
+            }
+        }
+    }
+}
+
+// Com até 256 cores (o normal, já que a paleta tem 4) os índices são exatos. Com mais cores
+// (mistura de quadros, por exemplo) o gif quantiza a imagem
+fn gif_frame(frame: &[u32], width: usize, height: usize) -> gif::Frame<'static> {
+    let mut colors: HashMap<u32, u8> = HashMap::new();
+    let mut palette = Vec::new();
+    let mut indices = Vec::with_capacity(frame.len());
+    for &color in frame {
+        let next = colors.len();
+        let index = match colors.get(&color) {
+            Some(&index) => index,
+            None if next < 256 => {
+                colors.insert(color, next as u8);
+                palette.extend_from_slice(&[(color >> 16) as u8, (color >> 8) as u8, color as u8]);
+                next as u8
+            }
+            None => {
+                let rgb = to_rgb(frame);
+                return gif::Frame::from_rgb_speed(width as u16, height as u16, &rgb, 10);
+            }
+        };
+        indices.push(index);
+    }
+    gif::Frame {
+        width: width as u16,
+        height: height as u16,
+        palette: Some(palette),
+        buffer: indices.into(),
+        ..gif::Frame::default()
+    }
+}
+
+// Dump sem perdas para codificar depois com ferramentas externas: cada quadro é o buffer inteiro em
+// rgb24 (3 bytes por pixel, linha por linha), sem cabeçalho nem separador, exatamente as cores da
+// tela. O ffmpeg lê com `-f rawvideo -pix_fmt rgb24 -s WxH -r 60`. Com o áudio do --wav, que tem
+// exatamente 1/60 s de amostras por quadro, os dois arquivos ficam sincronizados qualquer que seja
+// a velocidade da emulação
+pub struct RawVideoWriter<W: Write> {
+    writer: W,
+    width: usize,
+    height: usize,
+    rgb: Vec<u8>,
+}
+
+impl RawVideoWriter<BufWriter<File>> {
+    pub fn create<P: AsRef<Path>>(path: P, width: usize, height: usize) -> io::Result<Self> {
+        Ok(RawVideoWriter::new(
+            BufWriter::new(File::create(path)?),
+            width,
+            height,
+        ))
+    }
+}
+
+impl<W: Write> RawVideoWriter<W> {
+    pub fn new(writer: W, width: usize, height: usize) -> Self {
+        RawVideoWriter {
+            writer,
+            width,
+            height,
+            rgb: Vec::with_capacity(width * height * 3),
+        }
+    }
+
+    // Os argumentos do ffmpeg para ler este arquivo
+    pub fn ffmpeg_input(&self) -> String {
+        format!(
+            "-f rawvideo -pix_fmt rgb24 -s {}x{} -r 60",
+            self.width, self.height
+        )
+    }
+
+    pub fn write_frame(&mut self, frame: &[u32]) -> io::Result<()> {
+        self.rgb.clear();
+        self.rgb.extend(
+            frame
+                .iter()
+                .take(self.width * self.height)
+                .flat_map(|&c| [(c >> 16) as u8, (c >> 8) as u8, c as u8]),
+        );
+        self.writer.write_all(&self.rgb)
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+// Vídeo quase sem perdas, o extra do --dump-y4m para ferramentas que preferem y4m. O
+// y4m só carrega YUV, então cada pixel é convertido para 4:4:4 sem subamostragem, em faixa
+// completa (BT.601 do JPEG). Não há compressão nem perda de resolução de cor, mas a conversão
+// arredonda para 8 bits: voltando para RGB uma cor pode sair com um ou dois pontos de diferença em
+// algum canal. Para as cores exatas use o RawVideoWriter
+pub struct Y4mWriter<W: Write> {
+    writer: W,
+    width: usize,
+    height: usize,
+    planes: Vec<u8>,
+}
+
+impl Y4mWriter<BufWriter<File>> {
+    pub fn create<P: AsRef<Path>>(path: P, width: usize, height: usize) -> io::Result<Self> {
+        Y4mWriter::new(BufWriter::new(File::create(path)?), width, height)
+    }
+}
+
+impl<W: Write> Y4mWriter<W> {
+    pub fn new(mut writer: W, width: usize, height: usize) -> io::Result<Self> {
+        writeln!(
+            writer,
+            "YUV4MPEG2 W{} H{} F60:1 Ip A1:1 C444 XCOLORRANGE=FULL",
+            width, height
+        )?;
+        Ok(Y4mWriter {
+            writer,
+            width,
+            height,
+            planes: vec![0; width * height * 3],
+        })
+    }
+
+    pub fn write_frame(&mut self, frame: &[u32]) -> io::Result<()> {
+        let size = self.width * self.height;
+        let (y, chroma) = self.planes.split_at_mut(size);
+        let (u, v) = chroma.split_at_mut(size);
+        for (i, &color) in frame.iter().take(size).enumerate() {
+            let r = ((color >> 16) & 0xFF) as f32;
+            let g = ((color >> 8) & 0xFF) as f32;
+            let b = (color & 0xFF) as f32;
+            y[i] = (0.299 * r + 0.587 * g + 0.114 * b).round() as u8;
+            u[i] = (128.0 - 0.168736 * r - 0.331264 * g + 0.5 * b)
+                .round()
+                .clamp(0.0, 255.0) as u8;
+            v[i] = (128.0 + 0.5 * r - 0.418688 * g - 0.081312 * b)
+                .round()
+                .clamp(0.0, 255.0) as u8;
+        }
+        self.writer.write_all(b"FRAME\n")?;
+        self.writer.write_all(&self.planes)
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
@@ -0,0 +1,108 @@
+use crate::octo;
+use crate::romdb::RomEntry;
+use serde::Deserialize;
+use std::io::Cursor;
+
+// Cartuchos do Octo: GIFs com o programa e as opções escondidos nos 2 bits mais baixos do índice
+// de cor de cada pixel (4 pixels por byte, o mais significativo primeiro, somando todos os
+// quadros). Os 4 primeiros bytes dizem o tamanho, em big-endian, de um JSON como
+// {"program": "...", "options": {...}}
+//
+// O programa é o código-fonte do Octo, montado pelo assembler de `octo.rs`. Cartuchos que usam
+// macros ou outras diretivas que ele não conhece são recusados com um erro dizendo isso; esses
+// precisam ser exportados do Octo como .ch8
+
+pub fn is_cartridge(bytes: &[u8]) -> bool {
+    bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a")
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Cartridge {
+    pub program: String,
+    pub options: OctoOptions,
+}
+
+// Opções do Octo que têm equivalente aqui; as outras são ignoradas
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct OctoOptions {
+    pub tickrate: Option<usize>,
+    pub background_color: Option<String>,
+    pub fill_color: Option<String>,
+    pub fill_color2: Option<String>,
+    pub blend_color: Option<String>,
+    pub shift_quirks: Option<bool>,
+    pub load_store_quirks: Option<bool>,
+    pub clip_quirks: Option<bool>,
+    pub jump_quirks: Option<bool>,
+    pub logic_quirks: Option<bool>,
+    pub v_blank_quirks: Option<bool>,
+    // 3584 no CHIP-8, 3216 no SUPER-CHIP e 65024 no XO-CHIP
+    pub max_size: Option<usize>,
+}
+
+impl Cartridge {
+    pub fn decode(gif: &[u8]) -> Result<Cartridge, String> {
+        let mut decoder = gif::DecodeOptions::new()
+            .read_info(Cursor::new(gif))
+            .map_err(|e| e.to_string())?;
+        let mut indices = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().map_err(|e| e.to_string())? {
+            indices.extend_from_slice(&frame.buffer);
+        }
+        let bytes: Vec<u8> = indices
+            .chunks_exact(4)
+            .map(|p| p.iter().fold(0, |byte, &index| (byte << 2) | (index & 3)))
+            .collect();
+
+        let size = match bytes.get(..4) {
+            Some(&[a, b, c, d]) => u32::from_be_bytes([a, b, c, d]) as usize,
+            _ => return Err("image is too small to be an Octo cartridge".to_string()),
+        };
+        let json = bytes
+            .get(4..4 + size)
+            .ok_or("image is not an Octo cartridge (payload is truncated)")?;
+        serde_json::from_slice(json).map_err(|e| format!("invalid cartridge payload: {}", e))
+    }
+
+    // Monta o código-fonte do Octo
+    pub fn rom(&self) -> Result<Vec<u8>, String> {
+        octo::assemble(&self.program)
+    }
+
+    // As opções viram uma entrada do banco de ROMs
+    pub fn settings(&self) -> RomEntry {
+        let options = &self.options;
+        let platform = options.max_size.map(|size| match size {
+            0..=3216 => "schip",
+            3217..=3584 => "chip8",
+            _ => "xochip",
+        });
+        // Só quando as 4 cores estão definidas (fundo, plano 1, plano 2, os dois planos)
+        let colors = [
+            &options.background_color,
+            &options.fill_color,
+            &options.fill_color2,
+            &options.blend_color,
+        ]
+        .into_iter()
+        .cloned()
+        .collect::<Option<Vec<String>>>();
+
+        let mut entry = RomEntry {
+            platform: platform.map(str::to_string),
+            instructions_per_frame: options.tickrate,
+            colors,
+            ..RomEntry::default()
+        };
+        let quirks = &mut entry.quirks;
+        quirks.shift_vx = options.shift_quirks;
+        quirks.memory_increment_i = options.load_store_quirks.map(|quirk| !quirk);
+        quirks.clip_sprites = options.clip_quirks;
+        quirks.jump_vx = options.jump_quirks;
+        quirks.vf_reset = options.logic_quirks;
+        quirks.display_wait = options.v_blank_quirks;
+        entry
+    }
+}
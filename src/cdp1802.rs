@@ -0,0 +1,315 @@
+// CPU RCA CDP1802 (COSMAC), a do COSMAC VIP. Serve ao modelo da máquina inteira em src/vip.rs,
+// que roda o interpretador CHIP-8 original como código do 1802.
+//
+// O 1802 não tem um registrador de pc fixo: qualquer um dos 16 registradores de 16 bits pode ser
+// o contador de programa (o que P aponta) ou o ponteiro de dados (o que X aponta). Trocar de
+// P é o jeito de chamar subrotinas, e a interrupção sempre passa a executar por R1 com X = R2.
+// Toda instrução gasta 2 ciclos de máquina (busca e execução); as de desvio longo, 3.
+
+// O que fica em volta da CPU: memória, as portas de E/S (N = 1 a 7) e as flags externas EF1 a EF4
+pub trait Bus {
+    fn read(&mut self, addr: u16) -> u8;
+    fn write(&mut self, addr: u16, value: u8);
+    // OUT N: o byte de M(R(X)) vai para o barramento
+    fn output(&mut self, port: u8, value: u8);
+    // INP N: o valor do barramento vai para M(R(X)) e para D
+    fn input(&mut self, port: u8) -> u8;
+    // EF1 a EF4 (1 a 4) ativa, o que faz B1..B4 desviarem
+    fn flag(&self, ef: u8) -> bool;
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Cdp1802 {
+    pub r: [u16; 16],
+    // Registrador usado como pc
+    pub p: u8,
+    // Registrador usado como ponteiro de dados
+    pub x: u8,
+    // Acumulador
+    pub d: u8,
+    // Carry / "não houve empréstimo" na subtração
+    pub df: bool,
+    // X e P salvos pela interrupção (X no nibble alto)
+    pub t: u8,
+    // Interrupções habilitadas
+    pub ie: bool,
+    // Saída Q; no VIP liga o beep
+    pub q: bool,
+    // Parada pelo IDL até o próximo DMA ou interrupção
+    pub idle: bool,
+}
+
+impl Cdp1802 {
+    // Estado depois do reset: tudo zerado e as interrupções habilitadas, começando em R0
+    pub fn new() -> Self {
+        Cdp1802 {
+            ie: true,
+            ..Cdp1802::default()
+        }
+    }
+
+    pub fn pc(&self) -> u16 {
+        self.r[self.p as usize]
+    }
+
+    fn fetch(&mut self, bus: &mut impl Bus) -> u8 {
+        let p = self.p as usize;
+        let byte = bus.read(self.r[p]);
+        self.r[p] = self.r[p].wrapping_add(1);
+        byte
+    }
+
+    fn rx(&self) -> u16 {
+        self.r[self.x as usize]
+    }
+
+    // Interrupção aceita: salva X e P em T e passa a executar por R1, com X = R2. Gasta 1 ciclo
+    pub fn interrupt(&mut self) -> u32 {
+        self.t = (self.x << 4) | self.p;
+        self.p = 1;
+        self.x = 2;
+        self.ie = false;
+        self.idle = false;
+        1
+    }
+
+    // Ciclo de DMA de saída: o byte em R0 vai para o periférico (o CDP1861) e R0 avança
+    pub fn dma_out(&mut self, bus: &mut impl Bus) -> u8 {
+        let byte = bus.read(self.r[0]);
+        self.r[0] = self.r[0].wrapping_add(1);
+        self.idle = false;
+        byte
+    }
+
+    // D + valor + carry, com o vai-um em DF
+    fn add(&mut self, value: u8, carry: bool) {
+        let sum = self.d as u16 + value as u16 + carry as u16;
+        self.d = sum as u8;
+        self.df = sum > 0xFF;
+    }
+
+    // a - b - empréstimo, com DF = 1 quando não houve empréstimo
+    fn subtract(&mut self, a: u8, b: u8, borrow: bool) {
+        let difference = a as i16 - b as i16 - borrow as i16;
+        self.d = difference as u8;
+        self.df = difference >= 0;
+    }
+
+    // Executa uma instrução e retorna os ciclos de máquina gastos. Parado no IDL não faz nada
+    pub fn step(&mut self, bus: &mut impl Bus) -> u32 {
+        if self.idle {
+            return 1;
+        }
+        let opcode = self.fetch(bus);
+        let n = opcode & 0xF;
+        let rn = n as usize;
+        match opcode >> 4 {
+            // IDL espera um DMA ou uma interrupção; 0N com N != 0 é LDN
+            0x0 if n == 0 => self.idle = true,
+            0x0 => self.d = bus.read(self.r[rn]),
+            0x1 => self.r[rn] = self.r[rn].wrapping_add(1),
+            0x2 => self.r[rn] = self.r[rn].wrapping_sub(1),
+            // Desvios curtos: o byte seguinte troca só o byte baixo do pc
+            0x3 => {
+                let taken = match n {
+                    0x0 => true,
+                    0x1 => self.q,
+                    0x2 => self.d == 0,
+                    0x3 => self.df,
+                    0x4..=0x7 => bus.flag(n - 3),
+                    // SKP pula o byte sem desviar
+                    0x8 => false,
+                    0x9 => !self.q,
+                    0xA => self.d != 0,
+                    0xB => !self.df,
+                    _ => !bus.flag(n - 0xB),
+                };
+                let p = self.p as usize;
+                if taken {
+                    let low = bus.read(self.r[p]);
+                    self.r[p] = (self.r[p] & 0xFF00) | low as u16;
+                } else {
+                    self.r[p] = self.r[p].wrapping_add(1);
+                }
+            }
+            0x4 => {
+                self.d = bus.read(self.r[rn]);
+                self.r[rn] = self.r[rn].wrapping_add(1);
+            }
+            0x5 => bus.write(self.r[rn], self.d),
+            0x6 => match n {
+                // IRX
+                0x0 => {
+                    let x = self.x as usize;
+                    self.r[x] = self.r[x].wrapping_add(1);
+                }
+                0x1..=0x7 => {
+                    let value = bus.read(self.rx());
+                    bus.output(n, value);
+                    let x = self.x as usize;
+                    self.r[x] = self.r[x].wrapping_add(1);
+                }
+                // 68 não existe no 1802 (é o prefixo das instruções do 1804/1805)
+                0x8 => {}
+                _ => {
+                    let value = bus.input(n - 8);
+                    bus.write(self.rx(), value);
+                    self.d = value;
+                }
+            },
+            0x7 => match n {
+                // RET e DIS: X e P voltam da pilha
+                0x0 | 0x1 => {
+                    let value = bus.read(self.rx());
+                    let x = self.x as usize;
+                    self.r[x] = self.r[x].wrapping_add(1);
+                    self.x = value >> 4;
+                    self.p = value & 0xF;
+                    self.ie = n == 0;
+                }
+                // LDXA
+                0x2 => {
+                    self.d = bus.read(self.rx());
+                    let x = self.x as usize;
+                    self.r[x] = self.r[x].wrapping_add(1);
+                }
+                // STXD
+                0x3 => {
+                    bus.write(self.rx(), self.d);
+                    let x = self.x as usize;
+                    self.r[x] = self.r[x].wrapping_sub(1);
+                }
+                // ADC
+                0x4 => {
+                    let value = bus.read(self.rx());
+                    self.add(value, self.df);
+                }
+                // SDB
+                0x5 => {
+                    let value = bus.read(self.rx());
+                    self.subtract(value, self.d, !self.df);
+                }
+                // SHRC: o carry entra pela esquerda
+                0x6 => {
+                    let carry = self.df;
+                    self.df = self.d & 1 != 0;
+                    self.d = (self.d >> 1) | ((carry as u8) << 7);
+                }
+                // SMB
+                0x7 => {
+                    let value = bus.read(self.rx());
+                    self.subtract(self.d, value, !self.df);
+                }
+                // SAV
+                0x8 => bus.write(self.rx(), self.t),
+                // MARK: guarda X e P em M(R2) antes de uma chamada
+                0x9 => {
+                    self.t = (self.x << 4) | self.p;
+                    bus.write(self.r[2], self.t);
+                    self.x = self.p;
+                    self.r[2] = self.r[2].wrapping_sub(1);
+                }
+                0xA => self.q = false,
+                0xB => self.q = true,
+                // ADCI
+                0xC => {
+                    let value = self.fetch(bus);
+                    self.add(value, self.df);
+                }
+                // SDBI
+                0xD => {
+                    let value = self.fetch(bus);
+                    self.subtract(value, self.d, !self.df);
+                }
+                // SHLC: o carry entra pela direita
+                0xE => {
+                    let carry = self.df;
+                    self.df = self.d & 0x80 != 0;
+                    self.d = (self.d << 1) | carry as u8;
+                }
+                // SMBI
+                _ => {
+                    let value = self.fetch(bus);
+                    self.subtract(self.d, value, !self.df);
+                }
+            },
+            0x8 => self.d = self.r[rn] as u8,
+            0x9 => self.d = (self.r[rn] >> 8) as u8,
+            0xA => self.r[rn] = (self.r[rn] & 0xFF00) | self.d as u16,
+            0xB => self.r[rn] = (self.r[rn] & 0x00FF) | (self.d as u16) << 8,
+            // Desvios e skips longos, os únicos de 3 ciclos
+            0xC => {
+                let p = self.p as usize;
+                let condition = match n & 0x3 {
+                    0x0 => true,
+                    0x1 => self.q,
+                    0x2 => self.d == 0,
+                    _ => self.df,
+                };
+                match n {
+                    // NOP
+                    0x4 => {}
+                    // Desvios: LBR, LBQ, LBZ, LBDF e os contrários LBNQ, LBNZ, LBNF (C8 é o
+                    // LSKP, que pula sempre)
+                    0x0..=0x3 | 0x9..=0xB => {
+                        if condition == (n < 0x8) {
+                            let high = bus.read(self.r[p]);
+                            let low = bus.read(self.r[p].wrapping_add(1));
+                            self.r[p] = (high as u16) << 8 | low as u16;
+                        } else {
+                            self.r[p] = self.r[p].wrapping_add(2);
+                        }
+                    }
+                    // Skips: LSNQ, LSNZ, LSNF, LSKP, LSIE, LSQ, LSZ, LSDF
+                    _ => {
+                        let skip = match n {
+                            0x5 => !self.q,
+                            0x6 => self.d != 0,
+                            0x7 => !self.df,
+                            0x8 => true,
+                            0xC => self.ie,
+                            0xD => self.q,
+                            0xE => self.d == 0,
+                            _ => self.df,
+                        };
+                        if skip {
+                            self.r[p] = self.r[p].wrapping_add(2);
+                        }
+                    }
+                }
+                return 3;
+            }
+            0xD => self.p = n,
+            0xE => self.x = n,
+            // Lógica e aritmética com M(R(X)) (F0 a F7) ou com o byte seguinte (F8 a FF)
+            _ => {
+                let value = match n {
+                    0x6 | 0xE => 0,
+                    0x0..=0x7 => bus.read(self.rx()),
+                    _ => self.fetch(bus),
+                };
+                match n & 0x7 {
+                    0x0 => self.d = value,
+                    0x1 => self.d |= value,
+                    0x2 => self.d &= value,
+                    0x3 => self.d ^= value,
+                    0x4 => self.add(value, false),
+                    // SD / SDI: valor - D
+                    0x5 => self.subtract(value, self.d, false),
+                    // SHR e SHL não leem nada
+                    0x6 if n == 0x6 => {
+                        self.df = self.d & 1 != 0;
+                        self.d >>= 1;
+                    }
+                    0x6 => {
+                        self.df = self.d & 0x80 != 0;
+                        self.d <<= 1;
+                    }
+                    // SM / SMI: D - valor
+                    _ => self.subtract(self.d, value, false),
+                }
+            }
+        }
+        2
+    }
+}
@@ -0,0 +1,67 @@
+use crate::cpu::{VIDEO_HEIGHT, VIDEO_WIDTH};
+
+// Placa de cor VP-590 do CHIP-8X. A cor não fica nos pixels: a placa tem uma memória separada com
+// uma cor de frente para cada bloco de 8 pixels de largura por 1 linha, e uma cor de fundo única
+// para a tela toda. O Dxyn continua desenhando só em preto e branco; os pixels acesos aparecem com
+// a cor do bloco onde caem e os apagados com a cor de fundo
+
+pub const COLOR_COLUMNS: usize = VIDEO_WIDTH / 8;
+// O Bxy0 pinta zonas de 4 linhas
+pub const ZONE_LINES: usize = 4;
+
+// Cores de frente (3 bits: vermelho, azul e verde) e as quatro cores de fundo, na ordem em que o
+// 02A0 passa por elas
+pub const FOREGROUND_COLORS: [u32; 8] = [
+    0x000000, 0xFF0000, 0x0000FF, 0xFF00FF, 0x00FF00, 0xFFFF00, 0x00FFFF, 0xFFFFFF,
+];
+pub const BACKGROUND_COLORS: [u32; 4] = [0x000080, 0x000000, 0x008000, 0x800000];
+
+// Ao ligar a máquina tudo fica vermelho sobre azul
+const DEFAULT_FOREGROUND: u8 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColorMap {
+    // Índice em BACKGROUND_COLORS
+    pub background: u8,
+    // Índice em FOREGROUND_COLORS de cada bloco, por linha
+    pub zones: [[u8; COLOR_COLUMNS]; VIDEO_HEIGHT],
+}
+
+impl Default for ColorMap {
+    fn default() -> Self {
+        ColorMap {
+            background: 0,
+            zones: [[DEFAULT_FOREGROUND; COLOR_COLUMNS]; VIDEO_HEIGHT],
+        }
+    }
+}
+
+impl ColorMap {
+    // 02A0: próxima cor de fundo
+    pub fn next_background(&mut self) {
+        self.background = (self.background + 1) % BACKGROUND_COLORS.len() as u8;
+    }
+
+    // Pinta as colunas `columns` (blocos de 8 pixels) das linhas `lines`, dando a volta na tela
+    pub fn fill(
+        &mut self,
+        columns: impl Iterator<Item = usize> + Clone,
+        lines: impl Iterator<Item = usize>,
+        color: u8,
+    ) {
+        for y in lines {
+            for x in columns.clone() {
+                self.zones[y % VIDEO_HEIGHT][x % COLOR_COLUMNS] = color & 0x7;
+            }
+        }
+    }
+
+    // Cor do pixel (x, y) aceso ou apagado
+    pub fn color(&self, x: usize, y: usize, on: bool) -> u32 {
+        if on {
+            FOREGROUND_COLORS[self.zones[y][x / 8] as usize]
+        } else {
+            BACKGROUND_COLORS[self.background as usize]
+        }
+    }
+}
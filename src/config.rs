@@ -0,0 +1,199 @@
+use crate::flicker::FlickerMode;
+use crate::keymap::Keymap;
+use crate::palette::{Palette, PaletteError};
+use crate::postfx::PostFxSettings;
+use crate::quirks::QuirkOverrides;
+use crate::render::PixelStyle;
+use crate::romdb::{RomDatabase, RomEntry};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// Arquivo de configuração do frontend (chip8.toml). Exemplo:
+//
+// [display]
+// palette = "sunset"
+// pixel_style = "rounded"
+// flicker = "blend:3"
+//
+// [keymap]
+// preset = "azerty"
+//
+// [keymap.keys]
+// "5" = ["W", "Up"]
+//
+// [quirks]
+// key_wait_release = false
+//
+// [roms.1ba58656810b67fd131eb9af3e3987863bf26c90]
+// title = "IBM Logo"
+// platform = "chip8"
+// instructions_per_frame = 20
+//
+// [postfx]
+// scanlines = true
+// bloom_radius = 6
+//
+// [palettes.sunset]
+// colors = ["#1A0F2E", "#FF7F50", "#FFD700", "#FFFFFF"]
+pub const DEFAULT_CONFIG_PATH: &str = "chip8.toml";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub display: DisplayConfig,
+    pub keymap: KeymapConfig,
+    // Efeitos de CRT, todos desligados por padrão
+    pub postfx: PostFxSettings,
+    // Quirks que substituem as do perfil da plataforma em todas as ROMs
+    pub quirks: QuirkOverrides,
+    // Entradas do banco de ROMs, pelo SHA-1 do arquivo (veja src/romdb.toml)
+    pub roms: HashMap<String, RomEntry>,
+    // Paletas definidas pelo usuário, com 2 ou 4 cores
+    pub palettes: HashMap<String, CustomPalette>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DisplayConfig {
+    pub palette: Option<String>,
+    pub pixel_style: Option<String>,
+    // Redução de piscada: "off", "or", "blend[:N]" ou "decay[:P]"
+    pub flicker: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeymapConfig {
+    // qwerty, azerty, qwertz, dvorak ou numpad
+    pub preset: Option<String>,
+    // Tecla do CHIP-8 ("0" a "F") -> teclas do host, substituindo as do preset
+    pub keys: HashMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CustomPalette {
+    pub colors: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    Palette(PaletteError),
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "{}", e),
+            ConfigError::Parse(e) => write!(f, "{}", e),
+            ConfigError::Palette(e) => write!(f, "{}", e),
+            ConfigError::Invalid(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<io::Error> for ConfigError {
+    fn from(e: io::Error) -> Self {
+        ConfigError::Io(e)
+    }
+}
+
+impl From<toml::de::Error> for ConfigError {
+    fn from(e: toml::de::Error) -> Self {
+        ConfigError::Parse(e)
+    }
+}
+
+impl From<PaletteError> for ConfigError {
+    fn from(e: PaletteError) -> Self {
+        ConfigError::Palette(e)
+    }
+}
+
+impl Config {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Config, ConfigError> {
+        Config::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> Result<Config, ConfigError> {
+        Ok(toml::from_str(text)?)
+    }
+
+    // Procura primeiro entre as paletas do usuário e depois nos presets
+    pub fn palette(&self, name: &str) -> Result<Palette, ConfigError> {
+        if let Some(custom) = self.palettes.get(name) {
+            return Ok(Palette::from_hex(&custom.colors)?);
+        }
+        Palette::preset(name).ok_or_else(|| PaletteError::UnknownPalette(name.to_string()).into())
+    }
+
+    // Paleta escolhida em [display], ou a clássica
+    pub fn display_palette(&self) -> Result<Palette, ConfigError> {
+        match &self.display.palette {
+            Some(name) => self.palette(name),
+            None => Ok(Palette::default()),
+        }
+    }
+
+    pub fn pixel_style(&self) -> Result<PixelStyle, ConfigError> {
+        match &self.display.pixel_style {
+            Some(style) => style.parse().map_err(ConfigError::Invalid),
+            None => Ok(PixelStyle::default()),
+        }
+    }
+
+    // Preset da linha de comando ou de [keymap] (qwerty por padrão), com as teclas de
+    // [keymap.keys] por cima
+    pub fn keymap(&self, preset: Option<&str>) -> Result<Keymap, ConfigError> {
+        let mut keymap = match preset.or(self.keymap.preset.as_deref()) {
+            Some(name) => Keymap::preset(name)
+                .ok_or_else(|| ConfigError::Invalid(format!("unknown keymap '{}'", name)))?,
+            None => Keymap::default(),
+        };
+        keymap
+            .bind_all(&self.keymap.keys)
+            .map_err(ConfigError::Invalid)?;
+        Ok(keymap)
+    }
+
+    // Mapeamento de uma ROM. O --keymap da linha de comando (`cli_preset`) vale sobre tudo o que a
+    // entrada do banco define, o preset e as teclas trocadas; sem ele as teclas da entrada vão por
+    // cima do preset dela ou da seção [keymap]
+    pub fn rom_keymap(
+        &self,
+        cli_preset: Option<&str>,
+        entry: &RomEntry,
+    ) -> Result<Keymap, ConfigError> {
+        if cli_preset.is_some() {
+            return self.keymap(cli_preset);
+        }
+        let mut keymap = self.keymap(entry.keymap.as_deref())?;
+        entry
+            .apply_keys(&mut keymap)
+            .map_err(ConfigError::Invalid)?;
+        Ok(keymap)
+    }
+
+    // Banco embutido com as entradas do usuário por cima
+    pub fn rom_database(&self) -> RomDatabase {
+        let mut database = RomDatabase::builtin();
+        database.extend(RomDatabase::from_entries(self.roms.clone()));
+        database
+    }
+
+    pub fn flicker_mode(&self) -> Result<FlickerMode, ConfigError> {
+        match &self.display.flicker {
+            Some(mode) => mode.parse().map_err(ConfigError::Invalid),
+            None => Ok(FlickerMode::default()),
+        }
+    }
+}
@@ -0,0 +1,1352 @@
+use crate::color::{ColorMap, ZONE_LINES};
+use crate::instruction::{Instruction, InstructionSet};
+use crate::quirks::Quirks;
+use crate::timing::{VIP_CYCLES_PER_FRAME, vip_cycles};
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+
+pub const MEMORY_SIZE: usize = 4096;
+// O XO-CHIP endereça 64K
+pub const XO_MEMORY_SIZE: usize = 0x10000;
+pub const REGISTER_COUNT: usize = 16;
+pub const STACK_SIZE: usize = 16;
+pub const KEYPAD_SIZE: usize = 16;
+pub const VIDEO_WIDTH: usize = 64;
+pub const VIDEO_HEIGHT: usize = 32;
+// Maior tela suportada, a do CHIP-10
+pub const MAX_VIDEO_WIDTH: usize = 128;
+pub const MAX_VIDEO_HEIGHT: usize = 64;
+// Planos de bits da tela. O XO-CHIP desenha em dois planos e cada pixel vira um índice de 0 a 3 na
+// paleta; as outras plataformas só usam o primeiro
+pub const PLANE_COUNT: usize = 2;
+pub const START_ADDRESS: usize = 0x200;
+// Instruções executadas por quadro de 60Hz (~600 instruções por segundo)
+pub const DEFAULT_INSTRUCTIONS_PER_FRAME: usize = 10;
+pub const AUDIO_PATTERN_SIZE: usize = 16;
+// Pitch padrão do XO-CHIP, equivale a tocar o padrão a 4000 bits por segundo
+pub const DEFAULT_PITCH: u8 = 64;
+// Registradores de flags do SUPER-CHIP (Fx75 / Fx85). No HP-48 eram 8; o XO-CHIP usa 16
+pub const FLAG_COUNT: usize = 16;
+// Onde ficam as fontes na área do interpretador, abaixo de 0x200
+pub const FONT_ADDRESS: usize = 0x000;
+pub const BIG_FONT_ADDRESS: usize = 0x050;
+// Máscara de linhas sujas com todas as linhas da tela (cabe até a de 64 linhas do CHIP-10)
+pub const ALL_ROWS: u64 = u64::MAX;
+
+// Dígitos hexadecimais de 4x5 pixels que o Fx29 aponta, 5 bytes por dígito
+const FONT: [u8; 16 * 5] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x20, 0x60, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
+
+// Fonte grande de 8x10 do Fx30. O SUPER-CHIP 1.1 só tinha de 0 a 9; de A a F são as do XO-CHIP
+const BIG_FONT: [u8; 16 * 10] = [
+    0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, // 0
+    0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF, // 1
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // 2
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 3
+    0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 5
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 6
+    0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18, // 7
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 8
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 9
+    0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
+    0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0, // F
+];
+
+// Só imprime o trace das instruções quando `trace` estiver ligado. Imprimir a cada ciclo deixa
+// o fuzzer e os testes muito lentos e enche a saída da janela e do modo sem janela
+macro_rules! trace {
+    ($self:ident, $($arg:tt)*) => {
+        if $self.trace {
+            println!($($arg)*);
+        }
+    };
+}
+
+// Erros que uma ROM pode provocar no interpretador. Em vez de dar panic o ciclo retorna o erro
+// e quem chamou decide se para a emulação
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Chip8Error {
+    // CALL com a pilha cheia
+    StackOverflow { pc: u16 },
+    // RET com a pilha vazia
+    StackUnderflow { pc: u16 },
+    // ROM maior que o espaço entre 0x200 e o fim da memória
+    RomTooLarge { size: usize, max: usize },
+}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Chip8Error::StackOverflow { pc } => write!(f, "stack overflow at {:#05X}", pc),
+            Chip8Error::StackUnderflow { pc } => write!(f, "stack underflow at {:#05X}", pc),
+            Chip8Error::RomTooLarge { size, max } => {
+                write!(f, "ROM has {} bytes but only {} fit in memory", size, max)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Chip8Error {}
+
+// Estado do som num quadro, retornado por `Chip8::audio_state()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AudioState {
+    // sound_timer > 0
+    pub active: bool,
+    pub pattern: Option<[u8; AUDIO_PATTERN_SIZE]>,
+    pub pitch: u8,
+}
+
+#[derive(Clone)]
+pub struct Chip8 {
+    // 4K memory (64K no XO-CHIP). Escrever aqui direto depois de começar a executar exige um
+    // flush_decode_cache, senão o código antigo continua no cache
+    pub memory: Vec<u8>,
+    // Cache de decodificação: a instrução que começa em cada endereço, decodificada na primeira
+    // execução e apagada quando um dos dois bytes dela é escrito
+    decoded: Vec<Option<Instruction>>,
+    // Liga o cache (é desligado nos benchmarks para comparar)
+    pub decode_cache: bool,
+    // Tabela de opcodes da variante
+    instruction_set: InstructionSet,
+
+    // 16 general purpose 8-bit registers: V0 to VF
+    pub v: [u8; REGISTER_COUNT],
+
+    // Index register (16-bit)
+    pub i: u16,
+
+    // Program Counter starts at 0x200
+    pub pc: u16,
+
+    // Stack for subrotines calls
+    pub stack: [u16; STACK_SIZE],
+    pub sp: u8,
+
+    // Flags do SUPER-CHIP: o Fx75 guarda V0..Vx aqui e o Fx85 carrega de volta
+    pub flags: [u8; FLAG_COUNT],
+
+    // Timers (decrement at 60hz)
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+
+    // Áudio do XO-CHIP: padrão de 128 bits carregado pelo F002 e pitch definido pelo Fx3A.
+    // Enquanto nenhum padrão for carregado o frontend toca o beep normal
+    pub audio_pattern: Option<[u8; AUDIO_PATTERN_SIZE]>,
+    pub pitch: u8,
+
+    // Input keypad (16 keys): true enquanto a tecla está pressionada. O frontend deve atualizar
+    // pelo set_key, que também registra as bordas de pressionar e soltar
+    pub keypad: [bool; KEYPAD_SIZE],
+    // Teclas pressionadas e soltas desde o último quadro. Assim um toque mais curto que um quadro
+    // ainda é visto pelo Ex9E e pelo Fx0A
+    key_pressed: [bool; KEYPAD_SIZE],
+    key_released: [bool; KEYPAD_SIZE],
+    // Tecla pressionada durante um Fx0A, esperando ser solta (quirk key_wait_release)
+    pub key_wait: Option<u8>,
+    // Segundo teclado do CHIP-8X, lido pelo ExF2 / ExF5
+    pub second_keypad: [bool; KEYPAD_SIZE],
+
+    // Porta de E/S do CHIP-8X e do CHIP-8E. Não há periférico ligado: o que sai pela porta fica
+    // guardado e volta na próxima leitura
+    pub io_port: u8,
+    // Um Fx4F do CHIP-8E está esperando o delay_timer chegar a zero
+    delay_wait: bool,
+
+    pub quirks: Quirks,
+
+    // Video Buffer: um array de linhas por plano, uma linha por u128, com a coluna 0 no bit
+    // width - 1 (bit 63 na tela de 64 colunas). O Dxyn desenha uma linha inteira do sprite com um
+    // shift e um XOR. Só as `height` primeiras linhas são usadas. Para ler pixel a pixel use
+    // pixel() / pixels() (aceso em algum plano) ou pixel_index() / pixel_indices() (índice na
+    // paleta).
+    //
+    // As linhas são u128 em qualquer resolução: na tela de 64 colunas os 64 bits de cima ficam
+    // zerados e os valores são os mesmos de um u64. Um tipo por resolução obrigaria o Chip8 a ser
+    // genérico ou a decidir o tipo a cada Dxyn, e o shift e o XOR de um u128 custam só duas
+    // instruções a mais
+    pub video: [[u128; MAX_VIDEO_HEIGHT]; PLANE_COUNT],
+    // Planos em que o Dxyn desenha e que o 00E0 apaga, um bit por plano (bit 0 = primeiro plano).
+    // Só o Fn01 do XO-CHIP muda; nas outras plataformas fica sempre no primeiro
+    pub planes: u8,
+    // Resolução da tela: 64x32, ou 128x64 no CHIP-10, no SUPER-CHIP e no XO-CHIP
+    width: usize,
+    height: usize,
+    // Modo de baixa resolução do SUPER-CHIP e do XO-CHIP: a tela continua com 128x64, mas os
+    // programas enxergam 64x32 e cada pixel deles ocupa 2x2. O 00FF desliga e o 00FE liga. Trocar
+    // o tamanho da tela no meio do jogo faria o frontend parar a gravação e o --dump
+    lores: bool,
+    // Cores do CHIP-8X; nas outras variantes a tela é só acesa ou apagada
+    pub colors: Option<ColorMap>,
+    // Linhas da tela alteradas desde o último take_dirty_rows, um bit por linha (bit 0 = linha 0).
+    // O frontend redesenha só essas linhas e deixa de atualizar a janela quando nada mudou
+    dirty_rows: u64,
+
+    // Quadros de 60Hz completados pelo run_frame
+    pub frame_count: u64,
+    // Um Dxyn com a quirk display_wait já rodou neste quadro; o resto do quadro fica parado
+    // esperando a interrupção de vídeo
+    display_wait: bool,
+    // Ciclos que a última instrução do quadro anterior passou do limite no run_vip_frame, e que
+    // saem do próximo quadro
+    cycle_carry: u32,
+
+    // Imprime cada instrução executada. Começa desligado; quem quiser ver (um teste ou uma
+    // ferramenta de depuração) liga depois de criar o Chip8
+    pub trace: bool,
+
+    // Gerador do Cxkk. Fica na máquina para um clone sortear os mesmos números que o original:
+    // save states e comparações entre o interpretador e o recompilador ficam reproduzíveis
+    pub rng: StdRng,
+}
+
+impl Default for Chip8 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Chip8 {
+    pub fn new() -> Self {
+        Chip8::with_memory_size(MEMORY_SIZE)
+    }
+
+    // O tamanho da memória precisa ser escolhido antes de carregar a ROM
+    pub fn with_memory_size(size: usize) -> Self {
+        let mut chip8 = Chip8 {
+            memory: vec![0; size],
+            decoded: vec![None; size],
+            decode_cache: true,
+            instruction_set: InstructionSet::Chip8,
+            v: [0; REGISTER_COUNT],
+            i: 0,
+            pc: 0x200, // CHIP-8 programs start at 0x200
+            stack: [0; STACK_SIZE],
+            sp: 0,
+            flags: [0; FLAG_COUNT],
+            delay_timer: 0,
+            sound_timer: 0,
+            audio_pattern: None,
+            pitch: DEFAULT_PITCH,
+            keypad: [false; KEYPAD_SIZE],
+            key_pressed: [false; KEYPAD_SIZE],
+            key_released: [false; KEYPAD_SIZE],
+            key_wait: None,
+            second_keypad: [false; KEYPAD_SIZE],
+            io_port: 0,
+            delay_wait: false,
+            quirks: Quirks::default(),
+            video: [[0; MAX_VIDEO_HEIGHT]; PLANE_COUNT],
+            planes: 1,
+            width: VIDEO_WIDTH,
+            height: VIDEO_HEIGHT,
+            lores: false,
+            colors: None,
+            dirty_rows: ALL_ROWS,
+            frame_count: 0,
+            display_wait: false,
+            cycle_carry: 0,
+            trace: false,
+            rng: StdRng::from_entropy(),
+        };
+        //As fontes ficam na memória como no interpretador original, então um programa pode ler
+        //ou até sobrescrever os dígitos
+        let fonts = [(FONT_ADDRESS, &FONT[..]), (BIG_FONT_ADDRESS, &BIG_FONT[..])];
+        for (address, font) in fonts {
+            for (n, &byte) in font.iter().enumerate() {
+                if let Some(cell) = chip8.memory.get_mut(address + n) {
+                    *cell = byte;
+                }
+            }
+        }
+        chip8
+    }
+
+    //O Result serve para indicar que a função pode falhar e retornar um valor de sucesso ou um erro
+
+    //&mut self significa que o estado da objeto atual será mudado
+
+    //usasse &str na tipagem do parâmetro ou invés de apenas str para referenciar uma string de tamanho definido
+    //onde str não pode ser usado diretamente pois é um tipo de tamanho dinâmico (DST) e representa uma sequência
+    //de texto imutavél sem tamanho definido
+    pub fn load_rom(&mut self, filename: &str) -> std::io::Result<()> {
+        //como o processo de abrir o file pode falhar nos colocamos o ? no final da linha
+        //é um shortcut para
+        // let mut file =  match File::open(filename){
+        //     Ok(f) => f,
+        //     Err(e) => return Err(e),
+        // }
+        //Onde se der sucesso ele retorna o resultado esperado, senão, o erro
+        let mut file = File::open(filename)?;
+        //Cria um vetor de tamanho dinâmico pois não sabemos o tamanho na ROM
+        let mut buffer = Vec::new();
+        //é necessário passar &mut pois a função mudadará o estado do buffer
+        file.read_to_end(&mut buffer)?;
+        self.load_bytes(&buffer)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+
+    //Carrega a ROM direto de um slice de bytes, usado pelo load_rom e pelo fuzzer
+    pub fn load_bytes(&mut self, buffer: &[u8]) -> Result<(), Chip8Error> {
+        self.load_bytes_at(buffer, START_ADDRESS)
+    }
+
+    //Carrega a ROM a partir de `address`. O CHIP-8X, por exemplo, começa os programas em 0x300
+    pub fn load_bytes_at(&mut self, buffer: &[u8], address: usize) -> Result<(), Chip8Error> {
+        let max = self.memory.len().saturating_sub(address);
+        if buffer.len() > max {
+            return Err(Chip8Error::RomTooLarge {
+                size: buffer.len(),
+                max,
+            });
+        }
+        //um for in onde a gente tem o elemento e o index ao mesmo tempo!
+        //o .iter() fazer que iteramos por todos elementos de buffer
+        //o .enumerate fazer com que retorne tanto o valor quanto o index equivalente
+        //o & em &byte é usado para DESREFERENCIAR o valor do byte que vem como &u8. ao usar &byte o &u8 já retorna como u8
+        //meio confuso a principio para eu que nunca programei low level.
+        for (i, &byte) in buffer.iter().enumerate() {
+            self.memory[address + i] = byte;
+        }
+        self.flush_decode_cache();
+
+        Ok(())
+    }
+
+    //Endereços dão a volta na memória: 12 bits no CHIP-8, 16 no XO-CHIP
+    pub fn wrap_address(&self, addr: u16) -> u16 {
+        (addr as usize % self.memory.len()) as u16
+    }
+
+    //Acessos fora da memória dão a volta (I + n pode passar de 0xFFF)
+    pub fn read_byte(&self, addr: usize) -> u8 {
+        self.memory[addr % self.memory.len()]
+    }
+
+    //Código que se modifica: o byte escrito pode ser o primeiro da instrução em addr ou o
+    //segundo da instrução em addr - 1, então as duas entradas do cache são apagadas
+    pub fn write_byte(&mut self, addr: usize, value: u8) {
+        let len = self.memory.len();
+        let addr = addr % len;
+        self.memory[addr] = value;
+        self.decoded[addr] = None;
+        self.decoded[(addr + len - 1) % len] = None;
+    }
+
+    pub fn load_test_instructions(&mut self) {
+        let program: [u8; 12 + 5] = [
+            0x00, 0xE0, // CLS
+            0xF0, 0x0A, // LD V0, K
+            0x60, 0x00, // LD V0, 0
+            0x61, 0x00, // LD V1, 0
+            0xA3, 0x00, // LD I, 0x200
+            0xD0, 0x15, // DRW V0, V1, 5
+            // Dados do sprite (a partir de 0x200)
+            0xF0, 0x90, 0x90, 0x90, 0xF0,
+        ];
+
+        for (i, &byte) in program[..10].iter().enumerate() {
+            self.memory[0x200 + i] = byte;
+        }
+        for (i, &byte) in program[12..].iter().enumerate() {
+            self.memory[0x300 + i] = byte;
+        }
+        self.flush_decode_cache();
+    }
+
+    //Segundo a especificação os timers diminuiem uma unidade a cada 60Hz e isso é usado para coisas como animção e música
+    pub fn tick_timers(&mut self) {
+        if self.delay_timer > 0 {
+            self.delay_timer -= 1;
+        }
+
+        if self.sound_timer > 0 {
+            self.sound_timer -= 1;
+        }
+    }
+
+    //Executa um quadro de 60Hz: os timers são decrementados no início do quadro (como na
+    //interrupção de vídeo do VIP) e depois rodam `instructions` ciclos. Quando retorna a CPU está
+    //no "vblank": o frontend deve ler `video` e `audio_state()` nesse ponto, assim a tela mostrada
+    //é sempre a do fim de um quadro e não um estado no meio de um desenho
+    pub fn run_frame(&mut self, instructions: usize) -> Result<(), Chip8Error> {
+        self.tick_timers();
+        for _ in 0..instructions {
+            self.cycle()?;
+            if self.display_wait {
+                break;
+            }
+        }
+        self.end_frame();
+        Ok(())
+    }
+
+    //Quadro com os tempos do VIP: em vez de um número fixo de instruções, roda até gastar os
+    //ciclos de máquina que o 1802 tem num quadro, com o custo de cada instrução vindo de
+    //timing::vip_cycles. A instrução que passar do limite termina assim mesmo e o excesso sai do
+    //quadro seguinte. Retorna quantas instruções rodaram
+    pub fn run_vip_frame(&mut self) -> Result<usize, Chip8Error> {
+        self.tick_timers();
+        let mut cycles = self.cycle_carry;
+        let mut executed = 0;
+        while cycles < VIP_CYCLES_PER_FRAME && !self.display_wait {
+            self.pc = self.wrap_address(self.pc);
+            let instruction = self.fetch();
+            cycles += vip_cycles(self, instruction);
+            self.execute(instruction)?;
+            executed += 1;
+        }
+        //Esperando o vídeo a CPU fica parada até a interrupção, então não sobra dívida
+        self.cycle_carry = if self.display_wait {
+            0
+        } else {
+            cycles - VIP_CYCLES_PER_FRAME
+        };
+        self.end_frame();
+        Ok(executed)
+    }
+
+    //Fim do quadro: esquece as bordas das teclas e conta o quadro. Quem executa as instruções por
+    //outro caminho (o recompilador) chama no lugar do run_frame
+    pub fn end_frame(&mut self) {
+        self.key_pressed = [false; KEYPAD_SIZE];
+        self.key_released = [false; KEYPAD_SIZE];
+        self.display_wait = false;
+        self.frame_count += 1;
+    }
+
+    //Um Dxyn com a quirk display_wait parou o quadro atual. O recompilador e o JIT conferem depois
+    //de cada instrução que passa pelo interpretador
+    pub fn waiting_for_display(&self) -> bool {
+        self.display_wait
+    }
+
+    //Funções auxiliares das quirks
+
+    pub(crate) fn vf_reset(&mut self) {
+        if self.quirks.vf_reset {
+            self.v[0xF] = 0;
+        }
+    }
+
+    //Valor que o 8xy6 / 8xyE desloca
+    pub(crate) fn shift_source(&self, x: usize, y: usize) -> u8 {
+        if self.quirks.shift_vx {
+            self.v[x]
+        } else {
+            self.v[y]
+        }
+    }
+
+    pub(crate) fn memory_increment_i(&mut self, x: usize) {
+        if self.quirks.memory_increment_i {
+            self.i = self.i.wrapping_add(x as u16 + 1);
+        }
+    }
+
+    //O 5xy2 / 5xy3 do XO-CHIP nunca mexe no I; o do CHIP-8E segue a quirk como o Fx55
+    fn range_increment_i(&mut self, x: usize) {
+        if self.instruction_set == InstructionSet::Chip8E {
+            self.memory_increment_i(x);
+        }
+    }
+
+    //Atualiza o estado de uma tecla guardando as bordas: pressionar uma tecla solta ou soltar uma
+    //pressionada
+    pub fn set_key(&mut self, key: usize, down: bool) {
+        let key = key % KEYPAD_SIZE;
+        if down && !self.keypad[key] {
+            self.key_pressed[key] = true;
+        }
+        if !down && self.keypad[key] {
+            self.key_released[key] = true;
+        }
+        self.keypad[key] = down;
+    }
+
+    //A tecla foi pressionada / solta desde o início do quadro
+    pub fn key_pressed(&self, key: usize) -> bool {
+        self.key_pressed[key % KEYPAD_SIZE]
+    }
+
+    pub fn key_released(&self, key: usize) -> bool {
+        self.key_released[key % KEYPAD_SIZE]
+    }
+
+    //Pressionada agora ou em algum momento do quadro atual. É o que o Ex9E / ExA1 consultam, e o
+    //timing::vip_cycles usa o mesmo para cobrar o caminho que o skip vai tomar
+    pub(crate) fn key_down(&self, key: usize) -> bool {
+        self.keypad[key] || self.key_pressed[key]
+    }
+
+    //O beep toca enquanto o sound_timer for maior que zero. Quem gera o som é o frontend (ver audio.rs)
+    pub fn sound_active(&self) -> bool {
+        self.sound_timer > 0
+    }
+
+    //Tudo que o frontend precisa para gerar o áudio de um quadro
+    pub fn audio_state(&self) -> AudioState {
+        AudioState {
+            active: self.sound_active(),
+            pattern: self.audio_pattern,
+            pitch: self.pitch,
+        }
+    }
+
+    pub fn cycle(&mut self) -> Result<(), Chip8Error> {
+        //FETCH
+
+        //Garante que o pc está dentro da memória mesmo se alguém alterou o campo direto
+        self.pc = self.wrap_address(self.pc);
+        let instruction = self.fetch();
+        self.execute(instruction)
+    }
+
+    //Instrução no endereço do pc. Com o cache ligado os dois bytes só são lidos e decodificados
+    //na primeira vez que o endereço é executado; o write_byte apaga a entrada quando o código muda
+    fn fetch(&mut self) -> Instruction {
+        let pc = self.pc as usize;
+        if self.decode_cache
+            && let Some(instruction) = self.decoded[pc]
+        {
+            return instruction;
+        }
+        let high_byte: u16 = self.read_byte(pc) as u16;
+        let low_byte: u16 = self.read_byte(pc + 1) as u16;
+        let opcode: u16 = (high_byte << 8) | low_byte;
+        let instruction = self.instruction_set.decode(opcode);
+        if self.decode_cache {
+            self.decoded[pc] = Some(instruction);
+        }
+        instruction
+    }
+
+    //Linhas alteradas desde a última chamada, que passam a contar como limpas
+    pub fn take_dirty_rows(&mut self) -> u64 {
+        std::mem::take(&mut self.dirty_rows)
+    }
+
+    pub fn dirty_rows(&self) -> u64 {
+        self.dirty_rows
+    }
+
+    //Marca a tela toda para ser redesenhada. Só é preciso depois de escrever direto em `video`
+    pub fn mark_all_dirty(&mut self) {
+        self.dirty_rows = ALL_ROWS;
+    }
+
+    //Pula a próxima instrução. No XO-CHIP o F000 NNNN ocupa 4 bytes e é pulado inteiro
+    fn skip_next(&mut self) {
+        let next = self.pc.wrapping_add(2) as usize;
+        let long = self.instruction_set == InstructionSet::XoChip
+            && self.read_byte(next) == 0xF0
+            && self.read_byte(next + 1) == 0x00;
+        self.pc = self.pc.wrapping_add(if long { 4 } else { 2 });
+    }
+
+    //Índices dos planos selecionados
+    fn selected_planes(&self) -> impl Iterator<Item = usize> + use<> {
+        let planes = self.planes;
+        (0..PLANE_COUNT).filter(move |plane| planes & (1 << plane) != 0)
+    }
+
+    //00E0: apaga os planos selecionados. Limpar uma tela já apagada não suja nenhuma linha
+    pub(crate) fn clear_screen(&mut self) {
+        for plane in self.selected_planes() {
+            if self.plane_lit(plane) {
+                self.dirty_rows = ALL_ROWS;
+            }
+            self.video[plane] = [0; MAX_VIDEO_HEIGHT];
+        }
+    }
+
+    pub fn instruction_set(&self) -> InstructionSet {
+        self.instruction_set
+    }
+
+    //Trocar a tabela muda o significado de opcodes que já estão no cache, então ele é apagado
+    pub fn set_instruction_set(&mut self, instruction_set: InstructionSet) {
+        self.instruction_set = instruction_set;
+        self.flush_decode_cache();
+    }
+
+    //Largura e altura da tela em pixels
+    pub fn resolution(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    //Troca o tamanho da tela (até 128x64), que começa apagada. No mínimo 32 colunas, a largura
+    //do sprite de 16x16 em baixa resolução
+    pub fn set_resolution(&mut self, width: usize, height: usize) {
+        self.width = width.clamp(32, MAX_VIDEO_WIDTH);
+        self.height = height.clamp(1, MAX_VIDEO_HEIGHT);
+        self.video = [[0; MAX_VIDEO_HEIGHT]; PLANE_COUNT];
+        self.dirty_rows = ALL_ROWS;
+    }
+
+    //Baixa resolução do SUPER-CHIP / XO-CHIP (ver `lores`)
+    pub fn lores(&self) -> bool {
+        self.lores
+    }
+
+    //00FE / 00FF: troca o modo e apaga todos os planos, como no SUPER-CHIP
+    pub fn set_lores(&mut self, lores: bool) {
+        self.lores = lores;
+        self.video = [[0; MAX_VIDEO_HEIGHT]; PLANE_COUNT];
+        self.dirty_rows = ALL_ROWS;
+    }
+
+    //Tamanho de um pixel do programa na tela: 2 na baixa resolução, 1 no resto
+    fn pixel_scale(&self) -> usize {
+        if self.lores { 2 } else { 1 }
+    }
+
+    //Um plano com algum pixel aceso. Rolar ou apagar um plano vazio não suja nenhuma linha
+    fn plane_lit(&self, plane: usize) -> bool {
+        self.video[plane][..self.height].iter().any(|&row| row != 0)
+    }
+
+    //00Cn / 00Dn: rola os planos selecionados `rows` linhas para baixo (ou para cima). As linhas
+    //são as da resolução atual, então na baixa resolução cada uma vale duas da tela. Toda linha
+    //pode mudar, então a tela inteira fica suja
+    fn scroll_vertical(&mut self, rows: usize, down: bool) {
+        let height = self.height;
+        let amount = (rows * self.pixel_scale()).min(height);
+        for plane in self.selected_planes() {
+            if self.plane_lit(plane) {
+                self.dirty_rows = ALL_ROWS;
+            }
+            let video = &mut self.video[plane];
+            if down {
+                video.copy_within(0..height - amount, amount);
+                video[..amount].fill(0);
+            } else {
+                video.copy_within(amount..height, 0);
+                video[height - amount..height].fill(0);
+            }
+        }
+    }
+
+    //00FB / 00FC: rola os planos selecionados 4 pixels para a direita (ou para a esquerda). A
+    //coluna 0 é o bit mais alto da linha, então ir para a direita é um shift para a direita
+    fn scroll_horizontal(&mut self, right: bool) {
+        let amount = 4 * self.pixel_scale();
+        let mask = self.row_mask();
+        for plane in self.selected_planes() {
+            if self.plane_lit(plane) {
+                self.dirty_rows = ALL_ROWS;
+            }
+            for row in &mut self.video[plane][..self.height] {
+                *row = if right {
+                    *row >> amount
+                } else {
+                    (*row << amount) & mask
+                };
+            }
+        }
+    }
+
+    //Dxyn e Dxy0: desenha `rows` linhas de `columns` pixels (8, ou 16 no sprite grande) em cada
+    //plano selecionado. Na baixa resolução cada pixel do sprite vira 2x2 na tela
+    fn draw_sprite(&mut self, x: u8, y: u8, rows: usize, columns: usize) {
+        let scale = self.pixel_scale();
+        //A posição inicial sempre dá a volta na tela, o que passar da borda depende da quirk
+        let (screen_width, screen_height) = (self.width / scale, self.height / scale);
+        let x = self.v[x as usize] as usize % screen_width * scale;
+        let y = self.v[y as usize] as usize % screen_height;
+        //Cada linha do sprite tem 1 byte, ou 2 no de 16x16
+        let row_bytes = columns / 8;
+
+        self.v[0xF] = 0; // Reset VF
+
+        //os bytes do sprite estão no endereço de memoria I e vão até I + rows * row_bytes.
+        //Com os dois planos selecionados (XO-CHIP) os bytes do segundo plano vêm logo depois
+        let mut address = self.i as usize;
+        for plane in self.selected_planes() {
+            for row in 0..rows {
+                if self.quirks.clip_sprites && y + row >= screen_height {
+                    break;
+                }
+                //o modulo é usado para que caso a coordenada passe do limite da tela [screen_height] o pixel comece novamente em baixo ao invés de apenas n aparecer
+                let y_coord = (y + row) % screen_height;
+                let sprite = (0..row_bytes).fold(0u128, |line, n| {
+                    line << 8 | self.read_byte(address + row * row_bytes + n) as u128
+                });
+                let (sprite, width) = if scale == 2 {
+                    (double_bits(sprite, columns), columns * 2)
+                } else {
+                    (sprite, columns)
+                };
+
+                //A linha do sprite vai para as primeiras colunas (a coluna 0 é o bit width - 1)
+                //e é deslocada até a coluna x. Com o corte os bits que passam da borda somem
+                //no shift; sem ele os bits que saíram voltam pelo lado esquerdo
+                let line = sprite << (self.width - width);
+                let bits = if self.quirks.clip_sprites {
+                    line >> x
+                } else {
+                    let wrapped = line.checked_shl((self.width - x) as u32).unwrap_or(0);
+                    (line >> x | wrapped) & self.row_mask()
+                };
+                for y_coord in y_coord * scale..(y_coord + 1) * scale {
+                    //Um pixel aceso que vai ser apagado pelo XOR é uma colisão, em qualquer plano.
+                    //O VF fica em 1 e não conta as linhas que colidiram como no SUPER-CHIP
+                    if self.video[plane][y_coord] & bits != 0 {
+                        self.v[0xF] = 1;
+                    }
+                    self.video[plane][y_coord] ^= bits;
+                    //Uma linha do sprite toda zerada não muda nada na tela
+                    if bits != 0 {
+                        self.dirty_rows |= 1 << y_coord;
+                    }
+                }
+            }
+            address += rows * row_bytes;
+        }
+        if self.quirks.display_wait {
+            self.display_wait = true;
+        }
+    }
+
+    //Bits de uma linha que estão dentro da tela
+    fn row_mask(&self) -> u128 {
+        u128::MAX >> (MAX_VIDEO_WIDTH - self.width)
+    }
+
+    //Pixel na coluna x, linha y, aceso em algum dos planos
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        self.pixel_index(x, y) != 0
+    }
+
+    //Índice do pixel na paleta: bit 0 aceso no primeiro plano, bit 1 no segundo
+    pub fn pixel_index(&self, x: usize, y: usize) -> u8 {
+        let bit = 1 << (self.width - 1 - x);
+        (0..PLANE_COUNT)
+            .filter(|&plane| self.video[plane][y] & bit != 0)
+            .fold(0, |index, plane| index | 1 << plane)
+    }
+
+    //Acende ou apaga um pixel do primeiro plano, marcando a linha como alterada
+    pub fn set_pixel(&mut self, x: usize, y: usize, on: bool) {
+        let bit = 1 << (self.width - 1 - x);
+        if on {
+            self.video[0][y] |= bit;
+        } else {
+            self.video[0][y] &= !bit;
+        }
+        self.dirty_rows |= 1 << y;
+    }
+
+    //Todos os pixels, linha por linha e da esquerda para a direita (índice y * width + x)
+    pub fn pixels(&self) -> impl Iterator<Item = bool> + '_ {
+        self.pixel_indices().map(|index| index != 0)
+    }
+
+    //Como pixels(), mas com o índice de cada pixel na paleta
+    pub fn pixel_indices(&self) -> impl Iterator<Item = u8> + '_ {
+        let (width, height) = (self.width, self.height);
+        (0..height).flat_map(move |y| (0..width).map(move |x| self.pixel_index(x, y)))
+    }
+
+    //Apaga todo o cache de decodificação. Só é preciso depois de escrever direto em `memory`
+    pub fn flush_decode_cache(&mut self) {
+        self.decoded.fill(None);
+    }
+
+    //Executa uma instrução já decodificada como se ela estivesse no endereço do pc
+    pub fn execute(&mut self, instruction: Instruction) -> Result<(), Chip8Error> {
+        match instruction {
+            //Limpa a tela de toda informação
+            //CLS - Clear Screen
+            Instruction::Clear => {
+                self.clear_screen();
+                trace!(self, "Executed CLS (Clear Screen)");
+            }
+
+            Instruction::SkipKey(x) => {
+                //Pula a próxima instrução caso o botão com o valor de Vx estiver pressionado
+                //Só o nibble baixo de Vx é usado, assim como no VIP
+                let key = (self.v[x as usize] & 0x0F) as usize;
+                if self.key_down(key) {
+                    self.skip_next();
+                }
+            }
+
+            Instruction::SkipNotKey(x) => {
+                // Pula a próxima instrução caso o botão com o valor de Vx NÃO estiver pressionado
+                let key = (self.v[x as usize] & 0x0F) as usize;
+                if !self.key_down(key) {
+                    self.skip_next();
+                }
+            }
+
+            //Set the I register to address NNN
+            Instruction::LoadI(addr) => {
+                self.i = addr;
+                trace!(self, "Executed LD I, {:#05X}", addr);
+            }
+
+            //Return from subroutine
+            Instruction::Return => {
+                if self.sp == 0 {
+                    return Err(Chip8Error::StackUnderflow { pc: self.pc });
+                }
+                //Decrementa o ponteiro da pilha para pegar o endereço do topo
+                //Isso é necessário pois o ponteiro da pilha aponta para o próximo endereço livre
+
+                self.sp -= 1;
+
+                //Recupera o endereço do topo da pilha que representa o endereço de retorno
+                let return_addr = self.stack[self.sp as usize];
+
+                //Seta o pc para o endereço de retorno
+                self.pc = return_addr;
+                trace!(
+                    self,
+                    "Executed RET (Return from subroutine) to {:#05X}", return_addr
+                );
+            }
+
+            //1nnn - Jump to address nnn
+            //Instrução de setar um valor para o pc
+            Instruction::Jump(addr) => {
+                self.pc = addr;
+                trace!(self, "Executed JP {:03X}", addr);
+                //Encerra o fluxo aqui pois se ele passar ele vai incrementar o pc no final do match
+                //o que sairia do endereço que acabou de ser gerado
+                return Ok(());
+            }
+
+            // 2NNN: CALL NNN
+            //Call subroutine at NNN (push current PC to stack).
+            //Chama a subrotina no endereço NNN. Colocar no
+            Instruction::Call(addr) => {
+                if self.sp as usize >= STACK_SIZE {
+                    return Err(Chip8Error::StackOverflow { pc: self.pc });
+                }
+                //Coloca o endereço atual do pc no topo da pilha que é o endereço para qual retornará ao final da subrotina
+                //A pilha guarda os endereços das subrotinas que estão sendo executadas
+                self.stack[self.sp as usize] = self.pc;
+                //Incrementa o ponteiro da pilha para caso uma nova subrotina seja chamada ela seja colocada no topo
+                self.sp += 1;
+                //Seta o pc para o endereço da subrotina
+                self.pc = addr;
+                trace!(self, "Executed CALL {:03X}", addr);
+                //Assim como no JP o pc não pode ser incrementado, senão a subrotina começaria em addr + 2.
+                //O RET é quem pula a instrução do CALL ao voltar
+                return Ok(());
+            }
+
+            //6xkk - Set Vx = kk
+            //Passa um determinado valor para um register
+            Instruction::Load(x, kk) => {
+                self.v[x as usize] = kk;
+                trace!(self, "Executed LD V{:X}, {:#X}", x, kk);
+            }
+
+            //7xkk - Set Vx = Vx + kk
+            //Instrução que faz o somatório do valor atual do register com o valor em kk
+            Instruction::AddImmediate(x, kk) => {
+                let x = x as usize;
+                self.v[x] = self.v[x].wrapping_add(kk);
+                trace!(self, "Executed ADD V{:X}, {:#X}", x, kk);
+            }
+
+            Instruction::Move(x, y) => {
+                self.v[x as usize] = self.v[y as usize];
+                trace!(self, "Executed LD V{:X}, V{:X}", x, y);
+            }
+            Instruction::Or(x, y) => {
+                self.v[x as usize] |= self.v[y as usize];
+                self.vf_reset();
+                trace!(self, "Executed OR V{:X}, V{:X}", x, y);
+            }
+            Instruction::And(x, y) => {
+                self.v[x as usize] &= self.v[y as usize];
+                self.vf_reset();
+                trace!(self, "Executed AND V{:X}, V{:X}", x, y);
+            }
+            Instruction::Xor(x, y) => {
+                self.v[x as usize] ^= self.v[y as usize];
+                self.vf_reset();
+                trace!(self, "Executed XOR V{:X}, V{:X}", x, y);
+            }
+            Instruction::Add(x, y) => {
+                let (x, y) = (x as usize, y as usize);
+                let (result, carry) = self.v[x].overflowing_add(self.v[y]);
+                self.v[x] = result;
+                self.v[0xF] = if carry { 1 } else { 0 };
+                trace!(self, "Executed ADD V{:X}, V{:X} (with carry)", x, y);
+            }
+            Instruction::Sub(x, y) => {
+                let (x, y) = (x as usize, y as usize);
+                let (result, borrow) = self.v[x].overflowing_sub(self.v[y]);
+                self.v[x] = result;
+                self.v[0xF] = if borrow { 0 } else { 1 };
+                trace!(self, "Executed SUB V{:X}, V{:X}", x, y);
+            }
+            Instruction::ShiftRight(x, y) => {
+                let (x, y) = (x as usize, y as usize);
+                let source = self.shift_source(x, y);
+                //Salva o bit menos significativo
+                let flag = source & 0x01;
+                //Move o valor 1 bit para direita
+                self.v[x] = source >> 1;
+                //VF é escrito por último para que a flag prevaleça quando x = F
+                self.v[0xF] = flag;
+                trace!(self, "Executed SHR V{:X}", x);
+            }
+            Instruction::SubReverse(x, y) => {
+                let (x, y) = (x as usize, y as usize);
+                let (result, borrow) = self.v[y].overflowing_sub(self.v[x]);
+                self.v[x] = result;
+                self.v[0xF] = if borrow { 0 } else { 1 };
+                trace!(self, "Executed SUB V{:X}, V{:X}", x, y);
+            }
+            Instruction::ShiftLeft(x, y) => {
+                let (x, y) = (x as usize, y as usize);
+                let source = self.shift_source(x, y);
+                //Salva o bit mais significativo
+                let flag = (source & 0x80) >> 7;
+                //Move o valor 1 bit para esquerda
+                self.v[x] = source << 1;
+                self.v[0xF] = flag;
+                trace!(self, "Executed SHL V{:X}", x);
+            }
+
+            //Draw Sprites
+            //0xDXYN
+            //A altura do sprite também representa seu tamanho em bytes
+            //pois para cada unidade de altura tem um byte (8 bits - 10101010) que será desenhado horizontalmente
+            //pois o sprite tem apenas 1 byte de largura
+            Instruction::Draw(x, y, height) => {
+                self.draw_sprite(x, y, height as usize, 8);
+                trace!(self, "Coloriu");
+            }
+
+            //Timers -------------------------------------------
+
+            // Vai salvar o valor do delay_timer em VX
+            Instruction::LoadDelay(x) => {
+                self.v[x as usize] = self.delay_timer;
+                trace!(self, "Executed LD V{:X}, DT", x);
+            }
+
+            //Define o delay_timer com valor de VX
+            Instruction::SetDelay(x) => {
+                self.delay_timer = self.v[x as usize];
+                trace!(self, "Executed LD DT, V{:X}", x);
+            }
+
+            //Define o sound_timer com o valor de VX
+            Instruction::SetSound(x) => {
+                self.sound_timer = self.v[x as usize];
+                trace!(self, "Executed LD ST, V{:X}", x);
+            }
+
+            //XO-CHIP: F002 copia 16 bytes a partir de I para o padrão de áudio
+            Instruction::Audio => {
+                let mut pattern = [0; AUDIO_PATTERN_SIZE];
+                for (n, byte) in pattern.iter_mut().enumerate() {
+                    *byte = self.read_byte(self.i as usize + n);
+                }
+                self.audio_pattern = Some(pattern);
+                trace!(self, "Executed AUDIO");
+            }
+
+            //XO-CHIP: Fx3A define o pitch da reprodução do padrão com o valor de Vx
+            Instruction::Pitch(x) => {
+                self.pitch = self.v[x as usize];
+                trace!(self, "Executed PITCH V{:X}", x);
+            }
+
+            //IO---------------
+
+            //Soma o valor de VX ao de I
+            Instruction::AddI(x) => {
+                self.i = self.i.wrapping_add(self.v[x as usize] as u16);
+            }
+
+            //Espera uma tecla. Enquanto nenhuma chega o pc volta para esta mesma instrução
+            Instruction::WaitKey(x) => {
+                let x = x as usize;
+                let pressed = (0..KEYPAD_SIZE).find(|&key| self.key_down(key));
+                match (self.quirks.key_wait_release, self.key_wait, pressed) {
+                    // No VIP a tecla só é aceita quando é solta
+                    (true, Some(key), _) if !self.keypad[key as usize] => {
+                        self.key_wait = None;
+                        self.v[x] = key;
+                        trace!(self, "Executed LD V{:X}, K", x);
+                    }
+                    (true, None, Some(key)) => {
+                        self.key_wait = Some(key as u8);
+                        self.pc = self.pc.wrapping_sub(2);
+                    }
+                    (false, _, Some(key)) => {
+                        self.v[x] = key as u8;
+                        trace!(self, "Executed LD V{:X}, K", x);
+                    }
+                    _ => self.pc = self.pc.wrapping_sub(2),
+                }
+            }
+
+            // Seta I com o endereço de um character armazenado em Vx
+            // Fontes normalmente ocupam 5 bytes e são armazenadas a partir do endereço 0x000
+            Instruction::Font(x) => {
+                let digit = self.v[x as usize] as u16;
+                self.i = digit * 5;
+                trace!(self, "Executed LD F, V{:X} (char sprite addr)", x);
+            }
+
+            // Armazena o valor de Vx em formato decimal nos endereços I, I+1 e I+2
+            Instruction::Bcd(x) => {
+                let vx = self.v[x as usize];
+                let addr = self.i as usize;
+                self.write_byte(addr, vx / 100);
+                self.write_byte(addr + 1, (vx % 100) / 10);
+                self.write_byte(addr + 2, vx % 10);
+                trace!(self, "Executed LD B, V{:X}", x);
+            }
+
+            //Armazena os valores de V0 até Vx na memoria a partir do endereço I
+            Instruction::Store(x) => {
+                let x = x as usize;
+                for i in 0..=x {
+                    self.write_byte(self.i as usize + i, self.v[i]);
+                }
+                self.memory_increment_i(x);
+                trace!(self, "Executed LD [I], V0..V{:X}", x);
+            }
+
+            //Armazena os valores a partir de I até x em V0 até Vx
+            Instruction::Restore(x) => {
+                let x = x as usize;
+                for i in 0..=x {
+                    self.v[i] = self.read_byte(self.i as usize + i)
+                }
+                self.memory_increment_i(x);
+                trace!(self, "Executed LD V0..V{:X}, [I]", x);
+            }
+
+            // Pula a próxima instrução caso Vx seja igual a kk
+            Instruction::SkipEqual(x, kk) => {
+                if self.v[x as usize] == kk {
+                    self.skip_next();
+                }
+                trace!(self, "Executed SE V{:X}, {:#X}", x, kk);
+            }
+
+            // Pula a próxima instrução caso Vx seja diferente a kk
+            Instruction::SkipNotEqual(x, kk) => {
+                if self.v[x as usize] != kk {
+                    self.skip_next();
+                }
+                trace!(self, "Executed SNE V{:X}, {:#X}", x, kk);
+            }
+
+            // Pula a próxima instrução caso Vx seja igual a Vy
+            Instruction::SkipEqualRegister(x, y) => {
+                if self.v[x as usize] == self.v[y as usize] {
+                    self.skip_next();
+                }
+                trace!(self, "Executed SE V{:X}, V{:#X}", x, y);
+            }
+
+            // Pula a próxima instrução caso Vx seja diferente a Vy
+            Instruction::SkipNotEqualRegister(x, y) => {
+                if self.v[x as usize] != self.v[y as usize] {
+                    self.skip_next();
+                }
+                trace!(self, "Executed SNE V{:X}, V{:#X}", x, y);
+            }
+
+            //Bnnn - Jump to address nnn + V0
+            Instruction::JumpOffset(x, nnn) => {
+                //No SUPER-CHIP o registrador somado é o Vx do próprio opcode (Bxnn)
+                let x = if self.quirks.jump_vx { x as usize } else { 0 };
+                let addr = nnn + self.v[x] as u16;
+                self.pc = self.wrap_address(addr);
+                trace!(self, "Executed JP V{:X}, {:03X}", x, nnn);
+                return Ok(());
+            }
+
+            //Salva em Vx um (número aleatório de 0 a 255 AND kk)
+            Instruction::Random(x, kk) => {
+                let rnd = self.rng.next_u32() as u8;
+                self.v[x as usize] = rnd & kk;
+
+                trace!(self, "Executed RND V{:X}, {:#X} → random {:#X}", x, kk, rnd);
+            }
+
+            //CHIP-8X ---------------------------------------
+
+            //02A0: passa para a próxima cor de fundo
+            Instruction::Background => {
+                if let Some(colors) = &mut self.colors {
+                    colors.next_background();
+                    self.dirty_rows = ALL_ROWS;
+                }
+                trace!(self, "Executed BGCOL");
+            }
+
+            //5xy1: soma nibble a nibble, cada um com 3 bits. Com a máscara 0x77 o vai-um do nibble
+            //baixo nunca chega ao alto
+            Instruction::AddNibbles(x, y) => {
+                let sum = (self.v[x as usize] & 0x77) + (self.v[y as usize] & 0x77);
+                self.v[x as usize] = sum & 0x77;
+                trace!(self, "Executed ADD V{:X}, V{:X} (nibbles)", x, y);
+            }
+
+            //Bxyn: pinta com a cor Vy. Com n = 0 o Vx escolhe as colunas e o Vx+1 as linhas de
+            //zonas de 8x4 pixels: o nibble baixo é a primeira zona e o alto quantas zonas a mais.
+            //Com n > 0 pinta n linhas a partir da Vx+1 no bloco de 8 pixels da coluna Vx
+            Instruction::Color(x, y, n) => {
+                let horizontal = self.v[x as usize];
+                let vertical = self.v[(x as usize + 1) % REGISTER_COUNT];
+                let color = self.v[y as usize];
+                if let Some(colors) = &mut self.colors {
+                    let span = |value: u8| {
+                        let start = (value & 0xF) as usize;
+                        start..=start + (value >> 4) as usize
+                    };
+                    if n == 0 {
+                        let lines = span(vertical)
+                            .flat_map(|zone| zone * ZONE_LINES..(zone + 1) * ZONE_LINES);
+                        colors.fill(span(horizontal), lines, color);
+                    } else {
+                        let column = (horizontal as usize % VIDEO_WIDTH) / 8;
+                        let first = vertical as usize;
+                        colors.fill(column..=column, first..first + n as usize, color);
+                    }
+                    self.dirty_rows = ALL_ROWS;
+                }
+                trace!(self, "Executed COL V{:X}, V{:X}, {:X}", x, y, n);
+            }
+
+            //ExF2 / ExF5: os skips do Ex9E / ExA1 lendo o segundo teclado
+            Instruction::SkipKey2(x) => {
+                let key = (self.v[x as usize] & 0x0F) as usize;
+                if self.second_keypad[key] {
+                    self.skip_next();
+                }
+            }
+
+            Instruction::SkipNotKey2(x) => {
+                let key = (self.v[x as usize] & 0x0F) as usize;
+                if !self.second_keypad[key] {
+                    self.skip_next();
+                }
+            }
+
+            //Porta de E/S (CHIP-8X e CHIP-8E)
+            Instruction::Output(x) => {
+                self.io_port = self.v[x as usize];
+                trace!(self, "Executed OUT V{:X}", x);
+            }
+
+            Instruction::Input(x) => {
+                self.v[x as usize] = self.io_port;
+                trace!(self, "Executed IN V{:X}", x);
+            }
+
+            //CHIP-8E ---------------------------------------
+
+            //00ED / 00FD: para o programa. O pc não anda, então a mesma instrução roda para sempre
+            Instruction::Halt => {
+                trace!(self, "Executed STOP");
+                return Ok(());
+            }
+
+            //0151: espera o delay_timer chegar a zero
+            Instruction::WaitDelay => {
+                if self.delay_timer > 0 {
+                    return Ok(());
+                }
+            }
+
+            //0188: pula a próxima instrução sempre
+            Instruction::Skip => self.skip_next(),
+
+            //5xy1: pula se Vx > Vy
+            Instruction::SkipGreater(x, y) => {
+                if self.v[x as usize] > self.v[y as usize] {
+                    self.skip_next();
+                }
+                trace!(self, "Executed SGT V{:X}, V{:X}", x, y);
+            }
+
+            //5xy2 / 5xy3 (também no XO-CHIP): guardam e carregam Vx até Vy (em ordem decrescente
+            //se x > y) a partir de I
+            Instruction::StoreRange(x, y) => {
+                let (x, y) = (x as usize, y as usize);
+                let count = x.abs_diff(y) + 1;
+                for n in 0..count {
+                    let register = if x <= y { x + n } else { x - n };
+                    self.write_byte(self.i as usize + n, self.v[register]);
+                }
+                self.range_increment_i(count - 1);
+                trace!(self, "Executed LD [I], V{:X}..V{:X}", x, y);
+            }
+
+            Instruction::LoadRange(x, y) => {
+                let (x, y) = (x as usize, y as usize);
+                let count = x.abs_diff(y) + 1;
+                for n in 0..count {
+                    let register = if x <= y { x + n } else { x - n };
+                    self.v[register] = self.read_byte(self.i as usize + n);
+                }
+                self.range_increment_i(count - 1);
+                trace!(self, "Executed LD V{:X}..V{:X}, [I]", x, y);
+            }
+
+            //BBnn / BFnn: desvio de nn bytes para trás / para frente, contados a partir da
+            //instrução seguinte
+            Instruction::BranchBack(nn) => {
+                self.pc = self.wrap_address(self.pc.wrapping_add(2).wrapping_sub(nn as u16));
+                trace!(self, "Executed JB {:#X}", nn);
+                return Ok(());
+            }
+
+            Instruction::BranchForward(nn) => {
+                self.pc = self.wrap_address(self.pc.wrapping_add(2).wrapping_add(nn as u16));
+                trace!(self, "Executed JF {:#X}", nn);
+                return Ok(());
+            }
+
+            //Fx1B: pula Vx bytes
+            Instruction::SkipBytes(x) => {
+                self.pc = self.pc.wrapping_add(self.v[x as usize] as u16);
+                trace!(self, "Executed SKIP V{:X}", x);
+            }
+
+            //Fx4F: carrega o delay_timer com Vx e espera ele chegar a zero. Como no Fx0A o pc fica
+            //na instrução enquanto espera
+            Instruction::SetDelayWait(x) => {
+                if !self.delay_wait {
+                    self.delay_timer = self.v[x as usize];
+                    self.delay_wait = true;
+                }
+                if self.delay_timer > 0 {
+                    return Ok(());
+                }
+                self.delay_wait = false;
+                trace!(self, "Executed LD DT, V{:X} (wait)", x);
+            }
+
+            //SUPER-CHIP ------------------------------------
+
+            //00Cn: rola a tela n linhas para baixo
+            Instruction::ScrollDown(n) => {
+                self.scroll_vertical(n as usize, true);
+                trace!(self, "Executed SCD {:X}", n);
+            }
+
+            //00FB / 00FC: rola a tela 4 pixels para a direita / esquerda
+            Instruction::ScrollRight => {
+                self.scroll_horizontal(true);
+                trace!(self, "Executed SCR");
+            }
+
+            Instruction::ScrollLeft => {
+                self.scroll_horizontal(false);
+                trace!(self, "Executed SCL");
+            }
+
+            //00FE / 00FF: baixa (64x32) e alta resolução (128x64)
+            Instruction::LowRes => {
+                self.set_lores(true);
+                trace!(self, "Executed LOW");
+            }
+
+            Instruction::HighRes => {
+                self.set_lores(false);
+                trace!(self, "Executed HIGH");
+            }
+
+            //Dxy0: sprite de 16x16, 2 bytes por linha
+            Instruction::DrawLarge(x, y) => {
+                self.draw_sprite(x, y, 16, 16);
+                trace!(self, "Executed DRW V{:X}, V{:X}, 0", x, y);
+            }
+
+            //Fx30: I aponta para o dígito de Vx na fonte grande, 10 bytes por dígito
+            Instruction::BigFont(x) => {
+                let digit = (self.v[x as usize] & 0x0F) as u16;
+                self.i = BIG_FONT_ADDRESS as u16 + digit * 10;
+                trace!(self, "Executed LD HF, V{:X}", x);
+            }
+
+            //Fx75 / Fx85: guardam e carregam V0..Vx nas flags
+            Instruction::SaveFlags(x) => {
+                let x = x as usize;
+                self.flags[..=x].copy_from_slice(&self.v[..=x]);
+                trace!(self, "Executed LD R, V{:X}", x);
+            }
+
+            Instruction::LoadFlags(x) => {
+                let x = x as usize;
+                self.v[..=x].copy_from_slice(&self.flags[..=x]);
+                trace!(self, "Executed LD V{:X}, R", x);
+            }
+
+            //XO-CHIP ---------------------------------------
+
+            //00Dn: rola a tela n linhas para cima
+            Instruction::ScrollUp(n) => {
+                self.scroll_vertical(n as usize, false);
+                trace!(self, "Executed SCU {:X}", n);
+            }
+
+            //F000 NNNN: carrega I com os 16 bits da palavra seguinte, que é pulada junto
+            Instruction::LongLoadI => {
+                let addr = self.pc.wrapping_add(2) as usize;
+                self.i = u16::from_be_bytes([self.read_byte(addr), self.read_byte(addr + 1)]);
+                self.pc = self.pc.wrapping_add(2);
+                trace!(self, "Executed LD I, LONG {:#06X}", self.i);
+            }
+
+            //Fn01: escolhe os planos em que o Dxyn desenha e que o 00E0 apaga
+            Instruction::Plane(n) => {
+                self.planes = n & ((1 << PLANE_COUNT) - 1);
+                trace!(self, "Executed PLANE {:X}", n);
+            }
+
+            Instruction::Unknown(opcode) => trace!(self, "Unknown opcode! {:#06X}", opcode),
+        }
+
+        //Como dois bytes são lidos de uma vez o Program Counter tem que pular dois endereços de memoria de uma vez
+        self.pc = self.wrap_address(self.pc.wrapping_add(2));
+        Ok(())
+    }
+}
+
+//Cada bit das `width` colunas da linha vira dois, para a baixa resolução do SUPER-CHIP
+fn double_bits(line: u128, width: usize) -> u128 {
+    (0..width)
+        .filter(|bit| line & (1 << bit) != 0)
+        .fold(0, |wide, bit| wide | 0b11 << (2 * bit))
+}
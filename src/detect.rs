@@ -0,0 +1,193 @@
+use crate::cpu::{MEMORY_SIZE, START_ADDRESS};
+use crate::platform::Platform;
+use std::fmt;
+
+// Palpite da plataforma de uma ROM que não está no banco. O detector segue o fluxo do programa a
+// partir de 0x200 (saltos, chamadas e os dois caminhos de cada skip) e procura instruções que só
+// existem no SUPER-CHIP ou no XO-CHIP. Bytes fora do fluxo podem ser dados (um sprite com
+// 0x00 0xFF parece um 00FF), por isso só contam, e com pouca confiança, quando o fluxo se perde
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Confidence {
+    Low,
+    Medium,
+    High,
+}
+
+impl fmt::Display for Confidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Confidence::Low => "low",
+            Confidence::Medium => "medium",
+            Confidence::High => "high",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Detection {
+    pub platform: Platform,
+    pub confidence: Confidence,
+    // O que levou ao palpite, por exemplo "00FF at 0x204"
+    pub evidence: Vec<String>,
+}
+
+impl fmt::Display for Detection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({} confidence", self.platform, self.confidence)?;
+        if !self.evidence.is_empty() {
+            let shown: Vec<&str> = self.evidence.iter().take(3).map(String::as_str).collect();
+            write!(f, ": {}", shown.join(", "))?;
+            if self.evidence.len() > shown.len() {
+                write!(f, ", ...")?;
+            }
+        }
+        write!(f, ")")
+    }
+}
+
+// Extensão exigida pela instrução, se houver. O XO-CHIP também tem todas as do SUPER-CHIP
+fn extension(opcode: u16) -> Option<Platform> {
+    let x = (opcode & 0x0F00) >> 8;
+    match opcode {
+        0x00C1..=0x00CF | 0x00FB..=0x00FF => Some(Platform::SuperChip),
+        0xD000..=0xDFFF if opcode & 0x000F == 0 => Some(Platform::SuperChip),
+        0xF000..=0xFFFF if matches!(opcode & 0x00FF, 0x30 | 0x75 | 0x85) && x < 8 => {
+            Some(Platform::SuperChip)
+        }
+        0x00D1..=0x00DF | 0xF000 | 0xF002 => Some(Platform::XoChip),
+        0x5000..=0x5FFF if matches!(opcode & 0x000F, 0x2 | 0x3) => Some(Platform::XoChip),
+        0xF000..=0xFFFF if matches!(opcode & 0x00FF, 0x01 | 0x3A) => Some(Platform::XoChip),
+        // Fx75 / Fx85 com x acima de 7 só cabem nas 16 flags do XO-CHIP
+        0xF000..=0xFFFF if matches!(opcode & 0x00FF, 0x75 | 0x85) => Some(Platform::XoChip),
+        _ => None,
+    }
+}
+
+fn word(rom: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([
+        *rom.get(offset)?,
+        *rom.get(offset + 1)?,
+    ]))
+}
+
+// F000 NNNN ocupa 4 bytes
+fn instruction_size(rom: &[u8], offset: usize) -> usize {
+    if word(rom, offset) == Some(0xF000) {
+        4
+    } else {
+        2
+    }
+}
+
+// Posições (relativas ao início da ROM) das instruções alcançáveis a partir de 0x200, e se o
+// fluxo ficou completo (false quando algum Bnnn salta para um destino desconhecido)
+fn reachable(rom: &[u8]) -> (Vec<usize>, bool) {
+    let mut visited = vec![false; rom.len()];
+    let mut pending = vec![0];
+    let mut found = Vec::new();
+    let mut complete = true;
+    while let Some(offset) = pending.pop() {
+        let Some(opcode) = word(rom, offset) else {
+            continue;
+        };
+        if visited[offset] {
+            continue;
+        }
+        visited[offset] = true;
+        found.push(offset);
+
+        let next = offset + instruction_size(rom, offset);
+        let target = (opcode & 0x0FFF) as usize;
+        let target = target.checked_sub(START_ADDRESS);
+        match opcode & 0xF000 {
+            // 00EE volta da sub-rotina e 00FD sai do interpretador
+            0x0000 if opcode == 0x00EE || opcode == 0x00FD => {}
+            0x1000 => pending.extend(target),
+            0x2000 => {
+                pending.extend(target);
+                pending.push(next);
+            }
+            // O destino do Bnnn depende de um registrador
+            0xB000 => complete = false,
+            0x3000 | 0x4000 => pending.extend([next, next + instruction_size(rom, next)]),
+            0x5000 | 0x9000 if opcode & 0x000F == 0 => {
+                pending.extend([next, next + instruction_size(rom, next)])
+            }
+            0xE000 if matches!(opcode & 0x00FF, 0x9E | 0xA1) => {
+                pending.extend([next, next + instruction_size(rom, next)])
+            }
+            _ => pending.push(next),
+        }
+    }
+    found.sort_unstable();
+    (found, complete)
+}
+
+// Instruções de extensão nas posições dadas, da plataforma mais nova para a mais antiga
+fn extensions(rom: &[u8], offsets: impl Iterator<Item = usize>) -> Option<(Platform, Vec<String>)> {
+    let mut schip = Vec::new();
+    let mut xochip = Vec::new();
+    for offset in offsets {
+        let Some(opcode) = word(rom, offset) else {
+            continue;
+        };
+        let evidence = format!("{:04X} at {:#05X}", opcode, START_ADDRESS + offset);
+        match extension(opcode) {
+            Some(Platform::XoChip) => xochip.push(evidence),
+            Some(_) => schip.push(evidence),
+            None => {}
+        }
+    }
+    if !xochip.is_empty() {
+        Some((Platform::XoChip, xochip))
+    } else if !schip.is_empty() {
+        Some((Platform::SuperChip, schip))
+    } else {
+        None
+    }
+}
+
+pub fn detect(rom: &[u8]) -> Detection {
+    let max = MEMORY_SIZE - START_ADDRESS;
+    if rom.len() > max {
+        return Detection {
+            platform: Platform::XoChip,
+            confidence: Confidence::High,
+            evidence: vec![format!("{} bytes do not fit in 4K", rom.len())],
+        };
+    }
+
+    let (code, complete) = reachable(rom);
+    if let Some((platform, evidence)) = extensions(rom, code.into_iter()) {
+        // Uma instrução só pode ser coincidência (um salto que cai em dados, por exemplo)
+        let confidence = if evidence.len() > 1 {
+            Confidence::High
+        } else {
+            Confidence::Medium
+        };
+        return Detection {
+            platform,
+            confidence,
+            evidence,
+        };
+    }
+
+    // Código que só é alcançado pelo Bnnn fica de fora do fluxo, então nesse caso a ROM inteira é
+    // varrida. Sem Bnnn o resto da ROM são só dados
+    if !complete && let Some((platform, evidence)) = extensions(rom, (0..rom.len()).step_by(2)) {
+        return Detection {
+            platform,
+            confidence: Confidence::Low,
+            evidence,
+        };
+    }
+
+    // Nada fora do CHIP-8, mas alguns jogos de SUPER-CHIP só dependem das quirks
+    Detection {
+        platform: Platform::Chip8,
+        confidence: Confidence::Medium,
+        evidence: Vec::new(),
+    }
+}
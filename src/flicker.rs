@@ -0,0 +1,167 @@
+use crate::palette::{Palette, blend};
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+
+// Como o Dxyn desenha com XOR, os jogos apagam e redesenham os sprites a cada quadro e eles
+// piscam. Esses modos combinam quadros consecutivos para esconder a piscada
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FlickerMode {
+    #[default]
+    Off,
+    // Média das cores dos últimos N quadros
+    Blend(usize),
+    // Fósforo: o pixel acende na hora e apaga aos poucos. O valor é quanto do brilho anterior
+    // sobra a cada quadro (0 = apaga na hora, perto de 1 = rastro longo)
+    Decay(f32),
+    // Um pixel fica aceso se estava aceso neste quadro ou no anterior
+    OrLastTwo,
+}
+
+impl FlickerMode {
+    // Ordem usada pela tecla que alterna os modos
+    pub fn next(self) -> FlickerMode {
+        match self {
+            FlickerMode::Off => FlickerMode::OrLastTwo,
+            FlickerMode::OrLastTwo => FlickerMode::Blend(3),
+            FlickerMode::Blend(_) => FlickerMode::Decay(0.6),
+            FlickerMode::Decay(_) => FlickerMode::Off,
+        }
+    }
+}
+
+impl fmt::Display for FlickerMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FlickerMode::Off => write!(f, "off"),
+            FlickerMode::Blend(frames) => write!(f, "blend:{}", frames),
+            FlickerMode::Decay(persistence) => write!(f, "decay:{}", persistence),
+            FlickerMode::OrLastTwo => write!(f, "or"),
+        }
+    }
+}
+
+// "off", "or", "blend", "blend:4", "decay", "decay:0.8"
+impl FromStr for FlickerMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, arg) = match s.split_once(':') {
+            Some((name, arg)) => (name, Some(arg)),
+            None => (s, None),
+        };
+        let invalid = || format!("invalid argument in flicker mode '{}'", s);
+        match (name.to_ascii_lowercase().as_str(), arg) {
+            ("off", None) => Ok(FlickerMode::Off),
+            ("or", None) => Ok(FlickerMode::OrLastTwo),
+            ("blend", None) => Ok(FlickerMode::Blend(3)),
+            ("blend", Some(n)) => match n.parse() {
+                Ok(frames) if frames >= 1 => Ok(FlickerMode::Blend(frames)),
+                _ => Err(invalid()),
+            },
+            ("decay", None) => Ok(FlickerMode::Decay(0.6)),
+            ("decay", Some(p)) => match p.parse() {
+                Ok(persistence) if (0.0..1.0).contains(&persistence) => {
+                    Ok(FlickerMode::Decay(persistence))
+                }
+                _ => Err(invalid()),
+            },
+            _ => Err(format!(
+                "unknown flicker mode '{}' (expected off, or, blend[:N] or decay[:P])",
+                s
+            )),
+        }
+    }
+}
+
+// Guarda os quadros anteriores necessários para o modo atual. Recebe os índices de plano de cada
+// pixel (0 a 3, como no Palette::colorize) e escreve as cores finais
+#[derive(Default)]
+pub struct FlickerFilter {
+    mode: FlickerMode,
+    history: VecDeque<Vec<u8>>,
+    // Decay: último índice aceso de cada pixel e quanto do brilho dele ainda sobra (1 = aceso)
+    glow: Vec<(u8, f32)>,
+}
+
+impl FlickerFilter {
+    pub fn new(mode: FlickerMode) -> Self {
+        FlickerFilter {
+            mode,
+            ..FlickerFilter::default()
+        }
+    }
+
+    pub fn mode(&self) -> FlickerMode {
+        self.mode
+    }
+
+    // Troca o modo e descarta o histórico, que não faz sentido entre modos diferentes
+    pub fn set_mode(&mut self, mode: FlickerMode) {
+        self.mode = mode;
+        self.history.clear();
+        self.glow.clear();
+    }
+
+    pub fn process(&mut self, pixels: &[u8], palette: &Palette, out: &mut [u32]) {
+        match self.mode {
+            FlickerMode::Off => palette.colorize(pixels.iter().copied(), out),
+            FlickerMode::OrLastTwo => {
+                self.push_history(pixels, 2);
+                match self.history.front() {
+                    Some(previous) if self.history.len() == 2 => palette.colorize(
+                        pixels.iter().zip(previous).map(|(&cur, &prev)| cur | prev),
+                        out,
+                    ),
+                    _ => palette.colorize(pixels.iter().copied(), out),
+                }
+            }
+            FlickerMode::Blend(frames) => {
+                self.push_history(pixels, frames.max(1));
+                let count = self.history.len() as u32;
+                for (index, dst) in out.iter_mut().enumerate() {
+                    let mut sum = [0u32; 3];
+                    for frame in &self.history {
+                        let color = palette.color(frame[index]);
+                        sum[0] += (color >> 16) & 0xFF;
+                        sum[1] += (color >> 8) & 0xFF;
+                        sum[2] += color & 0xFF;
+                    }
+                    *dst = ((sum[0] / count) << 16) | ((sum[1] / count) << 8) | (sum[2] / count);
+                }
+            }
+            FlickerMode::Decay(persistence) => {
+                if self.glow.len() != pixels.len() {
+                    self.glow.clear();
+                    self.glow.resize(pixels.len(), (0, 0.0));
+                }
+                // Acende na hora, mas apaga devagar em direção ao fundo. O que decide é o índice
+                // do pixel e não o brilho das cores: em paletas como a lcd o fundo é mais claro
+                let background = palette.color(0);
+                for ((dst, &index), glow) in out.iter_mut().zip(pixels).zip(&mut self.glow) {
+                    if index != 0 {
+                        *glow = (index, 1.0);
+                    } else {
+                        glow.1 *= persistence;
+                    }
+                    *dst = blend(background, palette.color(glow.0), glow.1);
+                }
+            }
+        }
+    }
+
+    fn push_history(&mut self, pixels: &[u8], frames: usize) {
+        // Reaproveita o buffer do quadro mais antigo em vez de alocar outro
+        let mut frame = if self.history.len() >= frames {
+            self.history.pop_front().unwrap()
+        } else {
+            Vec::with_capacity(pixels.len())
+        };
+        frame.clear();
+        frame.extend_from_slice(pixels);
+        self.history.push_back(frame);
+        while self.history.len() > frames {
+            self.history.pop_front();
+        }
+    }
+}
@@ -0,0 +1,319 @@
+// Forma decodificada de um opcode, com os operandos já separados. O Chip8 guarda uma por endereço
+// (o cache de decodificação) para não refazer a busca e as máscaras a cada ciclo. Os registradores
+// são guardados como u8 para a instrução inteira caber em 4 bytes
+//
+// A decodificação não depende das quirks: o que muda com elas (Bnnn ou Bxnn, por exemplo) é
+// decidido na execução, então trocar as quirks não invalida o cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    // 00E0
+    Clear,
+    // 00EE
+    Return,
+    // 1nnn
+    Jump(u16),
+    // 2nnn
+    Call(u16),
+    // 3xkk
+    SkipEqual(u8, u8),
+    // 4xkk
+    SkipNotEqual(u8, u8),
+    // 5xy0
+    SkipEqualRegister(u8, u8),
+    // 9xy0
+    SkipNotEqualRegister(u8, u8),
+    // 6xkk
+    Load(u8, u8),
+    // 7xkk
+    AddImmediate(u8, u8),
+    // 8xy0
+    Move(u8, u8),
+    // 8xy1
+    Or(u8, u8),
+    // 8xy2
+    And(u8, u8),
+    // 8xy3
+    Xor(u8, u8),
+    // 8xy4
+    Add(u8, u8),
+    // 8xy5
+    Sub(u8, u8),
+    // 8xy6
+    ShiftRight(u8, u8),
+    // 8xy7
+    SubReverse(u8, u8),
+    // 8xyE
+    ShiftLeft(u8, u8),
+    // Annn
+    LoadI(u16),
+    // Bnnn, com o x do opcode para a quirk jump_vx
+    JumpOffset(u8, u16),
+    // Cxkk
+    Random(u8, u8),
+    // Dxyn
+    Draw(u8, u8, u8),
+    // Ex9E
+    SkipKey(u8),
+    // ExA1
+    SkipNotKey(u8),
+    // Fx07
+    LoadDelay(u8),
+    // Fx0A
+    WaitKey(u8),
+    // Fx15
+    SetDelay(u8),
+    // Fx18
+    SetSound(u8),
+    // Fx1E
+    AddI(u8),
+    // Fx29
+    Font(u8),
+    // Fx33
+    Bcd(u8),
+    // Fx55
+    Store(u8),
+    // Fx65
+    Restore(u8),
+    // 00Cn (SUPER-CHIP)
+    ScrollDown(u8),
+    // 00FB (SUPER-CHIP)
+    ScrollRight,
+    // 00FC (SUPER-CHIP)
+    ScrollLeft,
+    // 00FE (SUPER-CHIP)
+    LowRes,
+    // 00FF (SUPER-CHIP)
+    HighRes,
+    // Dxy0 (SUPER-CHIP)
+    DrawLarge(u8, u8),
+    // Fx30 (SUPER-CHIP)
+    BigFont(u8),
+    // Fx75 (SUPER-CHIP)
+    SaveFlags(u8),
+    // Fx85 (SUPER-CHIP)
+    LoadFlags(u8),
+    // 00Dn (XO-CHIP)
+    ScrollUp(u8),
+    // F000 NNNN (XO-CHIP), com o endereço na palavra seguinte
+    LongLoadI,
+    // Fn01 (XO-CHIP)
+    Plane(u8),
+    // F002 (XO-CHIP)
+    Audio,
+    // Fx3A (XO-CHIP)
+    Pitch(u8),
+    // 02A0 (CHIP-8X)
+    Background,
+    // 5xy1 (CHIP-8X)
+    AddNibbles(u8, u8),
+    // Bxyn (CHIP-8X)
+    Color(u8, u8, u8),
+    // ExF2 (CHIP-8X)
+    SkipKey2(u8),
+    // ExF5 (CHIP-8X)
+    SkipNotKey2(u8),
+    // FxF8 (CHIP-8X) e Fx03 (CHIP-8E)
+    Output(u8),
+    // FxFB (CHIP-8X) e FxE3 / FxE7 (CHIP-8E)
+    Input(u8),
+    // 00ED (CHIP-8E) e 00FD (SUPER-CHIP)
+    Halt,
+    // 0151 (CHIP-8E)
+    WaitDelay,
+    // 0188 (CHIP-8E)
+    Skip,
+    // 5xy1 (CHIP-8E)
+    SkipGreater(u8, u8),
+    // 5xy2 (CHIP-8E e XO-CHIP)
+    StoreRange(u8, u8),
+    // 5xy3 (CHIP-8E e XO-CHIP)
+    LoadRange(u8, u8),
+    // BBnn (CHIP-8E)
+    BranchBack(u8),
+    // BFnn (CHIP-8E)
+    BranchForward(u8),
+    // Fx1B (CHIP-8E)
+    SkipBytes(u8),
+    // Fx4F (CHIP-8E)
+    SetDelayWait(u8),
+    // Qualquer outro opcode, que é ignorado
+    Unknown(u16),
+}
+
+// Tabela de decodificação de cada variante. O CHIP-8X e o CHIP-8E são interpretadores do VIP
+// modificados: trocam ou acrescentam alguns opcodes e o resto é o CHIP-8 de sempre. O CHIP-10 só
+// muda a tela, então usa a tabela do CHIP-8. O SUPER-CHIP acrescenta opcodes ao CHIP-8 e o
+// XO-CHIP acrescenta aos do SUPER-CHIP
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InstructionSet {
+    #[default]
+    Chip8,
+    Chip8X,
+    Chip8E,
+    SuperChip,
+    XoChip,
+}
+
+impl InstructionSet {
+    pub fn decode(self, opcode: u16) -> Instruction {
+        match self {
+            InstructionSet::Chip8 => decode(opcode),
+            InstructionSet::Chip8X => decode_chip8x(opcode),
+            InstructionSet::Chip8E => decode_chip8e(opcode),
+            InstructionSet::SuperChip => decode_superchip(opcode),
+            InstructionSet::XoChip => decode_xochip(opcode),
+        }
+    }
+}
+
+// SUPER-CHIP 1.1: rolagem da tela, alta resolução, sprites de 16x16, a fonte grande e as flags do
+// HP-48. O 00FD sai do interpretador, o que aqui é o mesmo que o 00ED do CHIP-8E
+fn decode_superchip(opcode: u16) -> Instruction {
+    let x = ((opcode & 0x0F00) >> 8) as u8;
+    let y = ((opcode & 0x00F0) >> 4) as u8;
+    let n = (opcode & 0x000F) as u8;
+    let kk = (opcode & 0x00FF) as u8;
+    match opcode {
+        0x00C0..=0x00CF => Instruction::ScrollDown(n),
+        0x00FB => Instruction::ScrollRight,
+        0x00FC => Instruction::ScrollLeft,
+        0x00FD => Instruction::Halt,
+        0x00FE => Instruction::LowRes,
+        0x00FF => Instruction::HighRes,
+        _ => match (opcode >> 12, kk) {
+            (0xD, _) if n == 0 => Instruction::DrawLarge(x, y),
+            (0xF, 0x30) => Instruction::BigFont(x),
+            (0xF, 0x75) => Instruction::SaveFlags(x),
+            (0xF, 0x85) => Instruction::LoadFlags(x),
+            _ => decode(opcode),
+        },
+    }
+}
+
+// XO-CHIP: o SUPER-CHIP mais a rolagem para cima, o I de 16 bits (F000 NNNN ocupa 4 bytes), os
+// planos de desenho, os intervalos de registradores (5xy2 / 5xy3, os mesmos do CHIP-8E) e o áudio
+// (F002 e Fx3A)
+fn decode_xochip(opcode: u16) -> Instruction {
+    let x = ((opcode & 0x0F00) >> 8) as u8;
+    let y = ((opcode & 0x00F0) >> 4) as u8;
+    let n = (opcode & 0x000F) as u8;
+    let kk = (opcode & 0x00FF) as u8;
+    match opcode {
+        0x00D0..=0x00DF => Instruction::ScrollUp(n),
+        0xF000 => Instruction::LongLoadI,
+        0xF002 => Instruction::Audio,
+        _ => match (opcode >> 12, kk) {
+            (0x5, _) if n == 2 => Instruction::StoreRange(x, y),
+            (0x5, _) if n == 3 => Instruction::LoadRange(x, y),
+            (0xF, 0x01) => Instruction::Plane(x),
+            (0xF, 0x3A) => Instruction::Pitch(x),
+            _ => decode_superchip(opcode),
+        },
+    }
+}
+
+// CHIP-8X: cor (02A0 e Bxyn, que toma o lugar do Bnnn), o segundo teclado (ExF2 / ExF5) e as
+// portas de E/S
+fn decode_chip8x(opcode: u16) -> Instruction {
+    let x = ((opcode & 0x0F00) >> 8) as u8;
+    let y = ((opcode & 0x00F0) >> 4) as u8;
+    let n = (opcode & 0x000F) as u8;
+    let kk = (opcode & 0x00FF) as u8;
+    match opcode {
+        0x02A0 => Instruction::Background,
+        _ => match (opcode >> 12, kk) {
+            (0x5, _) if n == 1 => Instruction::AddNibbles(x, y),
+            (0xB, _) => Instruction::Color(x, y, n),
+            (0xE, 0xF2) => Instruction::SkipKey2(x),
+            (0xE, 0xF5) => Instruction::SkipNotKey2(x),
+            (0xF, 0xF8) => Instruction::Output(x),
+            (0xF, 0xFB) => Instruction::Input(x),
+            _ => decode(opcode),
+        },
+    }
+}
+
+// CHIP-8E: mais skips, desvios relativos (BBnn e BFnn no lugar de alguns Bnnn) e as portas de E/S.
+// O 00F2 é um NOP, que a tabela do CHIP-8 já ignora como opcode desconhecido
+fn decode_chip8e(opcode: u16) -> Instruction {
+    let x = ((opcode & 0x0F00) >> 8) as u8;
+    let y = ((opcode & 0x00F0) >> 4) as u8;
+    let n = (opcode & 0x000F) as u8;
+    let kk = (opcode & 0x00FF) as u8;
+    match opcode {
+        0x00ED => Instruction::Halt,
+        0x0151 => Instruction::WaitDelay,
+        0x0188 => Instruction::Skip,
+        _ => match (opcode >> 12, kk) {
+            (0x5, _) if n == 1 => Instruction::SkipGreater(x, y),
+            (0x5, _) if n == 2 => Instruction::StoreRange(x, y),
+            (0x5, _) if n == 3 => Instruction::LoadRange(x, y),
+            (0xB, _) if x == 0xB => Instruction::BranchBack(kk),
+            (0xB, _) if x == 0xF => Instruction::BranchForward(kk),
+            (0xF, 0x03) => Instruction::Output(x),
+            (0xF, 0x1B) => Instruction::SkipBytes(x),
+            (0xF, 0x4F) => Instruction::SetDelayWait(x),
+            // O FxE3 espera o strobe do periférico antes de ler; o FxE7 lê direto
+            (0xF, 0xE3 | 0xE7) => Instruction::Input(x),
+            _ => decode(opcode),
+        },
+    }
+}
+
+// Tabela do CHIP-8
+pub fn decode(opcode: u16) -> Instruction {
+    let x = ((opcode & 0x0F00) >> 8) as u8;
+    let y = ((opcode & 0x00F0) >> 4) as u8;
+    let n = (opcode & 0x000F) as u8;
+    let kk = (opcode & 0x00FF) as u8;
+    let nnn = opcode & 0x0FFF;
+    match opcode & 0xF000 {
+        0x0000 => match opcode {
+            0x00E0 => Instruction::Clear,
+            0x00EE => Instruction::Return,
+            _ => Instruction::Unknown(opcode),
+        },
+        0x1000 => Instruction::Jump(nnn),
+        0x2000 => Instruction::Call(nnn),
+        0x3000 => Instruction::SkipEqual(x, kk),
+        0x4000 => Instruction::SkipNotEqual(x, kk),
+        0x5000 if n == 0 => Instruction::SkipEqualRegister(x, y),
+        0x6000 => Instruction::Load(x, kk),
+        0x7000 => Instruction::AddImmediate(x, kk),
+        0x8000 => match n {
+            0x0 => Instruction::Move(x, y),
+            0x1 => Instruction::Or(x, y),
+            0x2 => Instruction::And(x, y),
+            0x3 => Instruction::Xor(x, y),
+            0x4 => Instruction::Add(x, y),
+            0x5 => Instruction::Sub(x, y),
+            0x6 => Instruction::ShiftRight(x, y),
+            0x7 => Instruction::SubReverse(x, y),
+            0xE => Instruction::ShiftLeft(x, y),
+            _ => Instruction::Unknown(opcode),
+        },
+        0x9000 if n == 0 => Instruction::SkipNotEqualRegister(x, y),
+        0xA000 => Instruction::LoadI(nnn),
+        0xB000 => Instruction::JumpOffset(x, nnn),
+        0xC000 => Instruction::Random(x, kk),
+        0xD000 => Instruction::Draw(x, y, n),
+        0xE000 => match kk {
+            0x9E => Instruction::SkipKey(x),
+            0xA1 => Instruction::SkipNotKey(x),
+            _ => Instruction::Unknown(opcode),
+        },
+        0xF000 => match kk {
+            0x07 => Instruction::LoadDelay(x),
+            0x0A => Instruction::WaitKey(x),
+            0x15 => Instruction::SetDelay(x),
+            0x18 => Instruction::SetSound(x),
+            0x1E => Instruction::AddI(x),
+            0x29 => Instruction::Font(x),
+            0x33 => Instruction::Bcd(x),
+            0x55 => Instruction::Store(x),
+            0x65 => Instruction::Restore(x),
+            _ => Instruction::Unknown(opcode),
+        },
+        _ => Instruction::Unknown(opcode),
+    }
+}
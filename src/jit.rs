@@ -0,0 +1,510 @@
+use crate::cpu::{Chip8, Chip8Error};
+use crate::instruction::{Instruction, InstructionSet};
+use crate::quirks::Quirks;
+use crate::recompiler::written_range;
+use cranelift_codegen::Context;
+use cranelift_codegen::ir::condcodes::IntCC;
+use cranelift_codegen::ir::{AbiParam, InstBuilder, MemFlags, Value, types};
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext};
+use cranelift_jit::{JITBuilder, JITModule};
+use cranelift_module::{Module, default_libcall_names};
+use std::mem::offset_of;
+
+// Compilador de blocos para código nativo com o Cranelift (feature `jit`). Segue o mesmo contrato
+// do recompilador de closures (recompiler.rs), um passo adiante: em vez de uma closure por
+// instrução, o bloco inteiro vira uma função nativa que recebe um ponteiro para o próprio Chip8.
+// Os registradores V, o I, o pc e os timers são lidos e escritos direto nos campos da struct
+// (offset_of!), então não existe um contexto separado para copiar antes e depois de cada bloco.
+//
+// Só a aritmética, o Annn / Fx1E / Fx29, os timers, o 1nnn e os skips de registrador viram código
+// nativo. O resto (CALL/RET, Dxyn, teclas, Cxkk, Fx33 / Fx55 / Fx65...) termina o bloco e é
+// executado pelo interpretador (Chip8::execute). Como nada do código nativo escreve na memória, as
+// escritas que mudam código só vêm das instruções interpretadas, e é nelas que a invalidação é
+// feita. Como no recompilador, o código nativo não imprime o trace
+
+// Blocos longos demais são divididos
+const MAX_BLOCK_LENGTH: usize = 64;
+
+type BlockFn = unsafe extern "C" fn(*mut Chip8);
+
+#[derive(Clone, Copy)]
+enum Entry {
+    // Função nativa e quantas instruções ela executa
+    Native { function: BlockFn, length: usize },
+    // A instrução do endereço não é compilada e roda pelo interpretador, já decodificada
+    Interpreted(Instruction),
+}
+
+pub struct Jit {
+    module: JITModule,
+    context: Context,
+    builder_context: FunctionBuilderContext,
+    entries: Vec<Option<Entry>>,
+    // Bytes da memória lidos por algum bloco compilado. Uma escrita num deles descarta todo o
+    // código, como no recompilador
+    translated: Vec<bool>,
+    // As quirks que mudam a aritmética (vf_reset, shift_vx) são fixadas no código gerado. Trocar
+    // as quirks (pelo menu de pausa, por exemplo) descarta os blocos
+    quirks: Quirks,
+    // Blocos compilados desde a criação
+    pub compilations: usize,
+}
+
+fn new_module() -> JITModule {
+    let builder = JITBuilder::with_flags(&[("opt_level", "speed")], default_libcall_names())
+        .expect("host machine is not supported by Cranelift");
+    JITModule::new(builder)
+}
+
+impl Jit {
+    pub fn new(memory_size: usize) -> Jit {
+        let module = new_module();
+        let context = module.make_context();
+        Jit {
+            module,
+            context,
+            builder_context: FunctionBuilderContext::new(),
+            entries: vec![None; memory_size],
+            translated: vec![false; memory_size],
+            quirks: Quirks::default(),
+            compilations: 0,
+        }
+    }
+
+    // Descarta todo o código gerado. Necessário depois de trocar a memória por fora das
+    // instruções: load_bytes, escrita direta em `memory` ou um save state carregado
+    pub fn flush(&mut self) {
+        let old = std::mem::replace(&mut self.module, new_module());
+        self.entries.fill(None);
+        self.translated.fill(false);
+        // SAFETY: nenhum ponteiro para as funções do módulo antigo sobrou em `entries`
+        unsafe { old.free_memory() };
+    }
+
+    // Mesmo contrato do Chip8::run_frame. Um bloco só é chamado se couber inteiro no que sobra do
+    // quadro; senão o quadro é completado pelo interpretador, então a contagem de instruções por
+    // quadro (e com ela os timers) é exata
+    pub fn run_frame(&mut self, chip8: &mut Chip8, instructions: usize) -> Result<(), Chip8Error> {
+        if self.translated.len() != chip8.memory.len() {
+            self.entries = vec![None; chip8.memory.len()];
+            self.translated = vec![false; chip8.memory.len()];
+            self.flush();
+        }
+        if self.quirks != chip8.quirks {
+            self.flush();
+            self.quirks = chip8.quirks;
+        }
+        chip8.tick_timers();
+        let mut remaining = instructions;
+        while remaining > 0 {
+            let start = chip8.wrap_address(chip8.pc) as usize;
+            let entry = match self.entries[start] {
+                Some(entry) => entry,
+                None => {
+                    let entry = self.compile(chip8, start as u16);
+                    self.entries[start] = Some(entry);
+                    entry
+                }
+            };
+            match entry {
+                Entry::Native { function, length } if length <= remaining => {
+                    // SAFETY: a função foi gerada por `compile` para este módulo e só acessa os
+                    // campos v, i, pc e timers do Chip8 recebido
+                    unsafe { function(chip8) };
+                    remaining -= length;
+                }
+                // O bloco não cabe: o fim do quadro vai pelo interpretador, sem compilar os
+                // pedaços que sobraram
+                Entry::Native { .. } => {
+                    while remaining > 0 && !chip8.waiting_for_display() {
+                        self.interpret(chip8)?;
+                        remaining -= 1;
+                    }
+                }
+                Entry::Interpreted(instruction) => {
+                    self.execute(chip8, instruction)?;
+                    remaining -= 1;
+                }
+            }
+            // O Dxyn nunca é compilado: a quirk display_wait só para o quadro depois de uma
+            // instrução do interpretador
+            if chip8.waiting_for_display() {
+                break;
+            }
+        }
+        chip8.end_frame();
+        Ok(())
+    }
+
+    // Uma instrução pelo interpretador, invalidando o código se ela escrever em cima de um bloco
+    fn interpret(&mut self, chip8: &mut Chip8) -> Result<(), Chip8Error> {
+        chip8.pc = chip8.wrap_address(chip8.pc);
+        let pc = chip8.pc as usize;
+        let opcode = (chip8.read_byte(pc) as u16) << 8 | chip8.read_byte(pc + 1) as u16;
+        self.execute(chip8, chip8.instruction_set().decode(opcode))
+    }
+
+    fn execute(&mut self, chip8: &mut Chip8, instruction: Instruction) -> Result<(), Chip8Error> {
+        let written = written_range(chip8, instruction);
+        chip8.execute(instruction)?;
+        if let Some((addr, len)) = written {
+            let size = self.translated.len();
+            if (addr..addr + len).any(|a| self.translated[a % size]) {
+                self.flush();
+            }
+        }
+        Ok(())
+    }
+
+    fn compile(&mut self, chip8: &Chip8, start: u16) -> Entry {
+        let size = self.translated.len();
+        let mut instructions = Vec::new();
+        let mut addr = start;
+        while instructions.len() < MAX_BLOCK_LENGTH {
+            let high_byte = chip8.read_byte(addr as usize) as u16;
+            let low_byte = chip8.read_byte(addr as usize + 1) as u16;
+            self.translated[addr as usize % size] = true;
+            self.translated[(addr as usize + 1) % size] = true;
+            let instruction = chip8.instruction_set().decode((high_byte << 8) | low_byte);
+            if !compiled(instruction, chip8.instruction_set()) {
+                if instructions.is_empty() {
+                    return Entry::Interpreted(instruction);
+                }
+                break;
+            }
+            instructions.push(instruction);
+            if ends_block(instruction) {
+                break;
+            }
+            addr = chip8.wrap_address(addr.wrapping_add(2));
+        }
+        match self.define(start, &instructions) {
+            Some(function) => {
+                self.compilations += 1;
+                Entry::Native {
+                    function,
+                    length: instructions.len(),
+                }
+            }
+            // Um erro do Cranelift não para a emulação: o endereço fica com o interpretador
+            None => {
+                self.module.clear_context(&mut self.context);
+                Entry::Interpreted(instructions[0])
+            }
+        }
+    }
+
+    // Compila as instruções num bloco. None se o Cranelift recusar a função
+    fn define(&mut self, start: u16, instructions: &[Instruction]) -> Option<BlockFn> {
+        let mut signature = self.module.make_signature();
+        let pointer = self.module.target_config().pointer_type();
+        signature.params.push(AbiParam::new(pointer));
+        let id = self.module.declare_anonymous_function(&signature).ok()?;
+        self.context.func.signature = signature;
+
+        let mut builder = FunctionBuilder::new(&mut self.context.func, &mut self.builder_context);
+        let entry = builder.create_block();
+        builder.append_block_params_for_function_params(entry);
+        builder.switch_to_block(entry);
+        builder.seal_block(entry);
+        let base = builder.block_params(entry)[0];
+        let mut emitter = Emitter {
+            builder,
+            base,
+            v: [None; 16],
+            v_written: [false; 16],
+            i: None,
+            quirks: self.quirks,
+            memory_size: self.translated.len(),
+        };
+        let next = emitter.wrap(start.wrapping_add(2 * instructions.len() as u16));
+        let mut pc = None;
+        for (n, &instruction) in instructions.iter().enumerate() {
+            let addr = emitter.wrap(start.wrapping_add(2 * n as u16));
+            pc = emitter.instruction(instruction, addr);
+        }
+        let pc = pc.unwrap_or_else(|| emitter.builder.ins().iconst(types::I16, next as i64));
+        emitter.finish(pc);
+
+        self.module.define_function(id, &mut self.context).ok()?;
+        self.module.clear_context(&mut self.context);
+        self.module.finalize_definitions().ok()?;
+        let code = self.module.get_finalized_function(id);
+        // SAFETY: a função foi declarada com a assinatura de BlockFn (um ponteiro, sem retorno)
+        Some(unsafe { std::mem::transmute::<*const u8, BlockFn>(code) })
+    }
+}
+
+// Instruções que viram código nativo
+fn compiled(instruction: Instruction, instruction_set: InstructionSet) -> bool {
+    // No XO-CHIP um skip pula os 4 bytes de um F000 NNNN, o que só se sabe lendo a memória na
+    // hora, então os skips ficam com o interpretador
+    if instruction_set == InstructionSet::XoChip && ends_block(instruction) {
+        return matches!(instruction, Instruction::Jump(_));
+    }
+    matches!(
+        instruction,
+        Instruction::Load(..)
+            | Instruction::AddImmediate(..)
+            | Instruction::Move(..)
+            | Instruction::Or(..)
+            | Instruction::And(..)
+            | Instruction::Xor(..)
+            | Instruction::Add(..)
+            | Instruction::Sub(..)
+            | Instruction::SubReverse(..)
+            | Instruction::ShiftRight(..)
+            | Instruction::ShiftLeft(..)
+            | Instruction::LoadI(_)
+            | Instruction::AddI(_)
+            | Instruction::Font(_)
+            | Instruction::LoadDelay(_)
+            | Instruction::SetDelay(_)
+            | Instruction::SetSound(_)
+            | Instruction::Unknown(_)
+    ) || ends_block(instruction)
+}
+
+// Instruções compiladas que mudam o pc e por isso fecham o bloco
+fn ends_block(instruction: Instruction) -> bool {
+    matches!(
+        instruction,
+        Instruction::Jump(_)
+            | Instruction::SkipEqual(..)
+            | Instruction::SkipNotEqual(..)
+            | Instruction::SkipEqualRegister(..)
+            | Instruction::SkipNotEqualRegister(..)
+    )
+}
+
+// Gera o corpo de um bloco. Os V e o I são carregados da struct só no primeiro uso e os que foram
+// alterados são gravados uma vez no fim; como o bloco não tem desvios (os skips viram um select do
+// novo pc), basta guardar o valor atual de cada registrador
+struct Emitter<'a> {
+    builder: FunctionBuilder<'a>,
+    base: Value,
+    v: [Option<Value>; 16],
+    v_written: [bool; 16],
+    i: Option<Value>,
+    quirks: Quirks,
+    // Tamanho da memória, para os endereços calculados darem a volta como no interpretador
+    memory_size: usize,
+}
+
+impl Emitter<'_> {
+    fn flags() -> MemFlags {
+        MemFlags::trusted()
+    }
+
+    fn wrap(&self, addr: u16) -> u16 {
+        (addr as usize % self.memory_size) as u16
+    }
+
+    fn v_offset(x: usize) -> i32 {
+        (offset_of!(Chip8, v) + x) as i32
+    }
+
+    fn get(&mut self, x: u8) -> Value {
+        let x = x as usize;
+        match self.v[x] {
+            Some(value) => value,
+            None => {
+                let value =
+                    self.builder
+                        .ins()
+                        .load(types::I8, Self::flags(), self.base, Self::v_offset(x));
+                self.v[x] = Some(value);
+                value
+            }
+        }
+    }
+
+    fn set(&mut self, x: u8, value: Value) {
+        self.v[x as usize] = Some(value);
+        self.v_written[x as usize] = true;
+    }
+
+    fn get_i(&mut self) -> Value {
+        match self.i {
+            Some(value) => value,
+            None => {
+                let offset = offset_of!(Chip8, i) as i32;
+                let value = self
+                    .builder
+                    .ins()
+                    .load(types::I16, Self::flags(), self.base, offset);
+                self.i = Some(value);
+                value
+            }
+        }
+    }
+
+    fn timer(&mut self, offset: usize) -> Value {
+        self.builder
+            .ins()
+            .load(types::I8, Self::flags(), self.base, offset as i32)
+    }
+
+    fn set_timer(&mut self, offset: usize, value: Value) {
+        self.builder
+            .ins()
+            .store(Self::flags(), value, self.base, offset as i32);
+    }
+
+    fn constant(&mut self, value: u8) -> Value {
+        self.builder.ins().iconst(types::I8, value as i64)
+    }
+
+    fn vf_reset(&mut self) {
+        if self.quirks.vf_reset {
+            let zero = self.constant(0);
+            self.set(0xF, zero);
+        }
+    }
+
+    // Gera uma instrução que está em `addr`. Retorna o novo pc se ela fecha o bloco
+    fn instruction(&mut self, instruction: Instruction, addr: u16) -> Option<Value> {
+        match instruction {
+            Instruction::Load(x, kk) => {
+                let value = self.constant(kk);
+                self.set(x, value);
+            }
+            Instruction::AddImmediate(x, kk) => {
+                let vx = self.get(x);
+                let value = self.builder.ins().iadd_imm(vx, kk as i64);
+                self.set(x, value);
+            }
+            Instruction::Move(x, y) => {
+                let vy = self.get(y);
+                self.set(x, vy);
+            }
+            Instruction::Or(x, y) | Instruction::And(x, y) | Instruction::Xor(x, y) => {
+                let (vx, vy) = (self.get(x), self.get(y));
+                let value = match instruction {
+                    Instruction::Or(..) => self.builder.ins().bor(vx, vy),
+                    Instruction::And(..) => self.builder.ins().band(vx, vy),
+                    _ => self.builder.ins().bxor(vx, vy),
+                };
+                self.set(x, value);
+                self.vf_reset();
+            }
+            Instruction::Add(x, y) => {
+                let (vx, vy) = (self.get(x), self.get(y));
+                let sum = self.builder.ins().iadd(vx, vy);
+                let carry = self.builder.ins().icmp(IntCC::UnsignedLessThan, sum, vx);
+                self.set(x, sum);
+                self.set(0xF, carry);
+            }
+            Instruction::Sub(x, y) | Instruction::SubReverse(x, y) => {
+                let (vx, vy) = (self.get(x), self.get(y));
+                let (a, b) = match instruction {
+                    Instruction::Sub(..) => (vx, vy),
+                    _ => (vy, vx),
+                };
+                let difference = self.builder.ins().isub(a, b);
+                let no_borrow = self
+                    .builder
+                    .ins()
+                    .icmp(IntCC::UnsignedGreaterThanOrEqual, a, b);
+                self.set(x, difference);
+                self.set(0xF, no_borrow);
+            }
+            Instruction::ShiftRight(x, y) => {
+                let source = self.get(if self.quirks.shift_vx { x } else { y });
+                let value = self.builder.ins().ushr_imm(source, 1);
+                let flag = self.builder.ins().band_imm(source, 1);
+                self.set(x, value);
+                self.set(0xF, flag);
+            }
+            Instruction::ShiftLeft(x, y) => {
+                let source = self.get(if self.quirks.shift_vx { x } else { y });
+                let value = self.builder.ins().ishl_imm(source, 1);
+                let flag = self.builder.ins().ushr_imm(source, 7);
+                self.set(x, value);
+                self.set(0xF, flag);
+            }
+            Instruction::LoadI(nnn) => {
+                self.i = Some(self.builder.ins().iconst(types::I16, nnn as i64));
+            }
+            Instruction::AddI(x) => {
+                let (i, vx) = (self.get_i(), self.get(x));
+                let vx = self.builder.ins().uextend(types::I16, vx);
+                self.i = Some(self.builder.ins().iadd(i, vx));
+            }
+            Instruction::Font(x) => {
+                let vx = self.get(x);
+                let digit = self.builder.ins().uextend(types::I16, vx);
+                self.i = Some(self.builder.ins().imul_imm(digit, 5));
+            }
+            Instruction::LoadDelay(x) => {
+                let value = self.timer(offset_of!(Chip8, delay_timer));
+                self.set(x, value);
+            }
+            Instruction::SetDelay(x) => {
+                let vx = self.get(x);
+                self.set_timer(offset_of!(Chip8, delay_timer), vx);
+            }
+            Instruction::SetSound(x) => {
+                let vx = self.get(x);
+                self.set_timer(offset_of!(Chip8, sound_timer), vx);
+            }
+            Instruction::Jump(nnn) => {
+                return Some(self.builder.ins().iconst(types::I16, nnn as i64));
+            }
+            Instruction::SkipEqual(x, kk) | Instruction::SkipNotEqual(x, kk) => {
+                let vx = self.get(x);
+                let cc = match instruction {
+                    Instruction::SkipEqual(..) => IntCC::Equal,
+                    _ => IntCC::NotEqual,
+                };
+                let condition = self.builder.ins().icmp_imm(cc, vx, kk as i64);
+                return Some(self.skip(condition, addr));
+            }
+            Instruction::SkipEqualRegister(x, y) | Instruction::SkipNotEqualRegister(x, y) => {
+                let (vx, vy) = (self.get(x), self.get(y));
+                let cc = match instruction {
+                    Instruction::SkipEqualRegister(..) => IntCC::Equal,
+                    _ => IntCC::NotEqual,
+                };
+                let condition = self.builder.ins().icmp(cc, vx, vy);
+                return Some(self.skip(condition, addr));
+            }
+            // Opcodes desconhecidos são ignorados, como no interpretador
+            _ => {}
+        }
+        None
+    }
+
+    fn skip(&mut self, condition: Value, addr: u16) -> Value {
+        let taken = self.wrap(addr.wrapping_add(4));
+        let not_taken = self.wrap(addr.wrapping_add(2));
+        let taken = self.builder.ins().iconst(types::I16, taken as i64);
+        let not_taken = self.builder.ins().iconst(types::I16, not_taken as i64);
+        self.builder.ins().select(condition, taken, not_taken)
+    }
+
+    // Grava os registradores alterados e o novo pc
+    fn finish(mut self, pc: Value) {
+        for x in 0..16 {
+            if self.v_written[x]
+                && let Some(value) = self.v[x]
+            {
+                self.builder
+                    .ins()
+                    .store(Self::flags(), value, self.base, Self::v_offset(x));
+            }
+        }
+        if let Some(i) = self.i {
+            let offset = offset_of!(Chip8, i) as i32;
+            self.builder
+                .ins()
+                .store(Self::flags(), i, self.base, offset);
+        }
+        let offset = offset_of!(Chip8, pc) as i32;
+        self.builder
+            .ins()
+            .store(Self::flags(), pc, self.base, offset);
+        self.builder.ins().return_(&[]);
+        self.builder.finalize();
+    }
+}
@@ -0,0 +1,310 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+// Mapeamento das teclas do computador (host) para as 16 teclas do CHIP-8. As teclas do host são
+// identificadas pelo nome das variantes de minifb::Key ("Key1", "Q", "NumPad7", ...), sem
+// diferenciar maiúsculas; "1" é aceito como atalho para "Key1". Cada tecla do CHIP-8 pode ter
+// várias teclas do host, mas cada tecla do host aciona no máximo uma tecla do CHIP-8
+
+// Teclas do CHIP-8 na ordem em que aparecem no teclado do COSMAC VIP:
+//   1 2 3 C
+//   4 5 6 D
+//   7 8 9 E
+//   A 0 B F
+pub const KEYPAD_LAYOUT: [u8; 16] = [
+    0x1, 0x2, 0x3, 0xC, 0x4, 0x5, 0x6, 0xD, 0x7, 0x8, 0x9, 0xE, 0xA, 0x0, 0xB, 0xF,
+];
+
+pub const DEFAULT_KEYMAP: &str = "qwerty";
+
+// Presets com as teclas do host na mesma ordem do KEYPAD_LAYOUT (um bloco 4x4 no teclado)
+const PRESETS: &[(&str, &str)] = &[
+    ("qwerty", "Key1 Key2 Key3 Key4 Q W E R A S D F Z X C V"),
+    ("azerty", "Key1 Key2 Key3 Key4 A Z E R Q S D F W X C V"),
+    ("qwertz", "Key1 Key2 Key3 Key4 Q W E R A S D F Y X C V"),
+    (
+        "dvorak",
+        "Key1 Key2 Key3 Key4 Apostrophe Comma Period P A O E U Semicolon Q J K",
+    ),
+    // No teclado numérico cada dígito aciona o mesmo dígito do CHIP-8, e A a F ficam nas teclas
+    // de operação: / * - + Enter .
+    (
+        "numpad",
+        "NumPad1 NumPad2 NumPad3 NumPadMinus NumPad4 NumPad5 NumPad6 NumPadPlus \
+         NumPad7 NumPad8 NumPad9 NumPadEnter NumPadSlash NumPad0 NumPadAsterisk NumPadDot",
+    ),
+];
+
+// Nomes válidos de teclas do host (as variantes de minifb::Key)
+const HOST_KEYS: &[&str] = &[
+    "Key0",
+    "Key1",
+    "Key2",
+    "Key3",
+    "Key4",
+    "Key5",
+    "Key6",
+    "Key7",
+    "Key8",
+    "Key9",
+    "A",
+    "B",
+    "C",
+    "D",
+    "E",
+    "F",
+    "G",
+    "H",
+    "I",
+    "J",
+    "K",
+    "L",
+    "M",
+    "N",
+    "O",
+    "P",
+    "Q",
+    "R",
+    "S",
+    "T",
+    "U",
+    "V",
+    "W",
+    "X",
+    "Y",
+    "Z",
+    "F1",
+    "F2",
+    "F3",
+    "F4",
+    "F5",
+    "F6",
+    "F7",
+    "F8",
+    "F9",
+    "F10",
+    "F11",
+    "F12",
+    "F13",
+    "F14",
+    "F15",
+    "Down",
+    "Left",
+    "Right",
+    "Up",
+    "Apostrophe",
+    "Backquote",
+    "Backslash",
+    "Comma",
+    "Equal",
+    "LeftBracket",
+    "Minus",
+    "Period",
+    "RightBracket",
+    "Semicolon",
+    "Slash",
+    "Backspace",
+    "Delete",
+    "End",
+    "Enter",
+    "Escape",
+    "Home",
+    "Insert",
+    "Menu",
+    "PageDown",
+    "PageUp",
+    "Pause",
+    "Space",
+    "Tab",
+    "NumLock",
+    "CapsLock",
+    "ScrollLock",
+    "LeftShift",
+    "RightShift",
+    "LeftCtrl",
+    "RightCtrl",
+    "NumPad0",
+    "NumPad1",
+    "NumPad2",
+    "NumPad3",
+    "NumPad4",
+    "NumPad5",
+    "NumPad6",
+    "NumPad7",
+    "NumPad8",
+    "NumPad9",
+    "NumPadDot",
+    "NumPadSlash",
+    "NumPadAsterisk",
+    "NumPadMinus",
+    "NumPadPlus",
+    "NumPadEnter",
+    "LeftAlt",
+    "RightAlt",
+    "LeftSuper",
+    "RightSuper",
+];
+
+// Converte o nome escrito pelo usuário para o nome canônico da tecla
+fn host_key(name: &str) -> Result<&'static str, String> {
+    let name = name.trim();
+    let name = if name.len() == 1 && name.as_bytes()[0].is_ascii_digit() {
+        format!("Key{}", name)
+    } else {
+        name.to_string()
+    };
+    HOST_KEYS
+        .iter()
+        .find(|key| key.eq_ignore_ascii_case(&name))
+        .copied()
+        .ok_or_else(|| format!("unknown host key '{}'", name))
+}
+
+// "0" a "F"
+pub fn parse_chip8_key(name: &str) -> Result<u8, String> {
+    match u8::from_str_radix(name.trim(), 16) {
+        Ok(key) if key < 16 && name.trim().len() == 1 => Ok(key),
+        _ => Err(format!("invalid CHIP-8 key '{}' (expected 0-F)", name)),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keymap {
+    bindings: HashMap<&'static str, u8>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap::preset(DEFAULT_KEYMAP).unwrap()
+    }
+}
+
+impl Keymap {
+    pub fn preset(name: &str) -> Option<Keymap> {
+        PRESETS
+            .iter()
+            .find(|(preset, _)| preset.eq_ignore_ascii_case(name))
+            .map(|(_, keys)| {
+                let bindings = keys.split_whitespace().zip(KEYPAD_LAYOUT).collect();
+                Keymap { bindings }
+            })
+    }
+
+    pub fn preset_names() -> impl Iterator<Item = &'static str> {
+        PRESETS.iter().map(|&(name, _)| name)
+    }
+
+    // Tecla do CHIP-8 acionada pela tecla do host, se houver
+    pub fn get(&self, host: &str) -> Option<u8> {
+        let host = host_key(host).ok()?;
+        self.bindings.get(host).copied()
+    }
+
+    // Teclas do host ligadas a uma tecla do CHIP-8, em ordem alfabética
+    pub fn host_keys(&self, chip8_key: u8) -> Vec<&'static str> {
+        let mut keys: Vec<_> = self
+            .bindings
+            .iter()
+            .filter(|&(_, &key)| key == chip8_key)
+            .map(|(&host, _)| host)
+            .collect();
+        keys.sort_unstable();
+        keys
+    }
+
+    // Substitui as teclas do host de uma tecla do CHIP-8. Uma tecla do host que estava ligada a
+    // outra tecla do CHIP-8 passa a acionar só esta
+    pub fn bind<S: AsRef<str>>(&mut self, chip8_key: u8, hosts: &[S]) -> Result<(), String> {
+        let hosts = hosts
+            .iter()
+            .map(|host| host_key(host.as_ref()))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.bindings.retain(|_, key| *key != chip8_key);
+        for host in hosts {
+            self.bindings.insert(host, chip8_key);
+        }
+        Ok(())
+    }
+
+    // Aplica uma tabela como a [keymap.keys] ("0" a "F" -> teclas do host). A ordem de uma
+    // HashMap muda a cada execução, então uma tecla do host listada em duas teclas do CHIP-8 é
+    // recusada em vez de ficar com a que por acaso vier por último
+    pub fn bind_all(&mut self, keys: &HashMap<String, Vec<String>>) -> Result<(), String> {
+        let mut owners: HashMap<&'static str, u8> = HashMap::new();
+        let mut bindings = Vec::with_capacity(keys.len());
+        for (key, hosts) in keys {
+            let key = parse_chip8_key(key)?;
+            for host in hosts {
+                let host = host_key(host)?;
+                if let Some(other) = owners.insert(host, key).filter(|&other| other != key) {
+                    let (a, b) = (other.min(key), other.max(key));
+                    return Err(format!(
+                        "host key '{}' is bound to both CHIP-8 keys {:X} and {:X}",
+                        host, a, b
+                    ));
+                }
+            }
+            bindings.push((key, hosts));
+        }
+        for (key, hosts) in bindings {
+            self.bind(key, hosts)?;
+        }
+        Ok(())
+    }
+
+    // Trecho de chip8.toml que recria este mapeamento
+    pub fn to_toml(&self) -> String {
+        let mut text = String::from("[keymap.keys]\n");
+        for key in KEYPAD_LAYOUT {
+            let hosts: Vec<String> = self
+                .host_keys(key)
+                .iter()
+                .map(|host| format!("\"{}\"", host))
+                .collect();
+            writeln!(text, "\"{:X}\" = [{}]", key, hosts.join(", ")).unwrap();
+        }
+        text
+    }
+}
+
+// Religação dentro da janela: pede uma tecla do host para cada tecla do CHIP-8, na ordem do
+// KEYPAD_LAYOUT. O resultado só substitui o mapeamento atual quando todas foram definidas
+pub struct Rebinder {
+    keymap: Keymap,
+    position: usize,
+}
+
+impl Rebinder {
+    pub fn new(current: &Keymap) -> Self {
+        Rebinder {
+            keymap: current.clone(),
+            position: 0,
+        }
+    }
+
+    // Tecla do CHIP-8 esperando uma tecla do host, ou None quando terminou
+    pub fn pending(&self) -> Option<u8> {
+        KEYPAD_LAYOUT.get(self.position).copied()
+    }
+
+    pub fn press(&mut self, host: &str) -> Result<(), String> {
+        if let Some(key) = self.pending() {
+            self.keymap.bind(key, &[host])?;
+            self.position += 1;
+        }
+        Ok(())
+    }
+
+    // Mantém as teclas atuais desta tecla do CHIP-8
+    pub fn skip(&mut self) {
+        self.position += 1;
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.pending().is_none()
+    }
+
+    pub fn finish(self) -> Keymap {
+        self.keymap
+    }
+}
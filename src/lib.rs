@@ -0,0 +1,31 @@
+pub mod audio;
+pub mod capture;
+pub mod cartridge;
+pub mod cdp1802;
+pub mod color;
+pub mod config;
+pub mod cpu;
+pub mod detect;
+pub mod flicker;
+pub mod font;
+pub mod instruction;
+#[cfg(feature = "jit")]
+pub mod jit;
+pub mod keymap;
+pub mod machine;
+pub mod menu;
+pub mod octo;
+pub mod osd;
+pub mod palette;
+pub mod platform;
+pub mod postfx;
+pub mod quirks;
+pub mod recompiler;
+pub mod render;
+pub mod romdb;
+pub mod source;
+pub mod timing;
+pub mod vip;
+
+pub use cpu::{AudioState, Chip8, Chip8Error, VIDEO_HEIGHT, VIDEO_WIDTH};
+pub use quirks::Quirks;
@@ -0,0 +1,74 @@
+use crate::cpu::{Chip8, Chip8Error, VIDEO_HEIGHT, VIDEO_WIDTH};
+use crate::vip::Vip;
+
+// O que os testes e as execuções em lote precisam de uma máquina que roda ROMs de CHIP-8: o core
+// de alto nível (Chip8) ou o COSMAC VIP inteiro (Vip), que executa o interpretador original no
+// 1802. Com as duas atrás do mesmo trait dá para rodar a mesma ROM nas duas e comparar as telas
+pub trait Machine {
+    // Programa em 0x200
+    fn load_bytes(&mut self, rom: &[u8]) -> Result<(), Chip8Error>;
+    // Um quadro de 60Hz. `instructions` só vale para o Chip8; o VIP anda no ritmo do próprio clock
+    fn run_frame(&mut self, instructions: usize) -> Result<(), Chip8Error>;
+    fn set_key(&mut self, key: usize, down: bool);
+    // Pixel na tela de 64x32
+    fn pixel(&self, x: usize, y: usize) -> bool;
+    fn sound_active(&self) -> bool;
+
+    // A tela inteira, uma linha por u64 com a coluna 0 no bit 63
+    fn screen(&self) -> [u64; VIDEO_HEIGHT] {
+        let mut screen = [0; VIDEO_HEIGHT];
+        for (y, row) in screen.iter_mut().enumerate() {
+            for x in 0..VIDEO_WIDTH {
+                if self.pixel(x, y) {
+                    *row |= 1 << (VIDEO_WIDTH - 1 - x);
+                }
+            }
+        }
+        screen
+    }
+}
+
+impl Machine for Chip8 {
+    fn load_bytes(&mut self, rom: &[u8]) -> Result<(), Chip8Error> {
+        Chip8::load_bytes(self, rom)
+    }
+
+    fn run_frame(&mut self, instructions: usize) -> Result<(), Chip8Error> {
+        Chip8::run_frame(self, instructions)
+    }
+
+    fn set_key(&mut self, key: usize, down: bool) {
+        Chip8::set_key(self, key, down)
+    }
+
+    fn pixel(&self, x: usize, y: usize) -> bool {
+        Chip8::pixel(self, x, y)
+    }
+
+    fn sound_active(&self) -> bool {
+        Chip8::sound_active(self)
+    }
+}
+
+impl Machine for Vip {
+    fn load_bytes(&mut self, rom: &[u8]) -> Result<(), Chip8Error> {
+        Vip::load_bytes(self, rom)
+    }
+
+    fn run_frame(&mut self, _instructions: usize) -> Result<(), Chip8Error> {
+        Vip::run_frame(self);
+        Ok(())
+    }
+
+    fn set_key(&mut self, key: usize, down: bool) {
+        Vip::set_key(self, key, down)
+    }
+
+    fn pixel(&self, x: usize, y: usize) -> bool {
+        Vip::pixel(self, x, y)
+    }
+
+    fn sound_active(&self) -> bool {
+        Vip::sound_active(self)
+    }
+}
@@ -1,594 +1,1200 @@
-use minifb::{Key, Window, WindowOptions};
+use chip8::audio::{AudioSink, Beeper, DEFAULT_SAMPLE_RATE, NullSink, ToneSettings, WavSink};
+use chip8::capture::{RawVideoWriter, Recorder, Y4mWriter, save_png};
+use chip8::config::{Config, DEFAULT_CONFIG_PATH};
+use chip8::cpu::ALL_ROWS;
+use chip8::detect::{Detection, detect};
+use chip8::flicker::{FlickerFilter, FlickerMode};
+#[cfg(feature = "jit")]
+use chip8::jit::Jit;
+use chip8::keymap::{Keymap, Rebinder};
+use chip8::menu::{
+    BrowserAction, MenuKey, PauseAction, PauseMenu, RomBrowser, SAVE_SLOTS, draw_menu,
+};
+use chip8::osd::{Osd, RunState};
+use chip8::palette::Palette;
+use chip8::platform::Platform;
+use chip8::postfx::{PostFxSettings, PostProcessor};
+use chip8::quirks::QUIRK_NAMES;
+use chip8::recompiler::Recompiler;
+use chip8::render::{PixelStyle, scale_buffer, scale_rows_into};
+use chip8::source::{Rom, RomSource};
+use chip8::timing::Timing;
+use chip8::{Chip8, Chip8Error, Quirks, VIDEO_HEIGHT, VIDEO_WIDTH};
+use minifb::{Key, KeyRepeat, Window, WindowOptions};
 use std::fs::File;
-use std::io::Read;
-use std::path;
+use std::io::{self, BufWriter, IsTerminal, Write};
+use std::path::Path;
+use std::str::FromStr;
 use std::thread::sleep;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use std::collections::HashMap;
-
-const MEMORY_SIZE: usize = 4092;
-const REGISTER_COUNT: usize = 16;
-const STACK_SIZE: usize = 16;
-const KEYPAD_SIZE: usize = 16;
-pub const VIDEO_WIDTH: usize = 64;
-pub const VIDEO_HEIGHT: usize = 32;
+// Ampliação da tela de 64x32. A janela tem sempre o mesmo tamanho, então a tela de 128x64 do
+// CHIP-10 usa a metade
 pub const DISPLAY_SCALE: usize = 10;
-const START_ADDRESS: usize = 0x200;
-
-struct Chip8 {
-    // 4K memory
-    memory: [u8; MEMORY_SIZE],
+const WINDOW_WIDTH: usize = VIDEO_WIDTH * DISPLAY_SCALE;
+const WINDOW_HEIGHT: usize = VIDEO_HEIGHT * DISPLAY_SCALE;
+// O segundo teclado do CHIP-8X fica no teclado numérico
+const SECOND_KEYPAD_PRESET: &str = "numpad";
+// Pasta aberta pelo navegador de ROMs
+const DEFAULT_ROMS_DIR: &str = "roms";
+// Tamanho da fonte dos menus em relação ao glifo de 5x7
+const MENU_SCALE: usize = 2;
+const PAUSE_HINT: &str = "Enter: select   Left/Right: change   Esc: resume";
+// Tamanho da fonte das mensagens e contadores por cima do jogo
+const OSD_SCALE: usize = 2;
+// Sem janela, roda 10 segundos se --frames não for passado
+const DEFAULT_HEADLESS_FRAMES: u64 = 600;
+
+// Opções de linha de comando
+#[derive(Default)]
+struct Options {
+    // Arquivo .ch8/.c8/.sc8/.xo8/.c8x, .zip, cartucho .gif do Octo ou "-" para a entrada padrão
+    rom_path: Option<String>,
+    // Pasta do navegador de ROMs
+    roms_dir: Option<String>,
+    tone: ToneSettings,
+    // Grava o áudio num arquivo WAV em vez de tocar na placa de som
+    wav_path: Option<String>,
+    // Arquivo de configuração; sem ele usa o chip8.toml do diretório atual, se existir
+    config_path: Option<String>,
+    // Sobrescrevem o que estiver no arquivo de configuração
+    palette: Option<String>,
+    pixel_style: Option<PixelStyle>,
+    // Preset de teclado: qwerty, azerty, qwertz, dvorak ou numpad
+    keymap: Option<String>,
+    // chip8, schip, xochip, chip8x, chip8e ou chip10; sem ela vale o banco de ROMs
+    platform: Option<Platform>,
+    // Liga todos os efeitos de CRT
+    crt: bool,
+    flicker: Option<FlickerMode>,
+    // Instruções executadas por quadro de 60Hz
+    instructions_per_frame: Option<usize>,
+    // ipf (o número acima) ou vip (ciclos do interpretador do COSMAC VIP)
+    timing: Timing,
+    // PNG salvo com a última tela quando o emulador para
+    screenshot_path: Option<String>,
+    // Grava desde o início em GIF (.gif) ou APNG (.png / .apng)
+    record_path: Option<String>,
+    // Ampliação das capturas; 1 é a resolução nativa (64x32, ou 128x64 no CHIP-10)
+    capture_scale: Option<usize>,
+    // Dump sem perdas: `<nome>.rgb` com o vídeo (rgb24 cru) e `<nome>.wav` com o áudio
+    dump_path: Option<String>,
+    // Escreve também `<nome>.y4m` (YUV 4:4:4, quase sem perdas)
+    dump_y4m: bool,
+    // Roda sem janela (para CI), por `frames` quadros
+    headless: bool,
+    frames: Option<u64>,
+    // Executa pelo recompilador de blocos em vez do interpretador
+    recompile: bool,
+    // Executa pelo JIT do Cranelift (feature `jit`)
+    #[cfg(feature = "jit")]
+    jit: bool,
+}
 
-    // 16 general purpose 8-bit registers: V0 to VF
-    v: [u8; REGISTER_COUNT],
+fn parse_value<T: FromStr>(flag: &str, value: Option<String>) -> Result<T, String>
+where
+    T::Err: std::fmt::Display,
+{
+    let value = value.ok_or_else(|| format!("{} expects a value", flag))?;
+    value
+        .parse()
+        .map_err(|e| format!("invalid value '{}' for {}: {}", value, flag, e))
+}
 
-    // Index register (16-bit)
-    i: u16,
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--tone" => options.tone.frequency = parse_value(&arg, args.next())?,
+            "--waveform" => options.tone.waveform = parse_value(&arg, args.next())?,
+            "--volume" => options.tone.volume = parse_value(&arg, args.next())?,
+            "--mute" => options.tone.muted = true,
+            "--wav" => options.wav_path = Some(parse_value(&arg, args.next())?),
+            "--config" => options.config_path = Some(parse_value(&arg, args.next())?),
+            "--palette" => options.palette = Some(parse_value(&arg, args.next())?),
+            "--platform" => options.platform = Some(parse_value(&arg, args.next())?),
+            "--keymap" => options.keymap = Some(parse_value(&arg, args.next())?),
+            "--pixel-style" => options.pixel_style = Some(parse_value(&arg, args.next())?),
+            "--crt" => options.crt = true,
+            "--flicker" => options.flicker = Some(parse_value(&arg, args.next())?),
+            "--ipf" => options.instructions_per_frame = Some(parse_value(&arg, args.next())?),
+            "--timing" => options.timing = parse_value(&arg, args.next())?,
+            "--screenshot" => options.screenshot_path = Some(parse_value(&arg, args.next())?),
+            "--record" => options.record_path = Some(parse_value(&arg, args.next())?),
+            "--capture-scale" => options.capture_scale = Some(parse_value(&arg, args.next())?),
+            "--dump" => options.dump_path = Some(parse_value(&arg, args.next())?),
+            "--dump-y4m" => options.dump_y4m = true,
+            "--roms" => options.roms_dir = Some(parse_value(&arg, args.next())?),
+            "--headless" => options.headless = true,
+            "--recompile" => options.recompile = true,
+            #[cfg(feature = "jit")]
+            "--jit" => options.jit = true,
+            "--frames" => options.frames = Some(parse_value(&arg, args.next())?),
+            _ if !arg.starts_with("--") && options.rom_path.is_none() => {
+                options.rom_path = Some(arg)
+            }
+            _ => return Err(format!("unknown argument '{}'", arg)),
+        }
+    }
+    // O áudio do dump usa o mesmo nome, a não ser que --wav aponte para outro arquivo
+    if let Some(dump) = &options.dump_path {
+        options
+            .wav_path
+            .get_or_insert_with(|| format!("{}.wav", dump));
+    }
+    Ok(options)
+}
 
-    // Program Counter starts at 0x200
-    pc: u16,
+fn load_config(options: &Options) -> Result<Config, String> {
+    let path = match &options.config_path {
+        Some(path) => path.as_str(),
+        None if Path::new(DEFAULT_CONFIG_PATH).exists() => DEFAULT_CONFIG_PATH,
+        None => return Ok(Config::default()),
+    };
+    Config::load(path).map_err(|e| format!("{}: {}", path, e))
+}
 
-    // Stack for subrotines calls
-    stack: [u16; STACK_SIZE],
-    sp: u8,
+// Quem executa as instruções de cada quadro: o interpretador, o recompilador de closures
+// (`--recompile`) ou o JIT (`--jit`, só com a feature `jit`). Com `--timing vip` o custo de cada
+// instrução é contado uma a uma, então sempre roda o interpretador
+enum Engine {
+    Interpreter,
+    Recompiler(Recompiler),
+    #[cfg(feature = "jit")]
+    Jit(Box<Jit>),
+}
 
-    // Timers (decrement at 60hz)
-    delay_timer: u8,
-    sound_timer: u8,
+impl Engine {
+    fn new(options: &Options, memory_size: usize) -> Engine {
+        #[cfg(feature = "jit")]
+        let compiled = options.recompile || options.jit;
+        #[cfg(not(feature = "jit"))]
+        let compiled = options.recompile;
+        if options.timing == Timing::Vip {
+            if compiled {
+                eprintln!("--timing vip counts cycles per instruction; running on the interpreter");
+            }
+            return Engine::Interpreter;
+        }
+        #[cfg(feature = "jit")]
+        if options.jit {
+            return Engine::Jit(Box::new(Jit::new(memory_size)));
+        }
+        if options.recompile {
+            Engine::Recompiler(Recompiler::new(memory_size))
+        } else {
+            Engine::Interpreter
+        }
+    }
 
-    // Input keypad (16 keys)
-    keypad: [bool; KEYPAD_SIZE],
+    // Retorna quantas instruções rodaram, que no modo vip muda de um quadro para outro
+    fn run_frame(
+        &mut self,
+        chip8: &mut Chip8,
+        timing: Timing,
+        instructions: usize,
+    ) -> Result<usize, Chip8Error> {
+        match (self, timing) {
+            (Engine::Interpreter, Timing::Vip) => return chip8.run_vip_frame(),
+            (Engine::Interpreter, Timing::Instructions) => chip8.run_frame(instructions),
+            (Engine::Recompiler(recompiler), _) => recompiler.run_frame(chip8, instructions),
+            #[cfg(feature = "jit")]
+            (Engine::Jit(jit), _) => jit.run_frame(chip8, instructions),
+        }
+        .map(|()| instructions)
+    }
 
-    // Video Buffer
-    video: [bool; VIDEO_WIDTH * VIDEO_HEIGHT],
-}
-fn build_keymap() -> HashMap<Key, u8> {
-    use Key::*;
-    [
-        (Key1, 0x1),
-        (Key2, 0x2),
-        (Key3, 0x3),
-        (Key4, 0xC),
-        (Q, 0x4),
-        (W, 0x5),
-        (E, 0x6),
-        (R, 0xD),
-        (A, 0x7),
-        (S, 0x8),
-        (D, 0x9),
-        (F, 0xE),
-        (Z, 0xA),
-        (X, 0x0),
-        (C, 0xB),
-        (V, 0xF),
-    ]
-    .into_iter()
-    .collect()
+    fn flush(&mut self) {
+        match self {
+            Engine::Interpreter => {}
+            Engine::Recompiler(recompiler) => recompiler.flush(),
+            #[cfg(feature = "jit")]
+            Engine::Jit(jit) => jit.flush(),
+        }
+    }
 }
-impl Chip8 {
-    fn new() -> Self {
-        Chip8 {
-            memory: [0; MEMORY_SIZE],
-            v: [0; REGISTER_COUNT],
-            i: 0,
-            pc: 0x200, // CHIP-8 programs start at 0x200
-            stack: [0; STACK_SIZE],
-            sp: 0,
-            delay_timer: 0,
-            sound_timer: 0,
-            keypad: [false; KEYPAD_SIZE],
-            video: [false; VIDEO_WIDTH * VIDEO_HEIGHT],
-        }
-    }
-
-    //O Result serve para indicar que a função pode falhar e retornar um valor de sucesso ou um erro
-
-    //&mut self significa que o estado da objeto atual será mudado
-
-    //usasse &str na tipagem do parâmetro ou invés de apenas str para referenciar uma string de tamanho definido
-    //onde str não pode ser usado diretamente pois é um tipo de tamanho dinâmico (DST) e representa uma sequência
-    //de texto imutavél sem tamanho definido
-    fn load_rom(&mut self, filename: &str) -> std::io::Result<()> {
-        //como o processo de abrir o file pode falhar nos colocamos o ? no final da linha
-        //é um shortcut para
-        // let mut file =  match File::open(filename){
-        //     Ok(f) => f,
-        //     Err(e) => return Err(e),
-        // }
-        //Onde se der sucesso ele retorna o resultado esperado, senão, o erro
-        let mut file = File::open(filename)?;
-        //Cria um vetor de tamanho dinâmico pois não sabemos o tamanho na ROM
-        let mut buffer = Vec::new();
-        //é necessário passar &mut pois a função mudadará o estado do buffer
-        file.read_to_end(&mut buffer)?;
-        //um for in onde a gente tem o elemento e o index ao mesmo tempo!
-        //o .iter() fazer que iteramos por todos elementos de buffer
-        //o .enumerate fazer com que retorne tanto o valor quanto o index equivalente
-        //o & em &byte é usado para DESREFERENCIAR o valor do byte que vem como &u8. ao usar &byte o &u8 já retorna como u8
-        //meio confuso a principio para eu que nunca programei low level.
-        for (i, &byte) in buffer.iter().enumerate() {
-            self.memory[START_ADDRESS + i] = byte;
+
+// Converte as linhas `rows` da tela do Chip8 nas cores de `buffer`. Com a redução de piscada ligada o
+// filtro sempre processa a tela inteira. No CHIP-8X as cores vêm da placa de cor e não da paleta,
+// e a redução de piscada não se aplica
+fn update_rows(
+    chip8: &Chip8,
+    pixels: &mut [u8],
+    buffer: &mut [u32],
+    flicker: &mut FlickerFilter,
+    palette: &Palette,
+    rows: u64,
+) {
+    let (width, height) = chip8.resolution();
+    if let Some(colors) = &chip8.colors {
+        for y in (0..height).filter(|&y| rows & (1 << y) != 0) {
+            for x in 0..width {
+                buffer[y * width + x] = colors.color(x, y, chip8.pixel(x, y));
+            }
+        }
+        return;
+    }
+    if flicker.mode() != FlickerMode::Off {
+        for (dst, index) in pixels.iter_mut().zip(chip8.pixel_indices()) {
+            *dst = index;
+        }
+        flicker.process(pixels, palette, buffer);
+        return;
+    }
+    for y in (0..height).filter(|&y| rows & (1 << y) != 0) {
+        let row = y * width..(y + 1) * width;
+        for (x, dst) in pixels[row.clone()].iter_mut().enumerate() {
+            *dst = chip8.pixel_index(x, y);
         }
+        palette.colorize(pixels[row.clone()].iter().copied(), &mut buffer[row]);
+    }
+}
 
-        Ok(())
+// Escolhe para onde vai o áudio: arquivo WAV, placa de som (feature `audio`) ou lugar nenhum
+fn open_audio(options: &Options) -> Box<dyn AudioSink> {
+    if let Some(path) = &options.wav_path {
+        match WavSink::create(path, DEFAULT_SAMPLE_RATE) {
+            Ok(sink) => return Box::new(sink),
+            Err(e) => eprintln!("Failed to create {}: {}", path, e),
+        }
+    }
+    #[cfg(feature = "audio")]
+    if !options.headless {
+        match chip8::audio::CpalSink::open() {
+            Ok(sink) => return Box::new(sink),
+            Err(e) => eprintln!("Audio disabled: {}", e),
+        }
     }
+    #[cfg(not(feature = "audio"))]
+    if !options.headless {
+        eprintln!(
+            "Audio disabled: built without the `audio` feature (cargo run --features audio, or --wav to record it)"
+        );
+    }
+    Box::new(NullSink::new(DEFAULT_SAMPLE_RATE))
+}
 
-    fn read_byte(&self, addr: usize) -> u8 {
-        self.memory[addr]
+// F1 scanlines, F2 bloom, F3 curvatura, F4 vinheta, F5 alterna o espaço entre pixels (0, 1, 2)
+fn toggle_postfx(window: &Window, settings: &mut PostFxSettings) {
+    let pressed = |key| window.is_key_pressed(key, KeyRepeat::No);
+    if pressed(Key::F1) {
+        settings.scanlines = !settings.scanlines;
+    }
+    if pressed(Key::F2) {
+        settings.bloom = !settings.bloom;
     }
+    if pressed(Key::F3) {
+        settings.curvature = !settings.curvature;
+    }
+    if pressed(Key::F4) {
+        settings.vignette = !settings.vignette;
+    }
+    if pressed(Key::F5) {
+        settings.pixel_gap = (settings.pixel_gap + 1) % 3;
+    }
+}
 
-    fn write_byte(&mut self, addr: usize, value: u8) {
-        self.memory[addr] = value;
+// Quadro usado nas capturas: a saída do filtro de piscada (`width` x `height`) ampliada com o
+// estilo de pixel, mas sem os efeitos de CRT
+fn capture_frame(
+    buffer: &[u32],
+    width: usize,
+    height: usize,
+    scale: usize,
+    style: PixelStyle,
+    palette: &Palette,
+) -> Vec<u32> {
+    if scale <= 1 {
+        buffer.to_vec()
+    } else {
+        scale_buffer(buffer, width, height, scale, style, palette)
     }
+}
 
-    fn load_test_instructions(&mut self) {
-        let program: [u8; 12 + 5] = [
-            0x00, 0xE0, // CLS
-            0xF0, 0x0A, // LD V0, K
-            0x60, 0x00, // LD V0, 0
-            0x61, 0x00, // LD V1, 0
-            0xA3, 0x00, // LD I, 0x200
-            0xD0, 0x15, // DRW V0, V1, 5
-            // Dados do sprite (a partir de 0x200)
-            0xF0, 0x90, 0x90, 0x90, 0xF0,
-        ];
+fn save_screenshot(
+    path: &str,
+    buffer: &[u32],
+    (width, height): (usize, usize),
+    scale: usize,
+    style: PixelStyle,
+    palette: &Palette,
+) {
+    let frame = capture_frame(buffer, width, height, scale, style, palette);
+    match save_png(path, &frame, width * scale, height * scale) {
+        Ok(()) => println!("Saved screenshot {}", path),
+        Err(e) => eprintln!("Failed to save {}: {}", path, e),
+    }
+}
 
-        for (i, &byte) in program[..10].iter().enumerate() {
-            self.memory[0x200 + i] = byte;
+// A gravação tem o tamanho da tela `width` x `height` ampliada por `scale`
+fn start_recording(
+    path: &str,
+    (width, height): (usize, usize),
+    scale: usize,
+) -> Option<(String, Recorder<BufWriter<File>>)> {
+    match Recorder::create(path, width * scale, height * scale) {
+        Ok(recorder) => {
+            println!("Recording to {}", path);
+            Some((path.to_string(), recorder))
         }
-        for (i, &byte) in program[12..].iter().enumerate() {
-            self.memory[0x300 + i] = byte;
+        Err(e) => {
+            eprintln!("Failed to record to {}: {}", path, e);
+            None
         }
     }
+}
 
-    //Segundo a especificação os timers diminuiem uma unidade a cada 60Hz e isso é usado para coisas como animção e música
-    pub fn tick_timers(&mut self) {
-        if self.delay_timer > 0 {
-            self.delay_timer -= 1;
-        }
+// Vídeo do --dump: o rgb24 sem perdas e, com --dump-y4m, o y4m junto
+struct Dump {
+    raw: RawVideoWriter<BufWriter<File>>,
+    y4m: Option<Y4mWriter<BufWriter<File>>>,
+}
+
+impl Dump {
+    fn create(name: &str, width: usize, height: usize, y4m: bool) -> Result<Dump, String> {
+        let path = format!("{}.rgb", name);
+        let raw = RawVideoWriter::create(&path, width, height)
+            .map_err(|e| format!("Failed to create {}: {}", path, e))?;
+        println!(
+            "Dumping video to {} (ffmpeg {} -i {})",
+            path,
+            raw.ffmpeg_input(),
+            path
+        );
+        let y4m = if y4m {
+            let path = format!("{}.y4m", name);
+            let writer = Y4mWriter::create(&path, width, height)
+                .map_err(|e| format!("Failed to create {}: {}", path, e))?;
+            Some(writer)
+        } else {
+            None
+        };
+        Ok(Dump { raw, y4m })
+    }
 
-        if self.sound_timer > 0 {
-            self.sound_timer -= 1;
-            //Trigger beep
-            println!("BEEEEEP!")
+    fn write_frame(&mut self, frame: &[u32]) -> io::Result<()> {
+        self.raw.write_frame(frame)?;
+        if let Some(y4m) = &mut self.y4m {
+            y4m.write_frame(frame)?;
         }
+        Ok(())
     }
 
-    fn cycle(&mut self) {
-        //FETCH
+    fn finish(self) -> io::Result<()> {
+        self.raw.finish()?;
+        if let Some(y4m) = self.y4m {
+            y4m.finish()?;
+        }
+        Ok(())
+    }
+}
 
-        let high_byte: u16 = self.memory[self.pc as usize] as u16;
-        let low_byte: u16 = self.memory[(self.pc + 1) as usize] as u16;
-        let opcode: u16 = (high_byte << 8) | low_byte;
+fn stop_recording((path, recorder): (String, Recorder<BufWriter<File>>)) {
+    match recorder
+        .finish()
+        .and_then(|mut file| std::io::Write::flush(&mut file))
+    {
+        Ok(()) => println!("Saved recording {}", path),
+        Err(e) => eprintln!("Failed to save {}: {}", path, e),
+    }
+}
 
-        match opcode {
-            //Limpa a tela de toda informação
-            //CLS - Clear Screen
-            0x00E0 => {
-                self.video = [false; VIDEO_WIDTH * VIDEO_HEIGHT];
-                println!("Executed CLS (Clear Screen)");
-            }
+const WINDOW_TITLE: &str = "CHIP-8 Emulator";
 
-            0xE000 => match opcode & 0x00FF {
-                0x9E => {
-                    //Pula a próxima instrução caso o botão com o valor de Vx estiver pressionado
-                    let x: usize = ((opcode & 0x0F00) >> 8) as usize;
-                    let key = self.v[x] as usize;
-                    if self.keypad[key] {
-                        self.pc += 2;
-                    }
-                }
+// Mostra no título da janela qual tecla do CHIP-8 está esperando uma tecla do host
+fn prompt_rebind(window: &mut Window, title: &str, rebinder: &Rebinder) {
+    if let Some(key) = rebinder.pending() {
+        window.set_title(&format!(
+            "{} - press a key for CHIP-8 key {:X} (Backspace skips, F7 cancels)",
+            title, key
+        ));
+    }
+}
 
-                0xA1 => {
-                    // Pula a próxima instrução caso o botão com o valor de Vx NÃO estiver pressionado
-                    let x = ((opcode & 0x0F00) >> 8) as usize;
-                    let key = self.v[x] as usize;
-                    if !self.keypad[key] {
-                        self.pc += 2;
-                    }
+// Um passo da religação de teclas. Retorna false quando ela termina ou é cancelada
+fn rebind_step(window: &mut Window, title: &str, rebinder: &mut Rebinder) -> bool {
+    for key in window.get_keys_pressed(KeyRepeat::No) {
+        match key {
+            Key::F7 => {
+                println!("Key rebinding cancelled");
+                window.set_title(title);
+                return false;
+            }
+            Key::Backspace => rebinder.skip(),
+            // O Esc abre o menu de pausa, então não pode ser usado no mapeamento
+            Key::Escape => {}
+            _ => {
+                if let Err(e) = rebinder.press(&format!("{:?}", key)) {
+                    eprintln!("{}", e);
                 }
-
-                _ => println!("Unknown 0xE op: {:04X}", opcode),
-            },
-
-            //Set the I register to address NNN
-            0xA000..=0xAFFF => {
-                let addr = (opcode & 0x0FFF) as u16;
-                self.i = addr;
-                println!("Executed LD I, {:#05X}", addr);
             }
+        }
+        if rebinder.is_done() {
+            break;
+        }
+    }
+    if rebinder.is_done() {
+        window.set_title(title);
+        return false;
+    }
+    prompt_rebind(window, title, rebinder);
+    true
+}
 
-            //Return from subroutine
-            0x00EE => {
-                if self.sp == 0 {
-                    println!("Stack underflow!");
-                    return;
-                }
-                //Decrementa o ponteiro da pilha para pegar o endereço do topo
-                //Isso é necessário pois o ponteiro da pilha aponta para o próximo endereço livre
+// Escolha no terminal de uma das ROMs de um .zip
+fn pick_rom(source: &RomSource, names: &[String]) -> Option<usize> {
+    // A entrada padrão já foi usada para ler o .zip
+    if *source == RomSource::Stdin || !io::stdin().is_terminal() {
+        eprintln!(
+            "The archive has {} ROMs; extract the one to run",
+            names.len()
+        );
+        return None;
+    }
+    println!("The archive has {} ROMs:", names.len());
+    for (i, name) in names.iter().enumerate() {
+        println!("  {:>2}. {}", i + 1, name);
+    }
+    print!("Choose one [1-{}]: ", names.len());
+    io::stdout().flush().ok()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer).ok()?;
+    answer.trim().parse::<usize>().ok()?.checked_sub(1)
+}
 
-                self.sp -= 1;
+// Tudo que depende da linha de comando, do chip8.toml e da entrada da ROM no banco. A ordem de
+// prioridade é: opção da linha de comando, entrada do banco, chip8.toml e por fim os padrões
+struct Setup {
+    palette: Palette,
+    pixel_style: PixelStyle,
+    flicker: FlickerMode,
+    keymap: Keymap,
+    // Teclas do segundo teclado, só no CHIP-8X
+    second_keymap: Option<Keymap>,
+    platform: Platform,
+    detection: Option<Detection>,
+    quirks: Quirks,
+    instructions_per_frame: usize,
+    title: String,
+}
 
-                //Recupera o endereço do topo da pilha que representa o endereço de retorno
-                let return_addr = self.stack[self.sp as usize];
+fn configure(options: &Options, config: &Config, rom: &Rom) -> Result<Setup, String> {
+    let database = config.rom_database();
+    let entry = match database.lookup(&rom.bytes) {
+        Some(entry) => entry.clone(),
+        None => rom.settings.clone(),
+    };
+
+    // Sem --platform e sem entrada no banco, a plataforma vem do conteúdo da ROM
+    let (platform, detection) = match (options.platform, entry.platform()?) {
+        (Some(platform), _) | (None, Some(platform)) => (platform, None),
+        (None, None) => {
+            let detection = detect(&rom.bytes);
+            (detection.platform, Some(detection))
+        }
+    };
+    let mut quirks = platform.quirks();
+    // A espera pelo vídeo faz parte da velocidade do VIP, mas o chip8.toml ainda pode desligá-la
+    if options.timing == Timing::Vip {
+        quirks.display_wait = true;
+    }
+    config.quirks.apply(&mut quirks);
+    entry.quirks.apply(&mut quirks);
+
+    let palette = match (&options.palette, entry.colors().map_err(|e| e.to_string())?) {
+        (Some(name), _) => config.palette(name),
+        (None, Some(colors)) => Ok(colors),
+        (None, None) => match &entry.palette {
+            Some(name) => config.palette(name),
+            None => config.display_palette(),
+        },
+    }
+    .map_err(|e| e.to_string())?;
+
+    let keymap = config
+        .rom_keymap(options.keymap.as_deref(), &entry)
+        .map_err(|e| e.to_string())?;
+    let second_keymap = match platform {
+        Platform::Chip8X => Keymap::preset(SECOND_KEYPAD_PRESET),
+        _ => None,
+    };
+
+    let pixel_style = match options.pixel_style {
+        Some(style) => style,
+        None => config.pixel_style().map_err(|e| e.to_string())?,
+    };
+    let flicker = match options.flicker {
+        Some(mode) => mode,
+        None => config.flicker_mode().map_err(|e| e.to_string())?,
+    };
+    let instructions_per_frame = options
+        .instructions_per_frame
+        .or(entry.instructions_per_frame)
+        .unwrap_or(platform.instructions_per_frame());
+    let game = entry
+        .display_title()
+        .or_else(|| (!rom.name.is_empty()).then(|| rom.name.clone()));
+    let title = match game {
+        Some(game) => format!("{} - {}", WINDOW_TITLE, game),
+        None => WINDOW_TITLE.to_string(),
+    };
+
+    Ok(Setup {
+        palette,
+        pixel_style,
+        flicker,
+        keymap,
+        second_keymap,
+        platform,
+        detection,
+        quirks,
+        instructions_per_frame,
+        title,
+    })
+}
 
-                //Seta o pc para o endereço de retorno
-                self.pc = return_addr;
-                println!(
-                    "Executed RET (Return from subroutine) to {:#05X}",
-                    return_addr
-                );
-            }
+// A ROM em execução com a configuração dela
+struct Game {
+    rom: Rom,
+    setup: Setup,
+    chip8: Chip8,
+}
 
-            //1nnn - Jump to address nnn
-            //Instrução de setar um valor para o pc
-            0x1000..=0x1FFF => {
-                let addr = opcode & 0x0FFF;
-                self.pc = addr;
-                println!("Executed JP {:03X}", addr);
-                //Encerra o fluxo aqui pois se ele passar ele vai incrementar o pc no final do match
-                //o que sairia do endereço que acabou de ser gerado
-                return;
-            }
+// Chip8 novo com a ROM carregada, como ao ligar a máquina
+fn boot(rom: &Rom, setup: &Setup) -> Result<Chip8, Chip8Error> {
+    let mut chip8 = setup.platform.machine();
+    chip8.quirks = setup.quirks;
+    chip8.load_bytes_at(&rom.bytes, setup.platform.start_address())?;
+    Ok(chip8)
+}
 
-            // 2NNN: CALL NNN
-            //Call subroutine at NNN (push current PC to stack).
-            //Chama a subrotina no endereço NNN. Colocar no
-            0x2000..0x2FFF => {
-                let addr = opcode & 0x0FFF;
-                //Coloca o endereço atual do pc no topo da pilha que é o endereço para qual retornará ao final da subrotina
-                //A pilha guarda os endereços das subrotinas que estão sendo executadas
-                self.stack[self.sp as usize] = self.pc;
-                //Incrementa o ponteiro da pilha para caso uma nova subrotina seja chamada ela seja colocada no topo
-                self.sp += 1;
-                //Seta o pc para o endereço da subrotina
-                self.pc = addr;
-            }
+fn platform_message(setup: &Setup) -> String {
+    match &setup.detection {
+        Some(detection) => format!("Detected platform: {}", detection),
+        None => format!("Platform: {}", setup.platform),
+    }
+}
 
-            //6xkk - Set Vx = kk
-            //Passa um determinado valor para um register
-            0x6000..=0x6FFF => {
-                let x: usize = ((opcode & 0x0F00) >> 8) as usize;
-                let kk: u8 = (opcode & 0x00FF) as u8;
-                self.v[x] = kk;
-                println!("Executed LD V{:X}, {:#X}", x, kk);
-            }
+fn start_game(rom: Rom, options: &Options, config: &Config) -> Result<Game, String> {
+    let setup = configure(options, config, &rom)?;
+    if !rom.bytes.is_empty() {
+        println!("{}", platform_message(&setup));
+    }
+    let chip8 = boot(&rom, &setup).map_err(|e| format!("Failed to load ROM: {}", e))?;
+    Ok(Game { rom, setup, chip8 })
+}
 
-            //7xkk - Set Vx = Vx + kk
-            //Instrução que faz o somatório do valor atual do register com o valor em kk
-            0x7000..=0x7FFF => {
-                let x = ((opcode & 0x0F00) >> 8) as usize;
-                let kk = (opcode & 0x00FF) as u8;
-                self.v[x] = self.v[x].wrapping_add(kk);
-                println!("Executed ADD V{:X}, {:#X}", x, kk);
-            }
+fn open_game(source: &RomSource, options: &Options, config: &Config) -> Result<Game, String> {
+    let rom = source
+        .load(|names| pick_rom(source, names))
+        .map_err(|e| format!("Failed to load ROM: {}", e))?;
+    start_game(rom, options, config)
+}
 
-            0x8000..=0x8FFF => {
-                let x = ((opcode & 0x0F00) >> 8) as usize;
-                let y = ((opcode & 0x00F0) >> 4) as usize;
+// Menu aberto por cima da tela
+enum Overlay {
+    Pause,
+    Browser(RomBrowser),
+}
 
-                match opcode & 0x000F {
-                    0x0 => {
-                        self.v[x] = self.v[y];
-                        println!("Executed LD V{:X}, V{:X}", x, y);
-                    }
-                    0x1 => {
-                        self.v[x] |= self.v[y];
-                        println!("Executed OR V{:X}, V{:X}", x, y);
-                    }
-                    0x2 => {
-                        self.v[x] &= self.v[y];
-                        println!("Executed AND V{:X}, V{:X}", x, y);
-                    }
-                    0x3 => {
-                        self.v[x] ^= self.v[y];
-                        println!("Executed XOR V{:X}, V{:X}", x, y);
-                    }
-                    0x4 => {
-                        let (result, carry) = self.v[x].overflowing_add(self.v[y]);
-                        self.v[x] = result;
-                        self.v[0xF] = if carry { 1 } else { 0 };
-                        println!("Executed ADD V{:X}, V{:X} (with carry)", x, y);
-                    }
-                    0x5 => {
-                        let (result, borrow) = self.v[x].overflowing_sub(self.v[y]);
-                        self.v[x] = result;
-                        self.v[0xF] = if borrow { 0 } else { 1 };
-                        println!("Executed SUB V{:X}, V{:X}", x, y);
-                    }
-                    0x6 => {
-                        //Salva o bit menos significativo em VF
-                        self.v[0xF] = self.v[x] & 0x01;
-                        //Move o valor de VX 1 bit para direita
-                        self.v[x] >>= 1;
-                        println!("Executed SHR V{:X}", x);
-                    }
-                    0x7 => {
-                        let (result, borrow) = self.v[y].overflowing_sub(self.v[x]);
-                        self.v[x] = result;
-                        self.v[0xF] = if borrow { 0 } else { 1 };
-                        println!("Executed SUB V{:X}, V{:X}", x, y);
-                    }
-                    0xE => {
-                        //Salva o bit mais significativo em VF
-                        self.v[0xF] = (self.v[x] & 0x08) >> 7;
-                        //Move o valor de VX 1 bit para esquerda
-                        self.v[x] <<= 1;
-                        println!("Executed SHR V{:X}", x);
-                    }
-                    _ => println!("Unknown 0x8 instruction: {:04X}", opcode),
-                }
-            }
+fn open_browser(dir: &str) -> RomBrowser {
+    RomBrowser::scan(Path::new(dir)).unwrap_or_else(|e| {
+        let mut browser = RomBrowser::empty(Path::new(dir));
+        browser.message = Some(format!("Cannot read {}: {}", dir, e));
+        browser
+    })
+}
 
-            //Draw Sprites
-            //0xDXYN
-            0xD000..=0xDFFF => {
-                //recuperando o valor de x e movendo 8 bits para direita para ter o valor "puro"
-                let x = self.v[((opcode & 0x0F00) >> 8) as usize] as u16;
-                //recuperando o valor de x e movendo 8 bits para direita para ter o valor "puro"
-                let y = self.v[((opcode & 0x00F0) >> 4) as usize] as u16;
-                //recuperando a altura do sprite. A altura do sprite também representa seu tamanho em bytes
-                //pois para cada unidade de altura tem um byte (8 bits - 10101010) que será desenhado horizontalmente
-                //pois o sprite tem apenas 1 byte de largura
-                let height = (opcode & 0x000F) as u16;
-
-                self.v[0xF] = 0; // Reset VF
-
-                for byte in 0..height {
-                    //o modulo é usado para que caso a coordenada passe do limite da tela [VIDEO_HEIGHT] o pixel comece novamente em baixo ao invés de apenas n aparecer
-                    let y_coord = (y + byte) % VIDEO_HEIGHT as u16;
-                    //os bytes sprite que será desenhado está no endereço de memoria I e vai até I+N (ou I + height)
-                    let sprite = self.memory[(self.i + byte) as usize];
-
-                    //loop para desenhar a linha
-                    for bit in 0..8 {
-                        let x_coord = (x + bit) % VIDEO_WIDTH as u16;
-                        //index para acessar o pixel no video buffer. Como estamos trabalhando com um array de uma dimenção
-                        //para acessar o pixel (x,y) precisamos acessar o index [width*y+x]
-                        let index = (y_coord * VIDEO_WIDTH as u16 + x_coord) as usize;
-
-                        //para recuperar o bit atual que será desenhado
-                        let sprite_pixel = (sprite >> (7 - bit)) & 1;
-
-                        //pega o estado atual daquela posição no buffer de video
-                        let screen_pixel = self.video[index];
-
-                        //Ele checa se o sprite_pixel é 1, Se for 1 ele faz um toggle(se tiver ligado desliga, se tiver desligado liga)
-                        //do pixel no video buffer. Se for 0 não faz nada.
-                        // XOR the sprite pixel onto the screen
-                        self.video[index] ^= sprite_pixel != 0;
-
-                        //Se o pixel for desligado precisamos ligar a flag de colizão do register VF
-                        // Set VF if a pixel was unset (collision)
-                        if screen_pixel && !self.video[index] {
-                            self.v[0xF] = 1;
-                        }
-                    }
-                }
-                println!("Coloriu");
-            }
+// Mensagens para as quirks e a velocidade alteradas no menu de pausa
+fn setting_changes(before: (Quirks, usize), after: (Quirks, usize)) -> Vec<String> {
+    let on_off = |on: bool| if on { "on" } else { "off" };
+    let mut changes: Vec<String> = QUIRK_NAMES
+        .iter()
+        .zip(before.0.flags().into_iter().zip(after.0.flags()))
+        .filter(|(_, (old, new))| old != new)
+        .map(|(name, (_, new))| format!("{}: {}", name, on_off(new)))
+        .collect();
+    if before.1 != after.1 {
+        changes.push(format!("Speed: {} per frame", after.1));
+    }
+    changes
+}
 
-            0xF000..=0xFFFF => match opcode & 0x00FF {
-                //Timers -------------------------------------------
+// Teclas de navegação dos menus, com repetição quando mantidas pressionadas
+fn menu_keys(window: &Window) -> Vec<MenuKey> {
+    window
+        .get_keys_pressed(KeyRepeat::Yes)
+        .into_iter()
+        .filter_map(|key| match key {
+            Key::Up => Some(MenuKey::Up),
+            Key::Down => Some(MenuKey::Down),
+            Key::Left => Some(MenuKey::Left),
+            Key::Right => Some(MenuKey::Right),
+            Key::Enter | Key::Space => Some(MenuKey::Select),
+            Key::Escape | Key::Backspace => Some(MenuKey::Back),
+            _ => None,
+        })
+        .collect()
+}
 
-                // Vai salvar o valor do delay_timer em VX
-                0x07 => {
-                    let x = ((opcode & 0x0F00) >> 8) as usize;
-                    self.v[x] = self.delay_timer;
-                    println!("Executed LD V{:X}, DT", x);
-                }
+fn main() {
+    let options = match parse_args() {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
 
-                //Define o delay_timer com valor de VX
-                0x15 => {
-                    let x = ((opcode & 0x0F00) >> 8) as usize;
-                    self.delay_timer = self.v[x];
-                    println!("Executed LD DT, V{:X}", x);
+    let config = match load_config(&options) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    let roms_dir = options
+        .roms_dir
+        .clone()
+        .unwrap_or_else(|| DEFAULT_ROMS_DIR.to_string());
+    // Sem ROM na linha de comando a janela abre no navegador de ROMs
+    let rom = match &options.rom_path {
+        Some(path) => {
+            let source = RomSource::parse(path);
+            match source.load(|names| pick_rom(&source, names)) {
+                Ok(rom) => rom,
+                Err(e) => {
+                    eprintln!("Failed to load ROM: {}", e);
+                    return;
                 }
+            }
+        }
+        None if options.headless => {
+            eprintln!("--headless needs a ROM");
+            return;
+        }
+        None => Rom::default(),
+    };
+    let mut menu = if options.rom_path.is_none() {
+        Some(Overlay::Browser(open_browser(&roms_dir)))
+    } else {
+        None
+    };
+    let mut game = match start_game(rom, &options, &config) {
+        Ok(game) => game,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    // Religação de teclas em andamento (F7)
+    let mut rebinding: Option<Rebinder> = None;
+    let mut flicker = FlickerFilter::new(game.setup.flicker);
+    // Menu de pausa (Esc), que guarda o item e o slot escolhidos entre uma pausa e outra
+    let mut pause = PauseMenu::new(0);
+    let mut slots: Vec<Option<Chip8>> = vec![None; SAVE_SLOTS];
+    // Os blocos traduzidos valem só para a memória atual: trocar o Chip8 (reset, save state,
+    // outra ROM) exige um flush
+    let mut engine = Engine::new(&options, game.chip8.memory.len());
+    // Mensagens e contadores por cima da tela; F10 pausa sem abrir o menu
+    let mut osd = Osd::new(Instant::now(), OSD_SCALE);
+    let mut paused = false;
+    if !game.rom.bytes.is_empty() {
+        osd.message(platform_message(&game.setup), Instant::now());
+    }
 
-                //Define o sound_timer com o valor de VX
-                0x18 => {
-                    let x = ((opcode & 0x0F00) >> 8) as usize;
-                    self.sound_timer = self.v[x];
-                    println!("Executed LD ST, V{:X}", x);
+    let mut postfx = PostProcessor::new(if options.crt {
+        PostFxSettings::crt()
+    } else {
+        config.postfx
+    });
+
+    // Tela da ROM atual; o CHIP-10 tem 128x64
+    let mut resolution = game.chip8.resolution();
+    let mut audio = open_audio(&options);
+    let mut beeper = Beeper::new(options.tone, audio.sample_rate());
+
+    let mut window = if options.headless {
+        None
+    } else {
+        let window = Window::new(
+            &game.setup.title,
+            WINDOW_WIDTH,
+            WINDOW_HEIGHT,
+            WindowOptions {
+                scale: minifb::Scale::X1,
+                ..WindowOptions::default()
+            },
+        )
+        .unwrap_or_else(|e| panic!("{}", e));
+        Some(window)
+    };
+    let frames = options.frames.unwrap_or(if options.headless {
+        DEFAULT_HEADLESS_FRAMES
+    } else {
+        u64::MAX
+    });
+    let capture_scale = options.capture_scale.unwrap_or(1).max(1);
+    let mut recording = options
+        .record_path
+        .as_deref()
+        .and_then(|path| start_recording(path, resolution, capture_scale));
+    let mut dump = match &options.dump_path {
+        Some(name) => {
+            let (width, height) = resolution;
+            let (width, height) = (width * capture_scale, height * capture_scale);
+            match Dump::create(name, width, height, options.dump_y4m) {
+                Ok(dump) => Some(dump),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return;
                 }
+            }
+        }
+        None => None,
+    };
 
-                //IO---------------
-
-                //Soma o valor de VX ao de I
-                0x1E => {
-                    let x = ((opcode & 0x0F00) >> 8) as usize;
-                    self.i = self.i.wrapping_add(self.v[x] as u16);
+    // Frame buffer for minifb (32-bit color)
+    let mut buffer: Vec<u32> = vec![0; resolution.0 * resolution.1];
+    // Índices de plano de cada pixel, entrada do filtro de piscada
+    let mut pixels: Vec<u8> = vec![0; resolution.0 * resolution.1];
+    // Último quadro mostrado na janela, que fica por baixo dos menus
+    let mut screen: Vec<u32> = vec![0; WINDOW_WIDTH * WINDOW_HEIGHT];
+    // O `screen` com o OSD por cima, o que vai para a janela
+    let mut display: Vec<u32> = Vec::new();
+    // Redesenha a tela inteira no próximo quadro: na primeira vez, depois de um menu ou da pausa
+    // (a janela mostrou outra coisa) e depois de um quadro com a redução de piscada ou o CRT
+    let mut redraw = true;
+    // O último quadro enviado tinha OSD
+    let mut overlay_shown = false;
+
+    while game.chip8.frame_count < frames {
+        let mut take_screenshot = false;
+        let mut toggle_recording = false;
+        let mut turbo = false;
+        if let Some(window) = &mut window {
+            if !window.is_open() {
+                break;
+            }
+            // A emulação fica pausada enquanto as teclas são religadas
+            if let Some(rebinder) = &mut rebinding {
+                if !rebind_step(window, &game.setup.title, rebinder) {
+                    let rebinder = rebinding.take().unwrap();
+                    if rebinder.is_done() {
+                        game.setup.keymap = rebinder.finish();
+                        println!("New keymap (add it to chip8.toml to keep it):");
+                        print!("{}", game.setup.keymap.to_toml());
+                    }
                 }
+                window.update();
+                redraw = true;
+                sleep(Duration::from_millis(16));
+                continue;
+            }
 
-                0x0A => {
-                    let x = ((opcode & 0x0F00) >> 8) as usize;
-                    if let Some(pressed_key) = self.keypad.iter().position(|&k| k) {
-                        self.v[x] = pressed_key as u8;
-                        println!("Executed LD V{:X}, K", x);
-                    } else {
-                        self.pc -= 2;
+            // Esc pausa e abre o menu; com um menu aberto a emulação também fica parada
+            if menu.is_none() && window.is_key_pressed(Key::Escape, KeyRepeat::No) {
+                menu = Some(Overlay::Pause);
+            } else if let Some(current) = &mut menu {
+                let mut close = false;
+                let mut quit = false;
+                for key in menu_keys(window) {
+                    let now = Instant::now();
+                    match current {
+                        Overlay::Pause => {
+                            let before = (game.chip8.quirks, game.setup.instructions_per_frame);
+                            let action = pause.handle(
+                                key,
+                                &mut game.chip8.quirks,
+                                &mut game.setup.instructions_per_frame,
+                            );
+                            let after = (game.chip8.quirks, game.setup.instructions_per_frame);
+                            for change in setting_changes(before, after) {
+                                osd.message(change, now);
+                            }
+                            match action {
+                                Some(PauseAction::Resume) => close = true,
+                                // As quirks trocadas no menu continuam valendo depois do reset
+                                Some(PauseAction::Reset) => {
+                                    let quirks = game.chip8.quirks;
+                                    match boot(&game.rom, &game.setup) {
+                                        Ok(chip8) => {
+                                            game.chip8 = chip8;
+                                            engine.flush();
+                                            game.chip8.quirks = quirks;
+                                            osd.message("Reset", now);
+                                            close = true;
+                                        }
+                                        Err(e) => eprintln!("Reset failed: {}", e),
+                                    }
+                                }
+                                Some(PauseAction::SaveState(slot)) => {
+                                    slots[slot] = Some(game.chip8.clone());
+                                    osd.message(format!("Saved state to slot {}", slot + 1), now);
+                                }
+                                Some(PauseAction::LoadState(slot)) => match &slots[slot] {
+                                    Some(state) => {
+                                        game.chip8 = state.clone();
+                                        engine.flush();
+                                        osd.message(
+                                            format!("Loaded state from slot {}", slot + 1),
+                                            now,
+                                        );
+                                        close = true;
+                                    }
+                                    None => osd.message(format!("Slot {} is empty", slot + 1), now),
+                                },
+                                Some(PauseAction::Browse) => {
+                                    *current = Overlay::Browser(open_browser(&roms_dir));
+                                }
+                                Some(PauseAction::Quit) => quit = true,
+                                None => {}
+                            }
+                        }
+                        Overlay::Browser(browser) => match browser.handle(key) {
+                            Some(BrowserAction::Open(path)) => {
+                                let source = RomSource::File(path);
+                                match open_game(&source, &options, &config) {
+                                    Ok(new_game) => {
+                                        game = new_game;
+                                        engine.flush();
+                                        slots = vec![None; SAVE_SLOTS];
+                                        flicker.set_mode(game.setup.flicker);
+                                        window.set_title(&game.setup.title);
+                                        osd.message(platform_message(&game.setup), now);
+                                        close = true;
+                                    }
+                                    Err(e) => browser.message = Some(e),
+                                }
+                            }
+                            // Sem nenhuma ROM carregada não há para onde voltar
+                            Some(BrowserAction::Close) if game.rom.bytes.is_empty() => quit = true,
+                            Some(BrowserAction::Close) => *current = Overlay::Pause,
+                            None => {}
+                        },
+                    }
+                    if close || quit {
+                        break;
                     }
                 }
-
-                // Seta I com o endereço de um character armazenado em Vx
-                // Fontes normalmente ocupam 5 bytes e são armazenadas a partir do endereço 0x000
-                0x29 => {
-                    let x = ((opcode & 0x0F00) >> 8) as usize;
-                    let digit = self.v[x] as u16;
-                    self.i = digit * 5;
-                    println!("Executed LD F, V{:X} (char sprite addr)", x);
+                if quit {
+                    break;
                 }
-
-                // Armazena o valor de Vx em formato decimal nos endereços I, I+1 e I+2
-                0x33 => {
-                    let x = ((opcode & 0x0F00) >> 8) as usize;
-                    let vx = self.v[x];
-                    self.memory[self.i as usize] = vx / 100;
-                    self.memory[(self.i + 1) as usize] = (vx % 100) / 10;
-                    self.memory[(self.i + 2) as usize] = vx % 10;
-                    println!("Executed LD B, V{:X}", x);
+                if close {
+                    menu = None;
                 }
-
-                //Armazena os valores de V0 até Vx na memoria a partir do endereço I
-                0x55 => {
-                    let x = ((opcode & 0x0F00) >> 8) as usize;
-                    for i in 0..=x {
-                        self.memory[(self.i + i as u16) as usize] = self.v[i];
+            }
+            if let Some(current) = &menu {
+                let mut frame = screen.clone();
+                let frame_width = WINDOW_WIDTH;
+                match current {
+                    Overlay::Pause => {
+                        let saved: Vec<bool> = slots.iter().map(Option::is_some).collect();
+                        let lines = pause.lines(
+                            &game.chip8.quirks,
+                            game.setup.instructions_per_frame,
+                            &saved,
+                        );
+                        draw_menu(
+                            &mut frame,
+                            frame_width,
+                            MENU_SCALE,
+                            "Paused",
+                            &lines,
+                            PAUSE_HINT,
+                        );
                     }
-                    println!("Executed LD [I], V0..V{:X}", x);
-                }
-
-                //Armazena os valores a partir de I até x em V0 até Vx
-                0x65 => {
-                    let x = ((opcode & 0x0F00) >> 8) as usize;
-                    for i in 0..=x {
-                        self.v[i] = self.memory[(self.i + i as u16) as usize]
+                    Overlay::Browser(browser) => {
+                        let title = format!("ROMs in {}", browser.dir.display());
+                        let lines = browser.lines();
+                        draw_menu(
+                            &mut frame,
+                            frame_width,
+                            MENU_SCALE,
+                            &title,
+                            &lines,
+                            &browser.hint(),
+                        );
                     }
-                    println!("Executed LD V0..V{:X}, [I]", x);
                 }
-
-                _ => println!("Unknown 0xF instruction: {:04X}", opcode),
-            },
-
-            // Pula a próxima instrução caso Vx seja igual a kk
-            0x3000..=0x3FFF => {
-                let x = ((opcode & 0x0F00) >> 8) as usize;
-                let kk = (opcode & 0x00FF) as u8;
-                if self.v[x] == kk {
-                    self.pc += 2;
-                }
-                println!("Executed SE V{:X}, {:#X}", x, kk);
+                let now = Instant::now();
+                osd.stats.restart(now);
+                osd.draw(
+                    &mut frame,
+                    frame_width,
+                    now,
+                    RunState::Menu,
+                    &game.chip8,
+                    game.setup.instructions_per_frame,
+                );
+                window
+                    .update_with_buffer(&frame, frame_width, WINDOW_HEIGHT)
+                    .unwrap();
+                redraw = true;
+                sleep(Duration::from_millis(16));
+                continue;
             }
 
-            // Pula a próxima instrução caso Vx seja diferente a kk
-            0x4000..=0x4FFF => {
-                let x = ((opcode & 0x0F00) >> 8) as usize;
-                let kk = (opcode & 0x00FF) as u8;
-                if self.v[x] != kk {
-                    self.pc += 2;
-                }
-                println!("Executed SNE V{:X}, {:#X}", x, kk);
+            // F8 mostra FPS e instruções por segundo, F9 os registradores, F10 pausa
+            if window.is_key_pressed(Key::F8, KeyRepeat::No) {
+                osd.show_stats = !osd.show_stats;
             }
-
-            // Pula a próxima instrução caso Vx seja igual a Vy
-            0x5000..=0x5FFF => {
-                if (opcode & 0x000F) == 0x0 {
-                    let x = ((opcode & 0x0F00) >> 8) as usize;
-                    let y = ((opcode & 0x00F0) >> 4) as usize;
-                    if self.v[x] == self.v[y] {
-                        self.pc += 2;
-                    }
-                    println!("Executed SE V{:X}, V{:#X}", x, y);
-                } else {
-                    println!("Unknown 0x5 instruction: {:04X}", opcode);
+            if window.is_key_pressed(Key::F9, KeyRepeat::No) {
+                osd.show_registers = !osd.show_registers;
+            }
+            if window.is_key_pressed(Key::F10, KeyRepeat::No) {
+                paused = !paused;
+            }
+            if paused {
+                let mut frame = screen.clone();
+                let now = Instant::now();
+                osd.stats.restart(now);
+                osd.draw(
+                    &mut frame,
+                    WINDOW_WIDTH,
+                    now,
+                    RunState::Paused,
+                    &game.chip8,
+                    game.setup.instructions_per_frame,
+                );
+                window
+                    .update_with_buffer(&frame, WINDOW_WIDTH, WINDOW_HEIGHT)
+                    .unwrap();
+                redraw = true;
+                sleep(Duration::from_millis(16));
+                continue;
+            }
+            // Tab mantido pressionado roda sem o limite de 60 quadros por segundo
+            turbo = window.is_key_down(Key::Tab);
+
+            // F7 religa as teclas do CHIP-8 uma a uma
+            if window.is_key_pressed(Key::F7, KeyRepeat::No) {
+                let rebinder = Rebinder::new(&game.setup.keymap);
+                prompt_rebind(window, &game.setup.title, &rebinder);
+                rebinding = Some(rebinder);
+                continue;
+            }
+            // M liga e desliga o som
+            if window.is_key_pressed(Key::M, KeyRepeat::No) {
+                beeper.tone.muted = !beeper.tone.muted;
+                let sound = if beeper.tone.muted { "off" } else { "on" };
+                osd.message(format!("Sound: {}", sound), Instant::now());
+            }
+            toggle_postfx(window, &mut postfx.settings);
+            // F6 alterna os modos de redução de piscada
+            if window.is_key_pressed(Key::F6, KeyRepeat::No) {
+                flicker.set_mode(flicker.mode().next());
+                let message = format!("Flicker reduction: {}", flicker.mode());
+                println!("{}", message);
+                osd.message(message, Instant::now());
+            }
+            // F11 começa ou termina uma gravação, F12 salva um screenshot
+            toggle_recording = window.is_key_pressed(Key::F11, KeyRepeat::No);
+            take_screenshot = window.is_key_pressed(Key::F12, KeyRepeat::No);
+            // Teclas mantidas pressionadas agora; o core compara com o quadro anterior para saber
+            // quais foram pressionadas e quais foram soltas
+            let mut held = [false; 16];
+            let mut second = [false; 16];
+            for key in window.get_keys() {
+                let host = format!("{:?}", key);
+                if let Some(chip8_index) = game.setup.keymap.get(&host) {
+                    held[chip8_index as usize] = true;
+                }
+                if let Some(keymap) = &game.setup.second_keymap
+                    && let Some(chip8_index) = keymap.get(&host)
+                {
+                    second[chip8_index as usize] = true;
                 }
             }
+            for (key, &down) in held.iter().enumerate() {
+                game.chip8.set_key(key, down);
+            }
+            game.chip8.second_keypad = second;
+        }
 
-            // Pula a próxima instrução caso Vx seja diferente a Vy
-            0x9000..=0x9FFF => {
-                if (opcode & 0x000F) == 0x0 {
-                    let x = ((opcode & 0x0F00) >> 8) as usize;
-                    let y = ((opcode & 0x00F0) >> 4) as usize;
-                    if self.v[x] != self.v[y] {
-                        self.pc += 2;
-                    }
-                    println!("Executed SE V{:X}, V{:#X}", x, y);
-                } else {
-                    println!("Unknown 0x5 instruction: {:04X}", opcode);
+        let ran = engine.run_frame(
+            &mut game.chip8,
+            options.timing,
+            game.setup.instructions_per_frame,
+        );
+        let executed = match ran {
+            Ok(executed) => executed,
+            Err(e) => {
+                eprintln!("Emulation stopped: {}", e);
+                break;
+            }
+        };
+        osd.stats.frame(Instant::now(), executed);
+        // Outra ROM pode ter outra resolução: os buffers mudam de tamanho e a gravação e o dump,
+        // que têm tamanho fixo, terminam
+        if game.chip8.resolution() != resolution {
+            resolution = game.chip8.resolution();
+            buffer = vec![0; resolution.0 * resolution.1];
+            pixels = vec![0; resolution.0 * resolution.1];
+            flicker.set_mode(flicker.mode());
+            redraw = true;
+            if let Some(recording) = recording.take() {
+                stop_recording(recording);
+            }
+            if let Some(writer) = dump.take() {
+                println!("Resolution changed, video dump stopped");
+                if let Err(e) = writer.finish() {
+                    eprintln!("Dump error: {}", e);
                 }
             }
-
-            //Salva em Vx um (número aleatório de 0 a 255 AND kk)
-            0xC000..=0xCFFF => {
-                let x = ((opcode & 0x0F00) >> 8) as usize;
-                let kk = (opcode & 0x00FF) as u8;
-
-                let rnd: u8 = rand::random();
-                self.v[x] = rnd & kk;
-
-                println!("Executed RND V{:X}, {:#X} → random {:#X}", x, kk, rnd);
+        }
+        let (width, height) = resolution;
+        let scale = DISPLAY_SCALE * VIDEO_WIDTH / width;
+        // Fim do quadro (vblank): a tela e o som são lidos só aqui
+        if let Err(e) = audio.write(beeper.render(&game.chip8.audio_state())) {
+            eprintln!("Audio error: {}", e);
+        }
+        // Só as linhas que o core marcou como alteradas são refeitas. A redução de piscada e os
+        // efeitos de CRT dependem dos quadros anteriores ou da tela inteira, então com eles
+        // ligados tudo é redesenhado
+        let incremental = flicker.mode() == FlickerMode::Off && !postfx.settings.is_enabled();
+        let dirty = game.chip8.take_dirty_rows();
+        let rows = if incremental && !redraw {
+            dirty
+        } else {
+            ALL_ROWS
+        };
+        redraw = !incremental;
+        update_rows(
+            &game.chip8,
+            &mut pixels,
+            &mut buffer,
+            &mut flicker,
+            &game.setup.palette,
+            rows,
+        );
+
+        if toggle_recording {
+            recording = match recording.take() {
+                Some(recording) => {
+                    stop_recording(recording);
+                    None
+                }
+                None => start_recording(
+                    &format!("recording-{}.gif", game.chip8.frame_count),
+                    resolution,
+                    capture_scale,
+                ),
+            };
+        }
+        if let Some((_, recorder)) = &mut recording {
+            let frame = capture_frame(
+                &buffer,
+                width,
+                height,
+                capture_scale,
+                game.setup.pixel_style,
+                &game.setup.palette,
+            );
+            if let Err(e) = recorder.push(&frame) {
+                eprintln!("Recording error: {}", e);
+                recording = None;
             }
-
-            _ => print!("Unknown opcode! {:#06X}", opcode),
         }
-
-        //Como dois bytes são lidos de uma vez o Program Counter tem que pular dois endereços de memoria de uma vez
-        self.pc += 2;
-    }
-}
-fn main() {
-    let mut chip8 = Chip8::new();
-
-    chip8.load_rom("roms/random_number_test.ch8");
-    // chip8.load_test_instructions();
-
-    let width = VIDEO_WIDTH;
-    let height = VIDEO_HEIGHT;
-    let keymap = build_keymap();
-
-    let mut window = Window::new(
-        "CHIP-8 Emulator",
-        width * DISPLAY_SCALE,
-        height * DISPLAY_SCALE,
-        WindowOptions {
-            scale: minifb::Scale::X1,
-            ..WindowOptions::default()
-        },
-    )
-    .unwrap_or_else(|e| panic!("{}", e));
-
-    // Frame buffer for minifb (32-bit color)
-    let mut buffer: Vec<u32> = vec![0; width * height];
-
-    while window.is_open() && !window.is_key_down(Key::Escape) {
-        chip8.cycle();
-        chip8.tick_timers();
-        chip8.keypad = [false; 16]; // limpa o estado das teclas
-
-        for key in window.get_keys_pressed(minifb::KeyRepeat::Yes) {
-            if let Some(&chip8_index) = keymap.get(&key) {
-                chip8.keypad[chip8_index as usize] = true;
+        // Todo quadro emulado vira um quadro do dump, então o vídeo não depende do relógio
+        if let Some(writer) = &mut dump {
+            let frame = capture_frame(
+                &buffer,
+                width,
+                height,
+                capture_scale,
+                game.setup.pixel_style,
+                &game.setup.palette,
+            );
+            if let Err(e) = writer.write_frame(&frame) {
+                eprintln!("Dump error: {}", e);
+                dump = None;
             }
         }
-        sleep(Duration::from_millis(16));
-        // Update buffer: map chip8.video (bool) to white or black pixels
-        for (i, &pixel_on) in chip8.video.iter().enumerate() {
-            buffer[i] = if pixel_on { 0xFFFFFF } else { 0x000000 };
+        if take_screenshot {
+            let path = format!("screenshot-{}.png", game.chip8.frame_count);
+            save_screenshot(
+                &path,
+                &buffer,
+                resolution,
+                capture_scale,
+                game.setup.pixel_style,
+                &game.setup.palette,
+            );
         }
 
+        let Some(window) = &mut window else {
+            continue;
+        };
         // Expand to scale
-        let scaled_buffer = scale_buffer(&buffer, width, height, DISPLAY_SCALE);
-
-        window
-            .update_with_buffer(
-                &scaled_buffer,
-                width * DISPLAY_SCALE,
-                height * DISPLAY_SCALE,
-            )
-            .unwrap();
-    }
-}
+        if rows != 0 {
+            scale_rows_into(
+                &buffer,
+                width,
+                height,
+                scale,
+                game.setup.pixel_style,
+                &game.setup.palette,
+                rows,
+                &mut screen,
+            );
+            postfx.apply(&mut screen, WINDOW_WIDTH, WINDOW_HEIGHT, scale);
+        }
 
-fn scale_buffer(buffer: &[u32], width: usize, height: usize, scale: usize) -> Vec<u32> {
-    let scaled_width = width * scale;
-    let scaled_height = height * scale;
-
-    let mut scaled = vec![0u32; scaled_width * scaled_height];
-
-    for y in 0..height {
-        for x in 0..width {
-            let color = buffer[y * width + x];
-            for dy in 0..scale {
-                for dx in 0..scale {
-                    let sx = x * scale + dx;
-                    let sy = y * scale + dy;
-                    let scaled_index = sy * scaled_width + sx;
-                    scaled[scaled_index] = color;
-                }
-            }
+        // O OSD fica fora do `screen`, que é o fundo dos menus e da pausa. Sem nenhuma linha
+        // alterada e sem OSD (agora ou no quadro anterior, que precisa ser apagado) a janela
+        // só processa os eventos
+        let now = Instant::now();
+        let state = if turbo {
+            RunState::Turbo
+        } else {
+            RunState::Running
+        };
+        let overlay = osd.is_visible(now, state, game.setup.instructions_per_frame);
+        if rows != 0 || overlay || overlay_shown {
+            display.clone_from(&screen);
+            osd.draw(
+                &mut display,
+                WINDOW_WIDTH,
+                now,
+                state,
+                &game.chip8,
+                game.setup.instructions_per_frame,
+            );
+            window
+                .update_with_buffer(&display, WINDOW_WIDTH, WINDOW_HEIGHT)
+                .unwrap();
+        } else {
+            window.update();
+        }
+        overlay_shown = overlay;
+        if !turbo {
+            sleep(Duration::from_millis(16));
         }
     }
 
-    scaled
+    // A última tela fica salva mesmo se a emulação parou com erro, útil para ver onde uma ROM
+    // de teste falhou
+    if let Some(path) = &options.screenshot_path {
+        save_screenshot(
+            path,
+            &buffer,
+            resolution,
+            capture_scale,
+            game.setup.pixel_style,
+            &game.setup.palette,
+        );
+    }
+    if let Some(recording) = recording {
+        stop_recording(recording);
+    }
+    if let Some(writer) = dump
+        && let Err(e) = writer.finish()
+    {
+        eprintln!("Dump error: {}", e);
+    }
+    if let Err(e) = audio.finish() {
+        eprintln!("Audio error: {}", e);
+    }
 }
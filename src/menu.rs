@@ -0,0 +1,346 @@
+use crate::font::{self, LINE_HEIGHT};
+use crate::quirks::{QUIRK_NAMES, Quirks};
+use crate::source::{is_loadable, read_sidecar};
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Menus desenhados dentro da janela: o navegador de ROMs e o menu de pausa. Aqui ficam só o
+// estado e a navegação; o main traduz as teclas do minifb para MenuKey e executa as ações
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuKey {
+    Up,
+    Down,
+    Left,
+    Right,
+    Select,
+    Back,
+}
+
+// Uma linha pronta para desenhar
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MenuLine {
+    pub text: String,
+    pub selected: bool,
+}
+
+const TEXT_COLOR: u32 = 0xFFFFFF;
+const SELECTED_COLOR: u32 = 0xFFB000;
+const HINT_COLOR: u32 = 0x909090;
+
+// Primeira de `rows` linhas visíveis, acompanhando a selecionada
+pub fn first_visible(lines: &[MenuLine], rows: usize) -> usize {
+    let selected = lines.iter().position(|line| line.selected).unwrap_or(0);
+    selected
+        .saturating_sub(rows / 2)
+        .min(lines.len().saturating_sub(rows))
+}
+
+// Escurece a tela inteira e desenha o título, as linhas que couberem e a dica de rodapé
+pub fn draw_menu(
+    buffer: &mut [u32],
+    width: usize,
+    scale: usize,
+    title: &str,
+    lines: &[MenuLine],
+    hint: &str,
+) {
+    let height = buffer.len() / width;
+    let line = LINE_HEIGHT * scale;
+    let margin = line;
+    font::dim_rect(buffer, width, 0, 0, width, height, 0.8);
+    font::draw_text(buffer, width, margin, line / 2, title, scale, TEXT_COLOR);
+    // Sobram as linhas entre o título e o rodapé
+    let rows = (height / line).saturating_sub(4).max(1);
+    let first = first_visible(lines, rows);
+    for (i, entry) in lines.iter().skip(first).take(rows).enumerate() {
+        let (text, color) = if entry.selected {
+            (format!("> {}", entry.text), SELECTED_COLOR)
+        } else {
+            (format!("  {}", entry.text), TEXT_COLOR)
+        };
+        let y = line / 2 + (i + 2) * line;
+        font::draw_text(buffer, width, margin, y, &text, scale, color);
+    }
+    let y = height.saturating_sub(line + line / 2);
+    font::draw_text(buffer, width, margin, y, hint, scale, HINT_COLOR);
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrowserEntry {
+    pub path: PathBuf,
+    // Título do sidecar ou o nome do arquivo
+    pub title: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BrowserAction {
+    Open(PathBuf),
+    Close,
+}
+
+pub struct RomBrowser {
+    pub dir: PathBuf,
+    entries: Vec<BrowserEntry>,
+    selected: usize,
+    // Erro da última tentativa de abrir uma ROM, mostrado no rodapé
+    pub message: Option<String>,
+}
+
+impl RomBrowser {
+    // Lista as ROMs da pasta em ordem de título
+    pub fn scan(dir: &Path) -> io::Result<RomBrowser> {
+        let mut entries = Vec::new();
+        for file in std::fs::read_dir(dir)? {
+            let path = file?.path();
+            if !path.is_file() || !is_loadable(&path) {
+                continue;
+            }
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            // Um sidecar com erro não esconde a ROM; o erro aparece ao abrir
+            let title = read_sidecar(&path)
+                .ok()
+                .flatten()
+                .and_then(|entry| entry.display_title())
+                .unwrap_or(name);
+            entries.push(BrowserEntry { path, title });
+        }
+        entries.sort_by_cached_key(|entry| entry.title.to_lowercase());
+        Ok(RomBrowser {
+            entries,
+            ..RomBrowser::empty(dir)
+        })
+    }
+
+    // Navegador sem ROMs, para quando a pasta não pode ser lida
+    pub fn empty(dir: &Path) -> RomBrowser {
+        RomBrowser {
+            dir: dir.to_path_buf(),
+            entries: Vec::new(),
+            selected: 0,
+            message: None,
+        }
+    }
+
+    pub fn entries(&self) -> &[BrowserEntry] {
+        &self.entries
+    }
+
+    pub fn selected(&self) -> Option<&BrowserEntry> {
+        self.entries.get(self.selected)
+    }
+
+    // Cima e baixo andam uma ROM (dando a volta), esquerda e direita uma página
+    pub fn handle(&mut self, key: MenuKey) -> Option<BrowserAction> {
+        const PAGE: usize = 10;
+        let count = self.entries.len();
+        match key {
+            MenuKey::Up if count > 0 => self.selected = (self.selected + count - 1) % count,
+            MenuKey::Down if count > 0 => self.selected = (self.selected + 1) % count,
+            MenuKey::Left => self.selected = self.selected.saturating_sub(PAGE),
+            MenuKey::Right => {
+                self.selected = (self.selected + PAGE).min(count.saturating_sub(1));
+            }
+            MenuKey::Select => {
+                return self
+                    .selected()
+                    .map(|entry| BrowserAction::Open(entry.path.clone()));
+            }
+            MenuKey::Back => return Some(BrowserAction::Close),
+            _ => {}
+        }
+        None
+    }
+
+    pub fn lines(&self) -> Vec<MenuLine> {
+        if self.entries.is_empty() {
+            return vec![MenuLine {
+                text: format!("No ROMs in {}", self.dir.display()),
+                selected: false,
+            }];
+        }
+        self.entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| MenuLine {
+                text: entry.title.clone(),
+                selected: i == self.selected,
+            })
+            .collect()
+    }
+
+    pub fn hint(&self) -> String {
+        match &self.message {
+            Some(message) => message.clone(),
+            None => "Enter: load   Esc: back".to_string(),
+        }
+    }
+}
+
+pub const SAVE_SLOTS: usize = 4;
+
+// Velocidades oferecidas no menu, em instruções por quadro
+pub const SPEEDS: [usize; 13] = [1, 2, 5, 7, 10, 15, 20, 30, 50, 100, 200, 500, 1000];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauseItem {
+    Resume,
+    Reset,
+    SaveState,
+    LoadState,
+    Slot,
+    Speed,
+    // Posição em QUIRK_NAMES
+    Quirk(usize),
+    Browse,
+    Quit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauseAction {
+    Resume,
+    Reset,
+    SaveState(usize),
+    LoadState(usize),
+    Browse,
+    Quit,
+}
+
+pub struct PauseMenu {
+    selected: usize,
+    pub slot: usize,
+}
+
+impl PauseMenu {
+    pub fn new(slot: usize) -> Self {
+        PauseMenu { selected: 0, slot }
+    }
+
+    pub fn items() -> Vec<PauseItem> {
+        let mut items = vec![
+            PauseItem::Resume,
+            PauseItem::Reset,
+            PauseItem::SaveState,
+            PauseItem::LoadState,
+            PauseItem::Slot,
+            PauseItem::Speed,
+        ];
+        items.extend((0..QUIRK_NAMES.len()).map(PauseItem::Quirk));
+        items.extend([PauseItem::Browse, PauseItem::Quit]);
+        items
+    }
+
+    pub fn selected(&self) -> PauseItem {
+        PauseMenu::items()[self.selected]
+    }
+
+    // As quirks e a velocidade mudam na hora; o resto vira uma ação para o main
+    pub fn handle(
+        &mut self,
+        key: MenuKey,
+        quirks: &mut Quirks,
+        instructions_per_frame: &mut usize,
+    ) -> Option<PauseAction> {
+        let count = PauseMenu::items().len();
+        let step = match key {
+            MenuKey::Up => {
+                self.selected = (self.selected + count - 1) % count;
+                return None;
+            }
+            MenuKey::Down => {
+                self.selected = (self.selected + 1) % count;
+                return None;
+            }
+            MenuKey::Back => return Some(PauseAction::Resume),
+            MenuKey::Left => -1,
+            MenuKey::Right | MenuKey::Select => 1,
+        };
+        match (self.selected(), key) {
+            (PauseItem::Slot, _) => {
+                self.slot = (self.slot as isize + step).rem_euclid(SAVE_SLOTS as isize) as usize;
+            }
+            (PauseItem::Speed, _) => {
+                *instructions_per_frame = next_speed(*instructions_per_frame, step);
+            }
+            (PauseItem::Quirk(index), _) => {
+                let flag = quirks.flags_mut().into_iter().nth(index).unwrap();
+                *flag = !*flag;
+            }
+            (item, MenuKey::Select) => {
+                return match item {
+                    PauseItem::Resume => Some(PauseAction::Resume),
+                    PauseItem::Reset => Some(PauseAction::Reset),
+                    PauseItem::SaveState => Some(PauseAction::SaveState(self.slot)),
+                    PauseItem::LoadState => Some(PauseAction::LoadState(self.slot)),
+                    PauseItem::Browse => Some(PauseAction::Browse),
+                    PauseItem::Quit => Some(PauseAction::Quit),
+                    _ => None,
+                };
+            }
+            _ => {}
+        }
+        None
+    }
+
+    // `saved` diz quais slots têm estado salvo
+    pub fn lines(
+        &self,
+        quirks: &Quirks,
+        instructions_per_frame: usize,
+        saved: &[bool],
+    ) -> Vec<MenuLine> {
+        let flags = quirks.flags();
+        let on_off = |on: bool| if on { "on" } else { "off" };
+        PauseMenu::items()
+            .into_iter()
+            .enumerate()
+            .map(|(i, item)| {
+                let text = match item {
+                    PauseItem::Resume => "Resume".to_string(),
+                    PauseItem::Reset => "Reset".to_string(),
+                    PauseItem::SaveState => "Save state".to_string(),
+                    PauseItem::LoadState => "Load state".to_string(),
+                    PauseItem::Slot => {
+                        let used = saved.get(self.slot).copied().unwrap_or(false);
+                        format!(
+                            "Slot: < {} > {}",
+                            self.slot + 1,
+                            if used { "(saved)" } else { "(empty)" }
+                        )
+                    }
+                    PauseItem::Speed => format!("Speed: < {} > per frame", instructions_per_frame),
+                    PauseItem::Quirk(index) => {
+                        format!("{}: {}", QUIRK_NAMES[index], on_off(flags[index]))
+                    }
+                    PauseItem::Browse => "Browse ROMs".to_string(),
+                    PauseItem::Quit => "Quit".to_string(),
+                };
+                MenuLine {
+                    text,
+                    selected: i == self.selected,
+                }
+            })
+            .collect()
+    }
+}
+
+// Próxima velocidade da lista acima ou abaixo da atual (que pode não estar na lista)
+fn next_speed(current: usize, step: isize) -> usize {
+    if step > 0 {
+        SPEEDS
+            .iter()
+            .copied()
+            .find(|&speed| speed > current)
+            .unwrap_or(current)
+    } else {
+        SPEEDS
+            .iter()
+            .rev()
+            .copied()
+            .find(|&speed| speed < current)
+            .unwrap_or(current)
+    }
+}
@@ -0,0 +1,629 @@
+use std::collections::HashMap;
+
+// Assembler do Octo, para o código-fonte que vem dentro dos cartuchos .gif. Cobre o que os
+// programas usam no dia a dia: as instruções (`clear`, `sprite`, `i := hex v0`...), as atribuições
+// e operações com registradores (`v0 := 5`, `v1 += v2`...), rótulos (": nome", usados como
+// endereço, com `:call` ou sozinhos como chamada), `:const`, `:alias`, `:org`, `:byte`, `:unpack`,
+// `if ... then`, `if ... begin / else / end` e `loop / while / again`. Macros, `:calc`,
+// `:stringmode` e as outras diretivas dão erro: esses programas precisam ser exportados do Octo
+// como .ch8
+
+const START_ADDRESS: usize = 0x200;
+const MEMORY_END: usize = 0x10000;
+
+// Onde um endereço ainda desconhecido vai ser escrito quando o rótulo aparecer
+#[derive(Debug, Clone, Copy)]
+enum Fixup {
+    // 12 bits baixos de uma instrução (jump, :call, i := ...)
+    Address,
+    // A palavra depois do F000 do `i := long`
+    Long,
+    // Os dois 6xkk do :unpack: v0 com o nibble dado e os 4 bits altos, v1 com o byte baixo. Com
+    // `long`, v0 recebe o byte alto inteiro
+    Unpack { long: bool },
+}
+
+// Blocos abertos, fechados por else/end/again
+enum Block {
+    // O salto de `if ... begin` que pula o corpo quando a condição é falsa
+    Begin(usize),
+    // O salto no fim do corpo que pula o else
+    Else(usize),
+    Loop { start: usize, breaks: Vec<usize> },
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Operand {
+    Register(u8),
+    Byte(u8),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Condition<'a> {
+    Compare(u8, &'a str, Operand),
+    // Tecla de Vx pressionada (`key`) ou solta (`-key`)
+    Key(u8, bool),
+}
+
+struct Assembler<'a> {
+    tokens: Vec<(usize, &'a str)>,
+    position: usize,
+    line: usize,
+    // Já passou do ponto em que o jump para o main teria que entrar
+    started: bool,
+    // Próximo endereço a ser escrito e a ROM a partir do 0x200
+    here: usize,
+    rom: Vec<u8>,
+    labels: HashMap<&'a str, usize>,
+    constants: HashMap<&'a str, i64>,
+    aliases: HashMap<&'a str, u8>,
+    fixups: Vec<(usize, usize, Fixup, &'a str)>,
+    blocks: Vec<Block>,
+}
+
+// Monta o programa, que começa no 0x200. Os erros dizem a linha do código-fonte
+pub fn assemble(source: &str) -> Result<Vec<u8>, String> {
+    let tokens = source
+        .lines()
+        .enumerate()
+        .flat_map(|(n, line)| {
+            let code = line.split('#').next().unwrap_or("");
+            code.split_whitespace().map(move |token| (n + 1, token))
+        })
+        .collect();
+    let mut assembler = Assembler {
+        tokens,
+        position: 0,
+        line: 1,
+        started: false,
+        here: START_ADDRESS,
+        rom: Vec::new(),
+        labels: HashMap::new(),
+        constants: HashMap::new(),
+        aliases: HashMap::new(),
+        fixups: Vec::new(),
+        blocks: Vec::new(),
+    };
+    assembler
+        .run()
+        .map_err(|e| format!("Octo source, line {}: {}", assembler.line, e))?;
+    Ok(assembler.rom)
+}
+
+fn number(token: &str) -> Option<i64> {
+    let (negative, digits) = match token.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, token),
+    };
+    let value = if let Some(hex) = digits.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if let Some(binary) = digits.strip_prefix("0b") {
+        i64::from_str_radix(binary, 2).ok()?
+    } else if digits.bytes().all(|b| b.is_ascii_digit()) {
+        digits.parse().ok()?
+    } else {
+        return None;
+    };
+    Some(if negative { -value } else { value })
+}
+
+// v0 a vf (o Octo aceita o dígito maiúsculo também)
+fn register_name(token: &str) -> Option<u8> {
+    let digit = token.strip_prefix('v')?;
+    if digit.len() != 1 {
+        return None;
+    }
+    u8::from_str_radix(digit, 16).ok()
+}
+
+impl<'a> Assembler<'a> {
+    fn run(&mut self) -> Result<(), String> {
+        while self.position < self.tokens.len() {
+            self.statement()?;
+        }
+        match self.blocks.last() {
+            Some(Block::Loop { .. }) => return Err("'loop' without 'again'".to_string()),
+            Some(_) => return Err("'begin' without 'end'".to_string()),
+            None => {}
+        }
+        if !self.labels.contains_key("main") {
+            return Err("the program has no 'main' label".to_string());
+        }
+        for (line, at, fixup, name) in std::mem::take(&mut self.fixups) {
+            self.line = line;
+            let address = *self
+                .labels
+                .get(name)
+                .ok_or_else(|| format!("undefined name '{}'", name))?;
+            self.resolve(at, fixup, address as i64)?;
+        }
+        if START_ADDRESS + self.rom.len() > MEMORY_END {
+            return Err("the program does not fit in memory".to_string());
+        }
+        Ok(())
+    }
+
+    fn next(&mut self) -> Result<&'a str, String> {
+        let &(line, token) = self
+            .tokens
+            .get(self.position)
+            .ok_or("unexpected end of the program")?;
+        self.position += 1;
+        self.line = line;
+        Ok(token)
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.position).map(|&(_, token)| token)
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), String> {
+        let token = self.next()?;
+        if token == expected {
+            Ok(())
+        } else {
+            Err(format!("expected '{}', found '{}'", expected, token))
+        }
+    }
+
+    fn emit(&mut self, byte: u8) {
+        let index = self.here - START_ADDRESS;
+        if index >= self.rom.len() {
+            self.rom.resize(index + 1, 0);
+        }
+        self.rom[index] = byte;
+        self.here += 1;
+    }
+
+    fn instruction(&mut self, opcode: u16) {
+        let [high, low] = opcode.to_be_bytes();
+        self.emit(high);
+        self.emit(low);
+    }
+
+    fn register(&mut self) -> Result<u8, String> {
+        let token = self.next()?;
+        self.register_of(token)
+            .ok_or_else(|| format!("expected a register, found '{}'", token))
+    }
+
+    fn register_of(&self, token: &str) -> Option<u8> {
+        register_name(token).or_else(|| self.aliases.get(token).copied())
+    }
+
+    // Número ou constante
+    fn value_of(&self, token: &str) -> Option<i64> {
+        number(token).or_else(|| self.constants.get(token).copied())
+    }
+
+    fn value(&mut self) -> Result<i64, String> {
+        let token = self.next()?;
+        self.value_of(token)
+            .ok_or_else(|| format!("expected a number, found '{}'", token))
+    }
+
+    // Bytes negativos valem como em complemento de dois (`v0 += -1`)
+    fn byte(&mut self) -> Result<u8, String> {
+        match self.value()? {
+            value @ -128..=255 => Ok(value as u8),
+            value => Err(format!("{} does not fit in a byte", value)),
+        }
+    }
+
+    fn nibble(&mut self) -> Result<u16, String> {
+        match self.value()? {
+            value @ 0..=15 => Ok(value as u16),
+            value => Err(format!("{} does not fit in 4 bits", value)),
+        }
+    }
+
+    fn name(&mut self) -> Result<&'a str, String> {
+        let token = self.next()?;
+        if number(token).is_some() || register_name(token).is_some() {
+            return Err(format!("'{}' cannot be used as a name", token));
+        }
+        Ok(token)
+    }
+
+    // Endereço de uma instrução já emitida em `at`: números e constantes entram direto, rótulos
+    // quando a montagem termina e todos já foram vistos
+    fn refer(&mut self, at: usize, fixup: Fixup, target: &'a str) -> Result<(), String> {
+        match self.value_of(target) {
+            Some(address) => self.resolve(at, fixup, address),
+            None => {
+                self.fixups.push((self.line, at, fixup, target));
+                Ok(())
+            }
+        }
+    }
+
+    fn resolve(&mut self, at: usize, fixup: Fixup, address: i64) -> Result<(), String> {
+        let limit = match fixup {
+            Fixup::Address | Fixup::Unpack { long: false } => 0xFFF,
+            Fixup::Long | Fixup::Unpack { long: true } => 0xFFFF,
+        };
+        if !(0..=limit).contains(&address) {
+            return Err(format!("address {:#X} is out of range", address));
+        }
+        let [high, low] = (address as u16).to_be_bytes();
+        let index = at - START_ADDRESS;
+        let rom = &mut self.rom;
+        match fixup {
+            Fixup::Address => {
+                rom[index] |= high;
+                rom[index + 1] = low;
+            }
+            Fixup::Long => rom[index..index + 2].copy_from_slice(&[high, low]),
+            Fixup::Unpack { long } => {
+                if long {
+                    rom[index + 1] = high;
+                } else {
+                    rom[index + 1] |= high;
+                }
+                rom[index + 3] = low;
+            }
+        }
+        Ok(())
+    }
+
+    // Troca o destino de um jump já emitido
+    fn patch_jump(&mut self, at: usize, target: usize) -> Result<(), String> {
+        self.resolve(at, Fixup::Address, target as i64)
+    }
+
+    fn statement(&mut self) -> Result<(), String> {
+        let token = self.next()?;
+        // A execução começa no 0x200: se algo vai ocupar memória antes de `: main`, o Octo põe ali
+        // um jump até ele
+        let layout_only = matches!(token, ":const" | ":alias" | ":breakpoint")
+            || (token == ":" && self.peek() == Some("main"));
+        if !layout_only && !self.started {
+            self.started = true;
+            if !self.labels.contains_key("main") {
+                self.fixups
+                    .push((self.line, self.here, Fixup::Address, "main"));
+                self.instruction(0x1000);
+            }
+        }
+        match token {
+            ":" => {
+                let name = self.name()?;
+                if self.labels.insert(name, self.here).is_some() {
+                    return Err(format!("label '{}' is defined twice", name));
+                }
+            }
+            ":const" => {
+                let name = self.name()?;
+                let value = self.value()?;
+                self.constants.insert(name, value);
+            }
+            ":alias" => {
+                let name = self.name()?;
+                let register = self.register()?;
+                self.aliases.insert(name, register);
+            }
+            ":org" => {
+                let address = self.value()?;
+                if !(START_ADDRESS as i64..MEMORY_END as i64).contains(&address) {
+                    return Err(format!("cannot place code at {:#X}", address));
+                }
+                self.here = address as usize;
+            }
+            ":byte" => {
+                let byte = self.byte()?;
+                self.emit(byte);
+            }
+            ":call" => {
+                let target = self.next()?;
+                let at = self.here;
+                self.instruction(0x2000);
+                self.refer(at, Fixup::Address, target)?;
+            }
+            ":unpack" => {
+                let long = self.peek() == Some("long");
+                let nibble = if long {
+                    self.next()?;
+                    0
+                } else {
+                    self.nibble()?
+                };
+                let target = self.next()?;
+                let at = self.here;
+                self.instruction(0x6000 | nibble << 4);
+                self.instruction(0x6100);
+                self.refer(at, Fixup::Unpack { long }, target)?;
+            }
+            // Só fazem sentido no depurador do Octo
+            ":breakpoint" => {
+                self.next()?;
+            }
+            "clear" => self.instruction(0x00E0),
+            "return" | ";" => self.instruction(0x00EE),
+            "hires" => self.instruction(0x00FF),
+            "lores" => self.instruction(0x00FE),
+            "exit" => self.instruction(0x00FD),
+            "scroll-down" => {
+                let n = self.nibble()?;
+                self.instruction(0x00C0 | n);
+            }
+            "scroll-up" => {
+                let n = self.nibble()?;
+                self.instruction(0x00D0 | n);
+            }
+            "scroll-right" => self.instruction(0x00FB),
+            "scroll-left" => self.instruction(0x00FC),
+            "audio" => self.instruction(0xF002),
+            "plane" => {
+                let n = self.nibble()?;
+                self.instruction(0xF001 | n << 8);
+            }
+            "jump" | "jump0" | "native" => {
+                let opcode = match token {
+                    "jump" => 0x1000,
+                    "jump0" => 0xB000,
+                    _ => 0x0000,
+                };
+                let target = self.next()?;
+                let at = self.here;
+                self.instruction(opcode);
+                self.refer(at, Fixup::Address, target)?;
+            }
+            "sprite" => {
+                let x = self.register()? as u16;
+                let y = self.register()? as u16;
+                let n = self.nibble()?;
+                self.instruction(0xD000 | x << 8 | y << 4 | n);
+            }
+            "save" | "load" => {
+                let x = self.register()? as u16;
+                if self.peek() == Some("-") {
+                    self.next()?;
+                    let y = self.register()? as u16;
+                    let n = if token == "save" { 2 } else { 3 };
+                    self.instruction(0x5000 | x << 8 | y << 4 | n);
+                } else {
+                    let kk = if token == "save" { 0x55 } else { 0x65 };
+                    self.instruction(0xF000 | x << 8 | kk);
+                }
+            }
+            "saveflags" | "loadflags" | "bcd" => {
+                let x = self.register()? as u16;
+                let kk = match token {
+                    "saveflags" => 0x75,
+                    "loadflags" => 0x85,
+                    _ => 0x33,
+                };
+                self.instruction(0xF000 | x << 8 | kk);
+            }
+            "delay" | "buzzer" | "pitch" => {
+                self.expect(":=")?;
+                let x = self.register()? as u16;
+                let kk = match token {
+                    "delay" => 0x15,
+                    "buzzer" => 0x18,
+                    _ => 0x3A,
+                };
+                self.instruction(0xF000 | x << 8 | kk);
+            }
+            "i" => self.index()?,
+            "if" => {
+                let condition = self.condition()?;
+                match self.next()? {
+                    // A próxima instrução é pulada quando a condição é falsa
+                    "then" => self.skip(condition, false),
+                    "begin" => {
+                        self.skip(condition, true);
+                        self.blocks.push(Block::Begin(self.here));
+                        self.instruction(0x1000);
+                    }
+                    other => return Err(format!("expected 'then' or 'begin', found '{}'", other)),
+                }
+            }
+            "else" => {
+                let Some(Block::Begin(begin)) = self.blocks.pop() else {
+                    return Err("'else' without 'begin'".to_string());
+                };
+                let jump = self.here;
+                self.instruction(0x1000);
+                self.patch_jump(begin, self.here)?;
+                self.blocks.push(Block::Else(jump));
+            }
+            "end" => match self.blocks.pop() {
+                Some(Block::Begin(jump) | Block::Else(jump)) => self.patch_jump(jump, self.here)?,
+                _ => return Err("'end' without 'begin'".to_string()),
+            },
+            "loop" => self.blocks.push(Block::Loop {
+                start: self.here,
+                breaks: Vec::new(),
+            }),
+            "while" => {
+                let condition = self.condition()?;
+                self.skip(condition, true);
+                let jump = self.here;
+                self.instruction(0x1000);
+                let innermost = self.blocks.iter_mut().rev().find_map(|block| match block {
+                    Block::Loop { breaks, .. } => Some(breaks),
+                    _ => None,
+                });
+                innermost.ok_or("'while' outside a loop")?.push(jump);
+            }
+            "again" => {
+                let Some(Block::Loop { start, breaks }) = self.blocks.pop() else {
+                    return Err("'again' without 'loop'".to_string());
+                };
+                let jump = self.here;
+                self.instruction(0x1000);
+                self.patch_jump(jump, start)?;
+                for jump in breaks {
+                    self.patch_jump(jump, self.here)?;
+                }
+            }
+            _ if token.starts_with(':') => {
+                return Err(format!(
+                    "unsupported Octo directive '{}' (export this cartridge from Octo as a .ch8)",
+                    token
+                ));
+            }
+            _ => {
+                if let Some(x) = self.register_of(token) {
+                    self.assignment(x as u16)?;
+                } else if let Some(value) = self.value_of(token) {
+                    // Um número solto é um byte de dados
+                    match value {
+                        -128..=255 => self.emit(value as u8),
+                        _ => return Err(format!("{} does not fit in a byte", value)),
+                    }
+                } else if token
+                    .chars()
+                    .next()
+                    .is_some_and(|c| c.is_alphabetic() || c == '_')
+                {
+                    // Um nome solto chama a sub-rotina com esse rótulo
+                    let at = self.here;
+                    self.instruction(0x2000);
+                    self.refer(at, Fixup::Address, token)?;
+                } else {
+                    return Err(format!("unexpected '{}'", token));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn index(&mut self) -> Result<(), String> {
+        match self.next()? {
+            ":=" => match self.next()? {
+                "long" => {
+                    let target = self.next()?;
+                    self.instruction(0xF000);
+                    let at = self.here;
+                    self.instruction(0x0000);
+                    self.refer(at, Fixup::Long, target)
+                }
+                font @ ("hex" | "bighex") => {
+                    let font = if font == "hex" { 0x29 } else { 0x30 };
+                    let x = self.register()? as u16;
+                    self.instruction(0xF000 | x << 8 | font);
+                    Ok(())
+                }
+                target => {
+                    let at = self.here;
+                    self.instruction(0xA000);
+                    self.refer(at, Fixup::Address, target)
+                }
+            },
+            "+=" => {
+                let x = self.register()? as u16;
+                self.instruction(0xF01E | x << 8);
+                Ok(())
+            }
+            other => Err(format!(
+                "expected ':=' or '+=' after 'i', found '{}'",
+                other
+            )),
+        }
+    }
+
+    // vx := ..., vx += ... e as outras operações do 8xyN
+    fn assignment(&mut self, x: u16) -> Result<(), String> {
+        let operator = self.next()?;
+        let alu = match operator {
+            ":=" => 0x0,
+            "|=" => 0x1,
+            "&=" => 0x2,
+            "^=" => 0x3,
+            "+=" => 0x4,
+            "-=" => 0x5,
+            ">>=" => 0x6,
+            "=-" => 0x7,
+            "<<=" => 0xE,
+            other => return Err(format!("unknown operator '{}'", other)),
+        };
+        let operand = self.next()?;
+        if let Some(y) = self.register_of(operand) {
+            self.instruction(0x8000 | x << 8 | (y as u16) << 4 | alu);
+            return Ok(());
+        }
+        let opcode = match (operator, operand) {
+            (":=", "random") => 0xC000 | self.byte()? as u16,
+            (":=", "key") => 0xF00A,
+            (":=", "delay") => 0xF007,
+            (":=" | "+=" | "-=", _) => {
+                self.position -= 1;
+                let byte = self.byte()?;
+                match operator {
+                    ":=" => 0x6000 | byte as u16,
+                    "+=" => 0x7000 | byte as u16,
+                    _ => 0x7000 | byte.wrapping_neg() as u16,
+                }
+            }
+            _ => {
+                return Err(format!(
+                    "'{}' needs a register, found '{}'",
+                    operator, operand
+                ));
+            }
+        };
+        self.instruction(opcode | x << 8);
+        Ok(())
+    }
+
+    fn condition(&mut self) -> Result<Condition<'a>, String> {
+        let x = self.register()?;
+        let operator = self.next()?;
+        match operator {
+            "key" => Ok(Condition::Key(x, true)),
+            "-key" => Ok(Condition::Key(x, false)),
+            "==" | "!=" | "<" | ">" | "<=" | ">=" => {
+                let token = self.next()?;
+                let operand = match self.register_of(token) {
+                    Some(y) => Operand::Register(y),
+                    None => {
+                        self.position -= 1;
+                        Operand::Byte(self.byte()?)
+                    }
+                };
+                Ok(Condition::Compare(x, operator, operand))
+            }
+            other => Err(format!("unknown comparison '{}'", other)),
+        }
+    }
+
+    // Emite o que faz a instrução seguinte ser pulada quando a condição vale `when`
+    fn skip(&mut self, condition: Condition, when: bool) {
+        match condition {
+            Condition::Key(x, pressed) => {
+                let kk = if pressed == when { 0x9E } else { 0xA1 };
+                self.instruction(0xE000 | (x as u16) << 8 | kk);
+            }
+            Condition::Compare(x, operator @ ("==" | "!="), operand) => {
+                let x = (x as u16) << 8;
+                let equal = (operator == "==") == when;
+                self.instruction(match (operand, equal) {
+                    (Operand::Byte(kk), true) => 0x3000 | x | kk as u16,
+                    (Operand::Byte(kk), false) => 0x4000 | x | kk as u16,
+                    (Operand::Register(y), true) => 0x5000 | x | (y as u16) << 4,
+                    (Operand::Register(y), false) => 0x9000 | x | (y as u16) << 4,
+                });
+            }
+            // As desigualdades passam pelo VF: ele recebe o operando e a subtração deixa no flag
+            // 0 quando `>` (vf -= vx) ou `<` (vf =- vx) vale
+            Condition::Compare(x, operator, operand) => {
+                self.instruction(match operand {
+                    Operand::Byte(kk) => 0x6F00 | kk as u16,
+                    Operand::Register(y) => 0x8F00 | (y as u16) << 4,
+                });
+                let x = (x as u16) << 4;
+                let strict = matches!(operator, ">" | "<");
+                let subtract = if matches!(operator, ">" | "<=") {
+                    0x8F05
+                } else {
+                    0x8F07
+                };
+                self.instruction(subtract | x);
+                let flag = if strict { 0 } else { 1 };
+                self.instruction(if when { 0x3F00 } else { 0x4F00 } | flag);
+            }
+        }
+    }
+}
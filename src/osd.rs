@@ -0,0 +1,260 @@
+use crate::cpu::Chip8;
+use crate::font::{self, GLYPH_HEIGHT, LINE_HEIGHT};
+use std::time::{Duration, Instant};
+
+// Camada de informações desenhada por cima da tela já ampliada (depois do scale_buffer e dos
+// efeitos de CRT, para o texto não ser curvado nem borrado): desempenho, estado da emulação,
+// mensagens que somem sozinhas e um painel com os registradores. O main decide o que mostrar
+
+// Tempo que uma mensagem fica inteira na tela e quanto ela leva para sumir depois disso
+pub const MESSAGE_TIME: Duration = Duration::from_secs(2);
+pub const FADE_TIME: Duration = Duration::from_millis(500);
+// Quando chega uma mensagem a mais a mais antiga sai
+const MAX_MESSAGES: usize = 4;
+// Intervalo de medição do FPS e das instruções por segundo
+const STATS_INTERVAL: Duration = Duration::from_millis(500);
+
+const TEXT_COLOR: u32 = 0xFFFFFF;
+const STATUS_COLOR: u32 = 0xFFB000;
+const HUD_COLOR: u32 = 0x80FF80;
+// Quanto o fundo atrás do texto é escurecido
+const BACKDROP: f32 = 0.6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunState {
+    Running,
+    Paused,
+    // Sem o limite de 60 quadros por segundo
+    Turbo,
+    // Parado num menu, que já ocupa a tela: só as mensagens aparecem
+    Menu,
+}
+
+impl RunState {
+    fn label(self) -> Option<&'static str> {
+        match self {
+            RunState::Running | RunState::Menu => None,
+            RunState::Paused => Some("PAUSED"),
+            RunState::Turbo => Some("TURBO"),
+        }
+    }
+}
+
+// Quadros e instruções por segundo, recalculados a cada STATS_INTERVAL
+pub struct Stats {
+    start: Instant,
+    frames: u32,
+    instructions: u64,
+    pub fps: f64,
+    pub ips: f64,
+}
+
+impl Stats {
+    pub fn new(now: Instant) -> Stats {
+        Stats {
+            start: now,
+            frames: 0,
+            instructions: 0,
+            fps: 0.0,
+            ips: 0.0,
+        }
+    }
+
+    // Chamado a cada quadro emulado com o número de instruções executadas nele
+    pub fn frame(&mut self, now: Instant, instructions: usize) {
+        self.frames += 1;
+        self.instructions += instructions as u64;
+        let elapsed = now.duration_since(self.start);
+        if elapsed >= STATS_INTERVAL {
+            let seconds = elapsed.as_secs_f64();
+            self.fps = self.frames as f64 / seconds;
+            self.ips = self.instructions as f64 / seconds;
+            self.restart(now);
+        }
+    }
+
+    // Começa uma medição nova sem perder os últimos valores, para o tempo parado em pausa ou
+    // num menu não entrar na conta
+    pub fn restart(&mut self, now: Instant) {
+        self.start = now;
+        self.frames = 0;
+        self.instructions = 0;
+    }
+}
+
+// 1234 -> "1234", 48000 -> "48.0K", 2500000 -> "2.50M"
+pub fn format_rate(rate: f64) -> String {
+    if rate >= 1_000_000.0 {
+        format!("{:.2}M", rate / 1_000_000.0)
+    } else if rate >= 1_000.0 {
+        format!("{:.1}K", rate / 1_000.0)
+    } else {
+        format!("{:.0}", rate)
+    }
+}
+
+// Opacidade de uma mensagem com essa idade: 1 até MESSAGE_TIME, depois cai até 0 em FADE_TIME
+pub fn opacity(age: Duration) -> f32 {
+    match age.checked_sub(MESSAGE_TIME) {
+        None => 1.0,
+        Some(fading) => (1.0 - fading.as_secs_f32() / FADE_TIME.as_secs_f32()).max(0.0),
+    }
+}
+
+// Painel de registradores: PC, I, pilha, timers e V0 a VF em duas linhas
+pub fn register_lines(chip8: &Chip8) -> Vec<String> {
+    let registers = |range: std::ops::Range<usize>| {
+        range
+            .map(|x| format!("V{:X} {:02X}", x, chip8.v[x]))
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    vec![
+        format!(
+            "PC {:04X}  I {:04X}  SP {:X}  DT {:02X}  ST {:02X}",
+            chip8.pc, chip8.i, chip8.sp, chip8.delay_timer, chip8.sound_timer
+        ),
+        registers(0..8),
+        registers(8..16),
+    ]
+}
+
+pub struct Osd {
+    messages: Vec<(String, Instant)>,
+    pub stats: Stats,
+    // Linha com FPS, instruções por segundo e instruções por quadro
+    pub show_stats: bool,
+    pub show_registers: bool,
+    // Tamanho da fonte em relação ao glifo de 5x7; o painel de registradores usa a metade
+    pub scale: usize,
+}
+
+impl Osd {
+    pub fn new(now: Instant, scale: usize) -> Osd {
+        Osd {
+            messages: Vec::new(),
+            stats: Stats::new(now),
+            show_stats: false,
+            show_registers: false,
+            scale: scale.max(1),
+        }
+    }
+
+    pub fn message(&mut self, text: impl Into<String>, now: Instant) {
+        self.messages.push((text.into(), now));
+        if self.messages.len() > MAX_MESSAGES {
+            self.messages.remove(0);
+        }
+    }
+
+    // Mensagens ainda visíveis, da mais antiga para a mais nova, com a opacidade de cada uma.
+    // As que já sumiram são descartadas
+    pub fn messages(&mut self, now: Instant) -> Vec<(&str, f32)> {
+        self.messages
+            .retain(|(_, shown)| now.duration_since(*shown) < MESSAGE_TIME + FADE_TIME);
+        self.messages
+            .iter()
+            .map(|(text, shown)| (text.as_str(), opacity(now.duration_since(*shown))))
+            .collect()
+    }
+
+    // Há algo para desenhar por cima da tela. Sem nada, um quadro em que a tela não mudou nem
+    // precisa ser enviado para a janela
+    pub fn is_visible(
+        &mut self,
+        now: Instant,
+        state: RunState,
+        instructions_per_frame: usize,
+    ) -> bool {
+        !self.status_line(state, instructions_per_frame).is_empty()
+            || !self.messages(now).is_empty()
+            || (self.show_registers && state != RunState::Menu)
+    }
+
+    // Primeira linha: os contadores (se ligados) e o estado, quando não está rodando normalmente
+    pub fn status_line(&self, state: RunState, instructions_per_frame: usize) -> String {
+        let mut parts = Vec::new();
+        if self.show_stats {
+            parts.push(format!(
+                "{:.0} FPS  {} IPS  {} IPF",
+                self.stats.fps,
+                format_rate(self.stats.ips),
+                instructions_per_frame
+            ));
+        }
+        parts.extend(state.label().map(str::to_string));
+        parts.join("  ")
+    }
+
+    // Desenha tudo no buffer da janela: estado e mensagens no canto superior direito, onde não
+    // cobrem o título dos menus, e os registradores no canto inferior esquerdo
+    pub fn draw(
+        &mut self,
+        buffer: &mut [u32],
+        width: usize,
+        now: Instant,
+        state: RunState,
+        chip8: &Chip8,
+        instructions_per_frame: usize,
+    ) {
+        let scale = self.scale;
+        let margin = 2 * scale;
+        let right = |text: &str| width.saturating_sub(margin + font::text_width(text, scale));
+        let mut y = margin;
+        let status = self.status_line(state, instructions_per_frame);
+        if !status.is_empty() {
+            let x = right(&status);
+            draw_label(buffer, width, x, y, &status, scale, STATUS_COLOR, 1.0);
+            y += LINE_HEIGHT * scale;
+        }
+        for (text, alpha) in self.messages(now) {
+            draw_label(
+                buffer,
+                width,
+                right(text),
+                y,
+                text,
+                scale,
+                TEXT_COLOR,
+                alpha,
+            );
+            y += LINE_HEIGHT * scale;
+        }
+
+        if self.show_registers && state != RunState::Menu {
+            let hud_scale = (scale / 2).max(1);
+            let lines = register_lines(chip8);
+            let height = buffer.len() / width;
+            let mut y = height.saturating_sub(margin + lines.len() * LINE_HEIGHT * hud_scale);
+            for line in &lines {
+                draw_label(buffer, width, margin, y, line, hud_scale, HUD_COLOR, 1.0);
+                y += LINE_HEIGHT * hud_scale;
+            }
+        }
+    }
+}
+
+// Texto sobre um retângulo escurecido, os dois com a mesma opacidade
+#[allow(clippy::too_many_arguments)]
+fn draw_label(
+    buffer: &mut [u32],
+    width: usize,
+    x: usize,
+    y: usize,
+    text: &str,
+    scale: usize,
+    color: u32,
+    alpha: f32,
+) {
+    let pad = scale;
+    font::dim_rect(
+        buffer,
+        width,
+        x.saturating_sub(pad),
+        y.saturating_sub(pad),
+        font::text_width(text, scale) + 2 * pad,
+        GLYPH_HEIGHT * scale + 2 * pad,
+        BACKDROP * alpha,
+    );
+    font::draw_text_faded(buffer, width, x, y, text, scale, color, alpha);
+}
@@ -0,0 +1,123 @@
+use std::fmt;
+
+// Paleta de 4 cores no formato 0x00RRGGBB que o minifb espera. O índice de cada pixel vem dos
+// planos de bits do XO-CHIP: 0 = fundo, 1 = plano 1, 2 = plano 2, 3 = os dois planos. Com um
+// único plano (CHIP-8 normal) só as cores 0 e 1 aparecem
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    pub colors: [u32; 4],
+}
+
+pub const DEFAULT_PALETTE: &str = "classic";
+
+// Presets embutidos. Os temas do Octo usam as mesmas cores do editor
+const PRESETS: &[(&str, [u32; 4])] = &[
+    ("classic", [0x000000, 0xFFFFFF, 0xAAAAAA, 0x555555]),
+    ("amber", [0x1A0F00, 0xFFB000, 0xCC7A00, 0x663D00]),
+    ("green", [0x001400, 0x33FF33, 0x1FAA1F, 0x0A550A]),
+    ("lcd", [0x9BBC0F, 0x0F380F, 0x306230, 0x8BAC0F]),
+    ("octo", [0x996600, 0xFFCC00, 0xFF6600, 0x662200]),
+    ("octo-lcd", [0xF9FFB3, 0x3D8026, 0xABCC47, 0x00131A]),
+    ("hotdog", [0x000000, 0xFF0000, 0xFFFF00, 0xFFFFFF]),
+    ("gray", [0xAAAAAA, 0x000000, 0xFFFFFF, 0x666666]),
+    ("cga0", [0x000000, 0x00FF00, 0xFF0000, 0xFFFF00]),
+    ("cga1", [0x000000, 0xFF00FF, 0x00FFFF, 0xFFFFFF]),
+];
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette::preset(DEFAULT_PALETTE).unwrap()
+    }
+}
+
+impl Palette {
+    pub fn preset(name: &str) -> Option<Palette> {
+        PRESETS
+            .iter()
+            .find(|(preset, _)| preset.eq_ignore_ascii_case(name))
+            .map(|&(_, colors)| Palette { colors })
+    }
+
+    pub fn preset_names() -> impl Iterator<Item = &'static str> {
+        PRESETS.iter().map(|&(name, _)| name)
+    }
+
+    // Aceita 2 cores (fundo e pixel aceso, as outras duas são derivadas) ou 4 cores
+    pub fn from_hex(colors: &[String]) -> Result<Palette, PaletteError> {
+        let parsed = colors
+            .iter()
+            .map(|c| parse_color(c))
+            .collect::<Result<Vec<_>, _>>()?;
+        match parsed[..] {
+            [background, foreground] => Ok(Palette {
+                colors: [
+                    background,
+                    foreground,
+                    blend(background, foreground, 0.66),
+                    blend(background, foreground, 0.33),
+                ],
+            }),
+            [c0, c1, c2, c3] => Ok(Palette {
+                colors: [c0, c1, c2, c3],
+            }),
+            _ => Err(PaletteError::WrongColorCount(parsed.len())),
+        }
+    }
+
+    pub fn background(&self) -> u32 {
+        self.colors[0]
+    }
+
+    pub fn color(&self, index: u8) -> u32 {
+        self.colors[(index & 0x3) as usize]
+    }
+
+    // Converte os índices de pixel (0 a 3) para cores, escrevendo em `out`
+    pub fn colorize<I: IntoIterator<Item = u8>>(&self, pixels: I, out: &mut [u32]) {
+        for (dst, index) in out.iter_mut().zip(pixels) {
+            *dst = self.color(index);
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PaletteError {
+    InvalidColor(String),
+    WrongColorCount(usize),
+    UnknownPalette(String),
+}
+
+impl fmt::Display for PaletteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaletteError::InvalidColor(c) => {
+                write!(f, "invalid color '{}' (expected #RRGGBB)", c)
+            }
+            PaletteError::WrongColorCount(n) => {
+                write!(f, "a palette needs 2 or 4 colors, got {}", n)
+            }
+            PaletteError::UnknownPalette(name) => write!(f, "unknown palette '{}'", name),
+        }
+    }
+}
+
+impl std::error::Error for PaletteError {}
+
+// "#RRGGBB" ou "RRGGBB"
+pub fn parse_color(text: &str) -> Result<u32, PaletteError> {
+    let hex = text.strip_prefix('#').unwrap_or(text);
+    if hex.len() != 6 {
+        return Err(PaletteError::InvalidColor(text.to_string()));
+    }
+    u32::from_str_radix(hex, 16).map_err(|_| PaletteError::InvalidColor(text.to_string()))
+}
+
+// Interpola entre duas cores, `t` = 0 retorna `a` e `t` = 1 retorna `b`
+pub fn blend(a: u32, b: u32, t: f32) -> u32 {
+    let channel = |shift: u32| {
+        let ca = ((a >> shift) & 0xFF) as f32;
+        let cb = ((b >> shift) & 0xFF) as f32;
+        ((ca + (cb - ca) * t).round() as u32) << shift
+    };
+    channel(16) | channel(8) | channel(0)
+}
@@ -0,0 +1,167 @@
+use crate::color::ColorMap;
+use crate::cpu::{
+    Chip8, DEFAULT_INSTRUCTIONS_PER_FRAME, MAX_VIDEO_HEIGHT, MAX_VIDEO_WIDTH, MEMORY_SIZE,
+    START_ADDRESS, VIDEO_HEIGHT, VIDEO_WIDTH, XO_MEMORY_SIZE,
+};
+use crate::instruction::InstructionSet;
+use crate::quirks::Quirks;
+use std::fmt;
+use std::str::FromStr;
+
+// Perfis das variantes do CHIP-8: cada uma tem suas quirks, uma velocidade típica, a tabela de
+// opcodes e o modelo de tela
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Platform {
+    // Interpretador original do COSMAC VIP
+    #[default]
+    Chip8,
+    // SUPER-CHIP 1.1 das calculadoras HP-48
+    SuperChip,
+    // Extensão do Octo
+    XoChip,
+    // CHIP-8 do VIP com a placa de cor VP-590 e o segundo teclado VP-580
+    Chip8X,
+    // CHIP-8 estendido do VIP, com skips e desvios a mais
+    Chip8E,
+    // CHIP-8 com tela de 128x64 no VIP com 4K a mais de RAM
+    Chip10,
+}
+
+impl Platform {
+    pub const ALL: [Platform; 6] = [
+        Platform::Chip8,
+        Platform::SuperChip,
+        Platform::XoChip,
+        Platform::Chip8X,
+        Platform::Chip8E,
+        Platform::Chip10,
+    ];
+
+    pub fn quirks(self) -> Quirks {
+        match self {
+            // As variantes do VIP herdam o comportamento do interpretador original
+            Platform::Chip8 | Platform::Chip8X | Platform::Chip8E | Platform::Chip10 => {
+                Quirks::default()
+            }
+            Platform::SuperChip => Quirks {
+                vf_reset: false,
+                memory_increment_i: false,
+                shift_vx: true,
+                jump_vx: true,
+                clip_sprites: true,
+                key_wait_release: false,
+                display_wait: false,
+            },
+            Platform::XoChip => Quirks {
+                vf_reset: false,
+                memory_increment_i: true,
+                shift_vx: false,
+                jump_vx: false,
+                clip_sprites: false,
+                key_wait_release: false,
+                display_wait: false,
+            },
+        }
+    }
+
+    pub fn memory_size(self) -> usize {
+        match self {
+            Platform::Chip8
+            | Platform::SuperChip
+            | Platform::Chip8X
+            | Platform::Chip8E
+            | Platform::Chip10 => MEMORY_SIZE,
+            Platform::XoChip => XO_MEMORY_SIZE,
+        }
+    }
+
+    // Instruções por quadro de 60Hz que os jogos de cada plataforma costumam esperar
+    pub fn instructions_per_frame(self) -> usize {
+        match self {
+            Platform::Chip8 | Platform::Chip8X | Platform::Chip8E | Platform::Chip10 => {
+                DEFAULT_INSTRUCTIONS_PER_FRAME
+            }
+            Platform::SuperChip => 30,
+            Platform::XoChip => 1000,
+        }
+    }
+
+    pub fn instruction_set(self) -> InstructionSet {
+        match self {
+            Platform::Chip8X => InstructionSet::Chip8X,
+            Platform::Chip8E => InstructionSet::Chip8E,
+            Platform::SuperChip => InstructionSet::SuperChip,
+            Platform::XoChip => InstructionSet::XoChip,
+            _ => InstructionSet::Chip8,
+        }
+    }
+
+    // Largura e altura da tela. O SUPER-CHIP e o XO-CHIP têm sempre 128x64 e começam na baixa
+    // resolução, com os pixels dobrados (ver Chip8::set_lores)
+    pub fn resolution(self) -> (usize, usize) {
+        match self {
+            Platform::Chip10 | Platform::SuperChip | Platform::XoChip => {
+                (MAX_VIDEO_WIDTH, MAX_VIDEO_HEIGHT)
+            }
+            _ => (VIDEO_WIDTH, VIDEO_HEIGHT),
+        }
+    }
+
+    // O interpretador do CHIP-8X é maior e empurra os programas para 0x300
+    pub fn start_address(self) -> usize {
+        match self {
+            Platform::Chip8X => 0x300,
+            _ => START_ADDRESS,
+        }
+    }
+
+    // Chip8 ligado com o modelo desta plataforma, ainda sem ROM (carregue em start_address)
+    pub fn machine(self) -> Chip8 {
+        let mut chip8 = Chip8::with_memory_size(self.memory_size());
+        chip8.quirks = self.quirks();
+        chip8.set_instruction_set(self.instruction_set());
+        let (width, height) = self.resolution();
+        chip8.set_resolution(width, height);
+        if self == Platform::Chip8X {
+            chip8.colors = Some(ColorMap::default());
+        }
+        if matches!(self, Platform::SuperChip | Platform::XoChip) {
+            chip8.set_lores(true);
+        }
+        chip8.pc = self.start_address() as u16;
+        chip8
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Platform::Chip8 => "chip8",
+            Platform::SuperChip => "schip",
+            Platform::XoChip => "xochip",
+            Platform::Chip8X => "chip8x",
+            Platform::Chip8E => "chip8e",
+            Platform::Chip10 => "chip10",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Platform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace('-', "").as_str() {
+            "chip8" | "vip" => Ok(Platform::Chip8),
+            "schip" | "superchip" => Ok(Platform::SuperChip),
+            "xochip" => Ok(Platform::XoChip),
+            "chip8x" => Ok(Platform::Chip8X),
+            "chip8e" => Ok(Platform::Chip8E),
+            "chip10" => Ok(Platform::Chip10),
+            _ => Err(format!(
+                "unknown platform '{}' (expected chip8, schip, xochip, chip8x, chip8e or chip10)",
+                s
+            )),
+        }
+    }
+}
@@ -0,0 +1,271 @@
+use serde::Deserialize;
+
+// Efeitos de pós-processamento estilo CRT aplicados no frame já ampliado, antes do
+// update_with_buffer. Tudo roda na CPU: cada efeito é uma passada simples sobre o buffer 0x00RRGGBB
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PostFxSettings {
+    // Escurece uma linha sim, outra não
+    pub scanlines: bool,
+    // Intensidade das linhas escuras (0 = sem efeito, 1 = preto)
+    pub scanline_strength: f32,
+
+    // Brilho do fósforo vazando para os vizinhos
+    pub bloom: bool,
+    pub bloom_radius: usize,
+    pub bloom_strength: f32,
+
+    // Distorção de barril da tela curva
+    pub curvature: bool,
+    pub curvature_amount: f32,
+
+    // Bordas mais escuras que o centro
+    pub vignette: bool,
+    pub vignette_strength: f32,
+
+    // Espaço (em pixels da janela) entre os pixels do CHIP-8
+    pub pixel_gap: usize,
+}
+
+impl Default for PostFxSettings {
+    fn default() -> Self {
+        PostFxSettings {
+            scanlines: false,
+            scanline_strength: 0.4,
+            bloom: false,
+            bloom_radius: 4,
+            bloom_strength: 0.6,
+            curvature: false,
+            curvature_amount: 0.08,
+            vignette: false,
+            vignette_strength: 0.5,
+            pixel_gap: 0,
+        }
+    }
+}
+
+impl PostFxSettings {
+    // Liga todos os efeitos com os parâmetros padrão (--crt)
+    pub fn crt() -> Self {
+        PostFxSettings {
+            scanlines: true,
+            bloom: true,
+            curvature: true,
+            vignette: true,
+            pixel_gap: 1,
+            ..PostFxSettings::default()
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.scanlines || self.bloom || self.curvature || self.vignette || self.pixel_gap > 0
+    }
+}
+
+#[inline]
+fn channels(color: u32) -> [f32; 3] {
+    [
+        ((color >> 16) & 0xFF) as f32,
+        ((color >> 8) & 0xFF) as f32,
+        (color & 0xFF) as f32,
+    ]
+}
+
+#[inline]
+fn pack([r, g, b]: [f32; 3]) -> u32 {
+    let c = |v: f32| v.clamp(0.0, 255.0) as u32;
+    (c(r) << 16) | (c(g) << 8) | c(b)
+}
+
+#[inline]
+fn scale_color(color: u32, factor: f32) -> u32 {
+    let [r, g, b] = channels(color);
+    pack([r * factor, g * factor, b * factor])
+}
+
+// Mantém os buffers de trabalho entre um quadro e outro para não alocar a cada frame
+#[derive(Default)]
+pub struct PostProcessor {
+    pub settings: PostFxSettings,
+    scratch: Vec<u32>,
+    blur: Vec<[f32; 3]>,
+    blur_tmp: Vec<[f32; 3]>,
+}
+
+impl PostProcessor {
+    pub fn new(settings: PostFxSettings) -> Self {
+        PostProcessor {
+            settings,
+            ..PostProcessor::default()
+        }
+    }
+
+    // `scale` é o fator usado no scale_buffer, necessário para saber onde ficam as bordas dos pixels
+    pub fn apply(&mut self, frame: &mut [u32], width: usize, height: usize, scale: usize) {
+        let settings = self.settings;
+        if !settings.is_enabled() {
+            return;
+        }
+        if settings.pixel_gap > 0 {
+            pixel_gap(frame, width, height, scale, settings.pixel_gap);
+        }
+        if settings.scanlines {
+            scanlines(frame, width, settings.scanline_strength);
+        }
+        if settings.bloom {
+            self.bloom(frame, width, height);
+        }
+        if settings.curvature {
+            self.scratch.clear();
+            self.scratch.extend_from_slice(frame);
+            curvature(
+                &self.scratch,
+                frame,
+                width,
+                height,
+                settings.curvature_amount,
+            );
+        }
+        if settings.vignette {
+            vignette(frame, width, height, settings.vignette_strength);
+        }
+    }
+
+    // Desfoca uma cópia do frame (box blur separável, horizontal e depois vertical) e soma ao original
+    fn bloom(&mut self, frame: &mut [u32], width: usize, height: usize) {
+        let radius = self.settings.bloom_radius;
+        if radius == 0 {
+            return;
+        }
+        self.blur.clear();
+        self.blur.extend(frame.iter().map(|&c| channels(c)));
+        self.blur_tmp.resize(frame.len(), [0.0; 3]);
+
+        box_blur(
+            &self.blur,
+            &mut self.blur_tmp,
+            width,
+            height,
+            radius,
+            1,
+            width,
+        );
+        box_blur(
+            &self.blur_tmp,
+            &mut self.blur,
+            height,
+            width,
+            radius,
+            width,
+            1,
+        );
+
+        let strength = self.settings.bloom_strength;
+        for (pixel, glow) in frame.iter_mut().zip(&self.blur) {
+            let [r, g, b] = channels(*pixel);
+            *pixel = pack([
+                r + glow[0] * strength,
+                g + glow[1] * strength,
+                b + glow[2] * strength,
+            ]);
+        }
+    }
+}
+
+// Média móvel de raio `radius` ao longo de `lines` linhas de `len` elementos. `step` é a distância
+// entre elementos da mesma linha e `stride` a distância entre linhas, assim a mesma função faz a
+// passada horizontal e a vertical
+fn box_blur(
+    src: &[[f32; 3]],
+    dst: &mut [[f32; 3]],
+    len: usize,
+    lines: usize,
+    radius: usize,
+    step: usize,
+    stride: usize,
+) {
+    let window = (2 * radius + 1) as f32;
+    for line in 0..lines {
+        let base = line * stride;
+        let at = |i: isize| -> [f32; 3] {
+            if i < 0 || i as usize >= len {
+                [0.0; 3]
+            } else {
+                src[base + i as usize * step]
+            }
+        };
+        let mut sum = [0.0f32; 3];
+        for i in -(radius as isize)..=radius as isize {
+            let c = at(i);
+            sum = [sum[0] + c[0], sum[1] + c[1], sum[2] + c[2]];
+        }
+        for i in 0..len {
+            dst[base + i * step] = [sum[0] / window, sum[1] / window, sum[2] / window];
+            let add = at(i as isize + radius as isize + 1);
+            let sub = at(i as isize - radius as isize);
+            sum = [
+                sum[0] + add[0] - sub[0],
+                sum[1] + add[1] - sub[1],
+                sum[2] + add[2] - sub[2],
+            ];
+        }
+    }
+}
+
+// Apaga as últimas `gap` linhas e colunas de cada célula `scale` x `scale`
+fn pixel_gap(frame: &mut [u32], width: usize, height: usize, scale: usize, gap: usize) {
+    if scale <= gap {
+        return;
+    }
+    for y in 0..height {
+        let row_gap = y % scale >= scale - gap;
+        for x in 0..width {
+            if row_gap || x % scale >= scale - gap {
+                frame[y * width + x] = scale_color(frame[y * width + x], 0.15);
+            }
+        }
+    }
+}
+
+fn scanlines(frame: &mut [u32], width: usize, strength: f32) {
+    let factor = 1.0 - strength.clamp(0.0, 1.0);
+    for row in frame.chunks_mut(width).skip(1).step_by(2) {
+        for pixel in row {
+            *pixel = scale_color(*pixel, factor);
+        }
+    }
+}
+
+// Para cada pixel de saída calcula de onde ele vem na imagem plana: as coordenadas normalizadas
+// em [-1, 1] são empurradas para fora proporcionalmente ao quadrado da distância ao centro
+fn curvature(src: &[u32], dst: &mut [u32], width: usize, height: usize, amount: f32) {
+    for y in 0..height {
+        let v = (y as f32 + 0.5) / height as f32 * 2.0 - 1.0;
+        for x in 0..width {
+            let u = (x as f32 + 0.5) / width as f32 * 2.0 - 1.0;
+            let r2 = u * u + v * v;
+            let su = u * (1.0 + amount * r2);
+            let sv = v * (1.0 + amount * r2);
+            dst[y * width + x] = if su.abs() > 1.0 || sv.abs() > 1.0 {
+                0
+            } else {
+                let sx = ((su + 1.0) * 0.5 * width as f32) as usize;
+                let sy = ((sv + 1.0) * 0.5 * height as f32) as usize;
+                src[sy.min(height - 1) * width + sx.min(width - 1)]
+            };
+        }
+    }
+}
+
+fn vignette(frame: &mut [u32], width: usize, height: usize, strength: f32) {
+    for y in 0..height {
+        let v = (y as f32 + 0.5) / height as f32 * 2.0 - 1.0;
+        for x in 0..width {
+            let u = (x as f32 + 0.5) / width as f32 * 2.0 - 1.0;
+            // r2 vai de 0 no centro até 2 nos cantos
+            let factor = 1.0 - strength * (u * u + v * v) / 2.0;
+            let pixel = &mut frame[y * width + x];
+            *pixel = scale_color(*pixel, factor.max(0.0));
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use serde::Deserialize;
+
+// Comportamentos que mudam entre as implementações do CHIP-8. Os valores padrão seguem o
+// interpretador original do COSMAC VIP
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quirks {
+    // 8xy1, 8xy2 e 8xy3 zeram o VF (efeito colateral das rotinas do VIP)
+    pub vf_reset: bool,
+    // Fx55 e Fx65 deixam I apontando para depois do último registrador (I += x + 1)
+    pub memory_increment_i: bool,
+    // 8xy6 e 8xyE deslocam o próprio Vx em vez de copiar Vy deslocado (SUPER-CHIP)
+    pub shift_vx: bool,
+    // Bxnn pula para xnn + Vx em vez de nnn + V0 (SUPER-CHIP)
+    pub jump_vx: bool,
+    // Sprites que passam da borda são cortados em vez de aparecer do outro lado. A posição
+    // inicial dá a volta na tela de qualquer jeito
+    pub clip_sprites: bool,
+    // Fx0A espera uma tecla ser pressionada e depois solta, e só então guarda o valor. Desligado,
+    // guarda a tecla assim que ela é pressionada (como vários interpretadores modernos), e uma
+    // tecla mantida pressionada satisfaz vários Fx0A seguidos
+    pub key_wait_release: bool,
+    // Dxyn espera a interrupção de vídeo do CDP1861: depois de um desenho nenhuma instrução roda
+    // até o próximo quadro, o que limita os jogos do VIP a um sprite por quadro
+    pub display_wait: bool,
+}
+
+// Nomes das quirks como na seção [quirks], na ordem de Quirks::flags
+pub const QUIRK_NAMES: [&str; 7] = [
+    "vf_reset",
+    "memory_increment_i",
+    "shift_vx",
+    "jump_vx",
+    "clip_sprites",
+    "key_wait_release",
+    "display_wait",
+];
+
+impl Quirks {
+    pub fn flags(&self) -> [bool; 7] {
+        [
+            self.vf_reset,
+            self.memory_increment_i,
+            self.shift_vx,
+            self.jump_vx,
+            self.clip_sprites,
+            self.key_wait_release,
+            self.display_wait,
+        ]
+    }
+
+    // Para trocar uma quirk pela posição em QUIRK_NAMES (no menu de pausa)
+    pub fn flags_mut(&mut self) -> [&mut bool; 7] {
+        [
+            &mut self.vf_reset,
+            &mut self.memory_increment_i,
+            &mut self.shift_vx,
+            &mut self.jump_vx,
+            &mut self.clip_sprites,
+            &mut self.key_wait_release,
+            &mut self.display_wait,
+        ]
+    }
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Quirks {
+            vf_reset: true,
+            memory_increment_i: true,
+            shift_vx: false,
+            jump_vx: false,
+            clip_sprites: true,
+            key_wait_release: true,
+            // Só faz sentido com a velocidade do VIP: com um número fixo de instruções por quadro
+            // ela deixaria os jogos lentos. O --timing vip liga
+            display_wait: false,
+        }
+    }
+}
+
+// Quirks trocadas pelo usuário (seção [quirks] do chip8.toml ou entrada do banco de ROMs). Só
+// o que estiver definido substitui o perfil da plataforma
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QuirkOverrides {
+    pub vf_reset: Option<bool>,
+    pub memory_increment_i: Option<bool>,
+    pub shift_vx: Option<bool>,
+    pub jump_vx: Option<bool>,
+    pub clip_sprites: Option<bool>,
+    pub key_wait_release: Option<bool>,
+    pub display_wait: Option<bool>,
+}
+
+impl QuirkOverrides {
+    pub fn apply(&self, quirks: &mut Quirks) {
+        let set = |target: &mut bool, value: Option<bool>| {
+            if let Some(value) = value {
+                *target = value;
+            }
+        };
+        set(&mut quirks.vf_reset, self.vf_reset);
+        set(&mut quirks.memory_increment_i, self.memory_increment_i);
+        set(&mut quirks.shift_vx, self.shift_vx);
+        set(&mut quirks.jump_vx, self.jump_vx);
+        set(&mut quirks.clip_sprites, self.clip_sprites);
+        set(&mut quirks.key_wait_release, self.key_wait_release);
+        set(&mut quirks.display_wait, self.display_wait);
+    }
+}
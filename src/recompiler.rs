@@ -0,0 +1,287 @@
+use crate::cpu::{AUDIO_PATTERN_SIZE, Chip8, Chip8Error};
+use crate::instruction::Instruction;
+use rand::RngCore;
+
+// Recompilador de blocos para execuções em lote sem janela (treino de agentes, fuzzing de
+// milhares de ROMs). Um bloco básico é a sequência de instruções a partir de um endereço até a
+// primeira que mexe no pc ou na memória; cada instrução do meio vira uma closure já
+// especializada com os operandos, e a do fim é executada pelo interpretador (Chip8::execute).
+//
+// O resultado tem que ser idêntico ao do run_frame, instrução por instrução (tests/recompiler.rs
+// compara os dois). A única diferença é que as instruções do meio dos blocos e os saltos não
+// imprimem o trace
+
+// Blocos longos demais são divididos
+const MAX_BLOCK_LENGTH: usize = 64;
+
+type Op = Box<dyn Fn(&mut Chip8) + Send + Sync>;
+
+struct Block {
+    // Instruções que só avançam o pc
+    ops: Vec<Op>,
+    // Instrução que termina o bloco: saltos, skips, CALL/RET, Fx0A, Dxyn e as que escrevem na
+    // memória (Fx33, Fx55). Fica None quando o bloco foi cortado no tamanho máximo
+    exit: Option<Instruction>,
+}
+
+pub struct Recompiler {
+    // Bloco que começa em cada endereço
+    blocks: Vec<Option<Block>>,
+    // Bytes da memória lidos por algum bloco traduzido. Uma escrita num deles apaga todos os
+    // blocos: código que se modifica é raro, então não vale a pena saber qual bloco foi atingido
+    translated: Vec<bool>,
+    // Blocos traduzidos desde a criação, para acompanhar quantas vezes o cache foi refeito
+    pub translations: usize,
+}
+
+impl Recompiler {
+    pub fn new(memory_size: usize) -> Recompiler {
+        Recompiler {
+            blocks: (0..memory_size).map(|_| None).collect(),
+            translated: vec![false; memory_size],
+            translations: 0,
+        }
+    }
+
+    // Descarta todos os blocos. Necessário depois de trocar a memória por fora das instruções:
+    // load_bytes, escrita direta em `memory` ou um save state carregado
+    pub fn flush(&mut self) {
+        self.blocks.iter_mut().for_each(|block| *block = None);
+        self.translated.fill(false);
+    }
+
+    // Mesmo contrato do Chip8::run_frame: timers no início, `instructions` instruções e as
+    // bordas das teclas apagadas no fim. Um bloco maior que o que sobra do quadro é executado só
+    // até onde der, então a contagem de instruções por quadro é exata
+    pub fn run_frame(&mut self, chip8: &mut Chip8, instructions: usize) -> Result<(), Chip8Error> {
+        if self.translated.len() != chip8.memory.len() {
+            *self = Recompiler {
+                translations: self.translations,
+                ..Recompiler::new(chip8.memory.len())
+            };
+        }
+        chip8.tick_timers();
+        let mut remaining = instructions;
+        while remaining > 0 {
+            let start = chip8.wrap_address(chip8.pc) as usize;
+            let block = self.blocks[start].get_or_insert_with(|| {
+                self.translations += 1;
+                translate(chip8, start as u16, &mut self.translated)
+            });
+
+            let count = block.ops.len().min(remaining);
+            for op in &block.ops[..count] {
+                op(chip8);
+            }
+            chip8.pc = chip8.wrap_address((start as u16).wrapping_add(2 * count as u16));
+            remaining -= count;
+
+            if remaining > 0
+                && let Some(exit) = block.exit
+            {
+                remaining -= 1;
+                match exit {
+                    // O salto é o fim mais comum (laços), então não passa pelo interpretador
+                    Instruction::Jump(addr) => chip8.pc = addr,
+                    _ => {
+                        let written = written_range(chip8, exit);
+                        chip8.execute(exit)?;
+                        if let Some((addr, len)) = written {
+                            self.invalidate(addr, len);
+                        }
+                    }
+                }
+                // O Dxyn só aparece como fim de bloco, então é aqui que a quirk display_wait pode
+                // parar o quadro
+                if chip8.waiting_for_display() {
+                    break;
+                }
+            }
+        }
+        chip8.end_frame();
+        Ok(())
+    }
+
+    // Uma escrita de `len` bytes a partir de `addr`
+    fn invalidate(&mut self, addr: usize, len: usize) {
+        let size = self.translated.len();
+        if (addr..addr + len).any(|a| self.translated[a % size]) {
+            self.flush();
+        }
+    }
+}
+
+// Traduz o bloco que começa em `start`, marcando os bytes lidos em `translated`
+fn translate(chip8: &Chip8, start: u16, translated: &mut [bool]) -> Block {
+    let size = translated.len();
+    let mut ops = Vec::new();
+    let mut exit = None;
+    let mut addr = start;
+    while ops.len() < MAX_BLOCK_LENGTH {
+        let high_byte = chip8.read_byte(addr as usize) as u16;
+        let low_byte = chip8.read_byte(addr as usize + 1) as u16;
+        translated[addr as usize % size] = true;
+        translated[(addr as usize + 1) % size] = true;
+        let instruction = chip8.instruction_set().decode((high_byte << 8) | low_byte);
+        match straight(instruction) {
+            Some(op) => ops.push(op),
+            None => {
+                exit = Some(instruction);
+                break;
+            }
+        }
+        addr = chip8.wrap_address(addr.wrapping_add(2));
+    }
+    Block { ops, exit }
+}
+
+// Bytes que a instrução vai escrever, calculados antes de ela rodar (o Fx55 pode mudar o I)
+pub(crate) fn written_range(chip8: &Chip8, instruction: Instruction) -> Option<(usize, usize)> {
+    match instruction {
+        Instruction::Bcd(_) => Some((chip8.i as usize, 3)),
+        Instruction::Store(x) => Some((chip8.i as usize, x as usize + 1)),
+        Instruction::StoreRange(x, y) => Some((chip8.i as usize, x.abs_diff(y) as usize + 1)),
+        _ => None,
+    }
+}
+
+// Closure para uma instrução que não mexe no pc nem escreve na memória, ou None se a instrução
+// termina o bloco. A semântica é a mesma do Chip8::execute
+fn straight(instruction: Instruction) -> Option<Op> {
+    let op: Op = match instruction {
+        Instruction::Clear => Box::new(|c: &mut Chip8| c.clear_screen()),
+        Instruction::Load(x, kk) => Box::new(move |c: &mut Chip8| c.v[x as usize] = kk),
+        Instruction::AddImmediate(x, kk) => {
+            let x = x as usize;
+            Box::new(move |c: &mut Chip8| c.v[x] = c.v[x].wrapping_add(kk))
+        }
+        Instruction::Move(x, y) => Box::new(move |c: &mut Chip8| c.v[x as usize] = c.v[y as usize]),
+        Instruction::Or(x, y) => Box::new(move |c: &mut Chip8| {
+            c.v[x as usize] |= c.v[y as usize];
+            c.vf_reset();
+        }),
+        Instruction::And(x, y) => Box::new(move |c: &mut Chip8| {
+            c.v[x as usize] &= c.v[y as usize];
+            c.vf_reset();
+        }),
+        Instruction::Xor(x, y) => Box::new(move |c: &mut Chip8| {
+            c.v[x as usize] ^= c.v[y as usize];
+            c.vf_reset();
+        }),
+        Instruction::Add(x, y) => {
+            let (x, y) = (x as usize, y as usize);
+            Box::new(move |c: &mut Chip8| {
+                let (result, carry) = c.v[x].overflowing_add(c.v[y]);
+                c.v[x] = result;
+                c.v[0xF] = carry as u8;
+            })
+        }
+        Instruction::Sub(x, y) => {
+            let (x, y) = (x as usize, y as usize);
+            Box::new(move |c: &mut Chip8| {
+                let (result, borrow) = c.v[x].overflowing_sub(c.v[y]);
+                c.v[x] = result;
+                c.v[0xF] = !borrow as u8;
+            })
+        }
+        Instruction::SubReverse(x, y) => {
+            let (x, y) = (x as usize, y as usize);
+            Box::new(move |c: &mut Chip8| {
+                let (result, borrow) = c.v[y].overflowing_sub(c.v[x]);
+                c.v[x] = result;
+                c.v[0xF] = !borrow as u8;
+            })
+        }
+        Instruction::ShiftRight(x, y) => {
+            let (x, y) = (x as usize, y as usize);
+            Box::new(move |c: &mut Chip8| {
+                let source = c.shift_source(x, y);
+                c.v[x] = source >> 1;
+                c.v[0xF] = source & 0x01;
+            })
+        }
+        Instruction::ShiftLeft(x, y) => {
+            let (x, y) = (x as usize, y as usize);
+            Box::new(move |c: &mut Chip8| {
+                let source = c.shift_source(x, y);
+                c.v[x] = source << 1;
+                c.v[0xF] = source >> 7;
+            })
+        }
+        Instruction::LoadI(addr) => Box::new(move |c: &mut Chip8| c.i = addr),
+        Instruction::Random(x, kk) => {
+            Box::new(move |c: &mut Chip8| c.v[x as usize] = c.rng.next_u32() as u8 & kk)
+        }
+        Instruction::LoadDelay(x) => Box::new(move |c: &mut Chip8| c.v[x as usize] = c.delay_timer),
+        Instruction::SetDelay(x) => Box::new(move |c: &mut Chip8| c.delay_timer = c.v[x as usize]),
+        Instruction::SetSound(x) => Box::new(move |c: &mut Chip8| c.sound_timer = c.v[x as usize]),
+        Instruction::AddI(x) => {
+            Box::new(move |c: &mut Chip8| c.i = c.i.wrapping_add(c.v[x as usize] as u16))
+        }
+        Instruction::Font(x) => Box::new(move |c: &mut Chip8| c.i = c.v[x as usize] as u16 * 5),
+        Instruction::Restore(x) => {
+            let x = x as usize;
+            Box::new(move |c: &mut Chip8| {
+                for n in 0..=x {
+                    c.v[n] = c.read_byte(c.i as usize + n);
+                }
+                c.memory_increment_i(x);
+            })
+        }
+        Instruction::Audio => Box::new(|c: &mut Chip8| {
+            let mut pattern = [0; AUDIO_PATTERN_SIZE];
+            for (n, byte) in pattern.iter_mut().enumerate() {
+                *byte = c.read_byte(c.i as usize + n);
+            }
+            c.audio_pattern = Some(pattern);
+        }),
+        Instruction::Pitch(x) => Box::new(move |c: &mut Chip8| c.pitch = c.v[x as usize]),
+        Instruction::Unknown(_) => Box::new(|_: &mut Chip8| {}),
+        Instruction::Return
+        | Instruction::Jump(_)
+        | Instruction::Call(_)
+        | Instruction::SkipEqual(..)
+        | Instruction::SkipNotEqual(..)
+        | Instruction::SkipEqualRegister(..)
+        | Instruction::SkipNotEqualRegister(..)
+        | Instruction::JumpOffset(..)
+        | Instruction::Draw(..)
+        | Instruction::SkipKey(_)
+        | Instruction::SkipNotKey(_)
+        | Instruction::WaitKey(_)
+        | Instruction::Bcd(_)
+        | Instruction::Store(_) => return None,
+        // As instruções do CHIP-8X, do CHIP-8E, do SUPER-CHIP e do XO-CHIP são raras e passam
+        // sempre pelo interpretador. O F000 NNNN também mexe no pc
+        Instruction::ScrollDown(_)
+        | Instruction::ScrollUp(_)
+        | Instruction::ScrollRight
+        | Instruction::ScrollLeft
+        | Instruction::LowRes
+        | Instruction::HighRes
+        | Instruction::DrawLarge(..)
+        | Instruction::BigFont(_)
+        | Instruction::SaveFlags(_)
+        | Instruction::LoadFlags(_)
+        | Instruction::LongLoadI
+        | Instruction::Plane(_)
+        | Instruction::Background
+        | Instruction::AddNibbles(..)
+        | Instruction::Color(..)
+        | Instruction::SkipKey2(_)
+        | Instruction::SkipNotKey2(_)
+        | Instruction::Output(_)
+        | Instruction::Input(_)
+        | Instruction::Halt
+        | Instruction::WaitDelay
+        | Instruction::Skip
+        | Instruction::SkipGreater(..)
+        | Instruction::StoreRange(..)
+        | Instruction::LoadRange(..)
+        | Instruction::BranchBack(_)
+        | Instruction::BranchForward(_)
+        | Instruction::SkipBytes(_)
+        | Instruction::SetDelayWait(_) => return None,
+    };
+    Some(op)
+}
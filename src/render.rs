@@ -0,0 +1,155 @@
+use crate::palette::{Palette, blend};
+use std::str::FromStr;
+
+// Como cada pixel do CHIP-8 é desenhado quando ampliado
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PixelStyle {
+    // Quadrados cheios, como o nearest-neighbour original
+    #[default]
+    Solid,
+    // Linhas de grade de 1px separando os pixels
+    Grid,
+    // Pixels com os cantos arredondados
+    Rounded,
+}
+
+impl FromStr for PixelStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "solid" => Ok(PixelStyle::Solid),
+            "grid" => Ok(PixelStyle::Grid),
+            "rounded" => Ok(PixelStyle::Rounded),
+            _ => Err(format!(
+                "unknown pixel style '{}' (expected solid, grid or rounded)",
+                s
+            )),
+        }
+    }
+}
+
+// Máscara de uma célula `scale` x `scale`: true onde a cor do pixel é usada, false onde entra a
+// cor de fundo (ou da grade)
+fn cell_mask(style: PixelStyle, scale: usize) -> Vec<bool> {
+    let mut mask = vec![true; scale * scale];
+    // Em escalas muito pequenas não sobra espaço para grade nem cantos
+    if scale < 3 {
+        return mask;
+    }
+    match style {
+        PixelStyle::Solid => {}
+        PixelStyle::Grid => {
+            for i in 0..scale {
+                mask[(scale - 1) * scale + i] = false;
+                mask[i * scale + scale - 1] = false;
+            }
+        }
+        PixelStyle::Rounded => {
+            // Retângulo com cantos de raio scale/3
+            let radius = scale as f32 / 3.0;
+            let size = scale as f32;
+            for dy in 0..scale {
+                for dx in 0..scale {
+                    let px = dx as f32 + 0.5;
+                    let py = dy as f32 + 0.5;
+                    let cx = px.clamp(radius, size - radius);
+                    let cy = py.clamp(radius, size - radius);
+                    let dist = ((px - cx).powi(2) + (py - cy).powi(2)).sqrt();
+                    mask[dy * scale + dx] = dist <= radius;
+                }
+            }
+        }
+    }
+    mask
+}
+
+// Amplia o buffer de cores `width` x `height` pelo fator `scale`, desenhando cada pixel de acordo
+// com o `style`. As partes fora da máscara usam a cor de fundo da paleta (na grade, uma cor um
+// pouco mais clara para as linhas aparecerem também nas áreas apagadas)
+pub fn scale_buffer(
+    buffer: &[u32],
+    width: usize,
+    height: usize,
+    scale: usize,
+    style: PixelStyle,
+    palette: &Palette,
+) -> Vec<u32> {
+    let mut scaled = Vec::new();
+    scale_buffer_into(buffer, width, height, scale, style, palette, &mut scaled);
+    scaled
+}
+
+// Mesmo que o scale_buffer, mas escrevendo num buffer já existente. O frontend amplia a tela a
+// cada quadro, então reaproveitar o Vec evita uma alocação de ~500K por quadro
+pub fn scale_buffer_into(
+    buffer: &[u32],
+    width: usize,
+    height: usize,
+    scale: usize,
+    style: PixelStyle,
+    palette: &Palette,
+    scaled: &mut Vec<u32>,
+) {
+    scale_rows_into(
+        buffer,
+        width,
+        height,
+        scale,
+        style,
+        palette,
+        u64::MAX,
+        scaled,
+    );
+}
+
+// Amplia só as linhas marcadas em `rows` (um bit por linha, como o Chip8::take_dirty_rows),
+// deixando o resto de `scaled` como estava. Se o tamanho de `scaled` não bater com a tela ampliada,
+// todas as linhas são desenhadas
+#[allow(clippy::too_many_arguments)]
+pub fn scale_rows_into(
+    buffer: &[u32],
+    width: usize,
+    height: usize,
+    scale: usize,
+    style: PixelStyle,
+    palette: &Palette,
+    rows: u64,
+    scaled: &mut Vec<u32>,
+) {
+    let scaled_width = width * scale;
+    let scaled_height = height * scale;
+
+    let rows = if scaled.len() == scaled_width * scaled_height {
+        rows
+    } else {
+        scaled.resize(scaled_width * scaled_height, 0);
+        u64::MAX
+    };
+
+    let mask = cell_mask(style, scale);
+    let gap_color = match style {
+        PixelStyle::Grid => blend(palette.background(), palette.color(1), 0.2),
+        _ => palette.background(),
+    };
+
+    // Telas com mais de 64 linhas não cabem na máscara: as linhas de baixo são sempre desenhadas
+    let marked = |y: usize| y >= u64::BITS as usize || rows & (1 << y) != 0;
+    for y in (0..height).filter(|&y| marked(y)) {
+        for x in 0..width {
+            let color = buffer[y * width + x];
+            for dy in 0..scale {
+                for dx in 0..scale {
+                    let sx = x * scale + dx;
+                    let sy = y * scale + dy;
+                    let scaled_index = sy * scaled_width + sx;
+                    scaled[scaled_index] = if mask[dy * scale + dx] {
+                        color
+                    } else {
+                        gap_color
+                    };
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use crate::keymap::Keymap;
+use crate::palette::{Palette, PaletteError};
+use crate::platform::Platform;
+use crate::quirks::QuirkOverrides;
+use serde::Deserialize;
+use sha1::{Digest, Sha1};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+// Banco de ROMs: configurações por jogo, encontradas pelo SHA-1 do arquivo. O banco embutido
+// fica em romdb.toml e o usuário acrescenta ou substitui entradas na seção [roms] do chip8.toml
+const BUILTIN: &str = include_str!("romdb.toml");
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RomEntry {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub year: Option<u16>,
+    pub platform: Option<String>,
+    pub instructions_per_frame: Option<usize>,
+    // Nome de uma paleta, ou as cores direto (2 ou 4)
+    pub palette: Option<String>,
+    pub colors: Option<Vec<String>>,
+    // Preset de teclado e teclas trocadas, como na seção [keymap]
+    pub keymap: Option<String>,
+    pub keys: HashMap<String, Vec<String>>,
+    pub quirks: QuirkOverrides,
+}
+
+impl RomEntry {
+    pub fn platform(&self) -> Result<Option<Platform>, String> {
+        self.platform.as_deref().map(str::parse).transpose()
+    }
+
+    pub fn colors(&self) -> Result<Option<Palette>, PaletteError> {
+        self.colors.as_deref().map(Palette::from_hex).transpose()
+    }
+
+    // Aplica as teclas da entrada por cima de um mapeamento
+    pub fn apply_keys(&self, keymap: &mut Keymap) -> Result<(), String> {
+        keymap.bind_all(&self.keys)
+    }
+
+    // "Título (autor, ano)", com o que estiver preenchido
+    pub fn display_title(&self) -> Option<String> {
+        let title = self.title.as_ref()?;
+        let details: Vec<String> = self
+            .author
+            .iter()
+            .cloned()
+            .chain(self.year.map(|year| year.to_string()))
+            .collect();
+        if details.is_empty() {
+            Some(title.clone())
+        } else {
+            Some(format!("{} ({})", title, details.join(", ")))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RomDatabase {
+    entries: HashMap<String, RomEntry>,
+}
+
+impl RomDatabase {
+    pub fn builtin() -> RomDatabase {
+        RomDatabase::parse(BUILTIN).expect("built-in ROM database is valid")
+    }
+
+    pub fn parse(text: &str) -> Result<RomDatabase, toml::de::Error> {
+        let entries: HashMap<String, RomEntry> = toml::from_str(text)?;
+        Ok(RomDatabase::from_entries(entries))
+    }
+
+    pub fn from_entries(entries: HashMap<String, RomEntry>) -> RomDatabase {
+        let entries = entries
+            .into_iter()
+            .map(|(hash, entry)| (hash.to_ascii_lowercase(), entry))
+            .collect();
+        RomDatabase { entries }
+    }
+
+    // Entradas de `other` substituem as que tiverem o mesmo hash
+    pub fn extend(&mut self, other: RomDatabase) {
+        self.entries.extend(other.entries);
+    }
+
+    pub fn get(&self, sha1: &str) -> Option<&RomEntry> {
+        self.entries.get(&sha1.to_ascii_lowercase())
+    }
+
+    pub fn lookup(&self, rom: &[u8]) -> Option<&RomEntry> {
+        self.get(&sha1_hex(rom))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+// SHA-1 em hexadecimal minúsculo, igual ao sha1sum
+pub fn sha1_hex(bytes: &[u8]) -> String {
+    Sha1::digest(bytes)
+        .iter()
+        .fold(String::with_capacity(40), |mut hex, byte| {
+            write!(hex, "{:02x}", byte).unwrap();
+            hex
+        })
+}
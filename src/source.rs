@@ -0,0 +1,234 @@
+use crate::cartridge::{self, Cartridge};
+use crate::platform::Platform;
+use crate::romdb::RomEntry;
+use std::fmt;
+use std::io::{self, Cursor, Read};
+use std::path::{Path, PathBuf};
+
+// De onde vem a ROM. O formato é decidido pelo conteúdo e não pelo nome, então um .zip ou um
+// cartucho do Octo também podem chegar pela entrada padrão:
+//   - ZIP ("PK"): uma das ROMs de dentro, escolhida pelo usuário quando há mais de uma
+//   - GIF: cartucho do Octo, com as opções do Octo
+//   - qualquer outra coisa: a ROM em si
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RomSource {
+    File(PathBuf),
+    // "-" na linha de comando
+    Stdin,
+}
+
+// Extensões reconhecidas como ROM dentro de um .zip
+pub const ROM_EXTENSIONS: [&str; 5] = ["ch8", "c8", "sc8", "xo8", "c8x"];
+
+// .ch8 é usado por todas as variantes, então não diz nada sobre a plataforma
+pub fn extension_platform(name: &str) -> Option<Platform> {
+    let extension = Path::new(name).extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+        "c8" => Some(Platform::Chip8),
+        "sc8" => Some(Platform::SuperChip),
+        "xo8" => Some(Platform::XoChip),
+        "c8x" => Some(Platform::Chip8X),
+        _ => None,
+    }
+}
+
+// Metadados opcionais ao lado da ROM: pong.ch8 usa pong.toml, com os mesmos campos de uma
+// entrada do banco de ROMs (title, author, platform, colors, keys...)
+pub fn sidecar_path(rom: &Path) -> PathBuf {
+    rom.with_extension("toml")
+}
+
+pub fn read_sidecar(rom: &Path) -> Result<Option<RomEntry>, RomError> {
+    let path = sidecar_path(rom);
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(RomError::Io(e)),
+    };
+    toml::from_str(&text)
+        .map(Some)
+        .map_err(|e| RomError::Sidecar(path, e.to_string()))
+}
+
+// Arquivos que o navegador de ROMs mostra: ROMs, .zip e cartuchos
+pub fn is_loadable(path: &Path) -> bool {
+    let name = path.to_string_lossy();
+    is_rom_name(&name)
+        || path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| {
+                extension.eq_ignore_ascii_case("zip") || extension.eq_ignore_ascii_case("gif")
+            })
+}
+
+fn is_rom_name(name: &str) -> bool {
+    Path::new(name)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            ROM_EXTENSIONS
+                .iter()
+                .any(|rom| rom.eq_ignore_ascii_case(extension))
+        })
+}
+
+#[derive(Debug)]
+pub enum RomError {
+    Io(io::Error),
+    Zip(zip::result::ZipError),
+    Cartridge(String),
+    // Arquivo de metadados ao lado da ROM com erro
+    Sidecar(PathBuf, String),
+    // Um .zip sem nenhum arquivo com extensão de ROM
+    NoRoms,
+    // O usuário não escolheu nenhuma das ROMs do .zip
+    NotSelected,
+}
+
+impl fmt::Display for RomError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RomError::Io(e) => write!(f, "{}", e),
+            RomError::Zip(e) => write!(f, "invalid zip archive: {}", e),
+            RomError::Cartridge(e) => write!(f, "{}", e),
+            RomError::Sidecar(path, e) => write!(f, "{}: {}", path.display(), e),
+            RomError::NoRoms => write!(
+                f,
+                "zip archive has no ROMs (.{})",
+                ROM_EXTENSIONS.join(", .")
+            ),
+            RomError::NotSelected => write!(f, "no ROM selected"),
+        }
+    }
+}
+
+impl std::error::Error for RomError {}
+
+impl From<io::Error> for RomError {
+    fn from(e: io::Error) -> Self {
+        RomError::Io(e)
+    }
+}
+
+impl From<zip::result::ZipError> for RomError {
+    fn from(e: zip::result::ZipError) -> Self {
+        RomError::Zip(e)
+    }
+}
+
+// A ROM pronta para o load_bytes
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Rom {
+    // Nome do arquivo (o de dentro do .zip, se for o caso)
+    pub name: String,
+    pub bytes: Vec<u8>,
+    // Configuração que veio com a ROM: a plataforma da extensão ou as opções do cartucho. Uma
+    // entrada do banco de ROMs com o mesmo hash vale mais
+    pub settings: RomEntry,
+}
+
+impl Rom {
+    // Decide o formato pelos primeiros bytes. pick recebe os nomes das ROMs de um .zip com mais
+    // de uma e devolve a posição da escolhida
+    pub fn from_bytes<F>(name: &str, bytes: Vec<u8>, pick: F) -> Result<Rom, RomError>
+    where
+        F: FnOnce(&[String]) -> Option<usize>,
+    {
+        if bytes.starts_with(b"PK\x03\x04") {
+            return Rom::from_zip(bytes, pick);
+        }
+        if cartridge::is_cartridge(&bytes) {
+            let cartridge = Cartridge::decode(&bytes).map_err(RomError::Cartridge)?;
+            return Ok(Rom {
+                name: name.to_string(),
+                bytes: cartridge.rom().map_err(RomError::Cartridge)?,
+                settings: cartridge.settings(),
+            });
+        }
+        Ok(Rom {
+            name: name.to_string(),
+            bytes,
+            settings: RomEntry {
+                platform: extension_platform(name).map(|platform| platform.to_string()),
+                ..RomEntry::default()
+            },
+        })
+    }
+
+    fn from_zip<F>(bytes: Vec<u8>, pick: F) -> Result<Rom, RomError>
+    where
+        F: FnOnce(&[String]) -> Option<usize>,
+    {
+        let mut archive = zip::ZipArchive::new(Cursor::new(bytes))?;
+        let mut names: Vec<String> = archive
+            .file_names()
+            .filter(|name| is_rom_name(name))
+            .map(str::to_string)
+            .collect();
+        names.sort();
+        let name = match names.len() {
+            0 => return Err(RomError::NoRoms),
+            1 => names.remove(0),
+            _ => match pick(&names) {
+                Some(index) if index < names.len() => names.swap_remove(index),
+                _ => return Err(RomError::NotSelected),
+            },
+        };
+
+        let mut rom = Vec::new();
+        archive.by_name(&name)?.read_to_end(&mut rom)?;
+        // Dentro do .zip só vale a extensão; um .zip ou cartucho dentro de outro não é aberto
+        Ok(Rom {
+            settings: RomEntry {
+                platform: extension_platform(&name).map(|platform| platform.to_string()),
+                ..RomEntry::default()
+            },
+            name,
+            bytes: rom,
+        })
+    }
+}
+
+impl RomSource {
+    pub fn parse(arg: &str) -> RomSource {
+        if arg == "-" {
+            RomSource::Stdin
+        } else {
+            RomSource::File(PathBuf::from(arg))
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            RomSource::File(path) => path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| path.display().to_string()),
+            RomSource::Stdin => "stdin".to_string(),
+        }
+    }
+
+    pub fn load<F>(&self, pick: F) -> Result<Rom, RomError>
+    where
+        F: FnOnce(&[String]) -> Option<usize>,
+    {
+        match self {
+            RomSource::File(path) => {
+                let mut rom = Rom::from_bytes(&self.name(), std::fs::read(path)?, pick)?;
+                // O sidecar substitui o que veio da extensão ou do cartucho, menos a plataforma
+                // quando ele não diz qual é
+                if let Some(mut sidecar) = read_sidecar(path)? {
+                    sidecar.platform = sidecar.platform.or(rom.settings.platform);
+                    rom.settings = sidecar;
+                }
+                Ok(rom)
+            }
+            RomSource::Stdin => {
+                let mut bytes = Vec::new();
+                io::stdin().read_to_end(&mut bytes)?;
+                Rom::from_bytes(&self.name(), bytes, pick)
+            }
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use crate::cpu::Chip8;
+use crate::instruction::Instruction;
+use std::fmt;
+use std::str::FromStr;
+
+// Como a velocidade da emulação é medida
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Timing {
+    // Um número fixo de instruções por quadro (--ipf ou o padrão da plataforma)
+    #[default]
+    Instructions,
+    // Cada instrução custa os ciclos que o interpretador do COSMAC VIP gastava nela
+    Vip,
+}
+
+impl fmt::Display for Timing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Timing::Instructions => "ipf",
+            Timing::Vip => "vip",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Timing {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "ipf" => Ok(Timing::Instructions),
+            "vip" => Ok(Timing::Vip),
+            _ => Err(format!("unknown timing '{}' (expected ipf or vip)", s)),
+        }
+    }
+}
+
+// Os tempos abaixo são ciclos de máquina do CDP1802 (8 pulsos do clock de 1,76MHz do VIP), com
+// base nas medições publicadas do interpretador CHIP-8 do VIP. São aproximados: o custo real de
+// algumas instruções ainda varia alguns ciclos com o endereço (cruzar uma página de 256 bytes)
+
+// Um quadro de 60Hz tem 3668 ciclos, mas o CDP1861 rouba 1024 para o DMA das 128 linhas da tela
+// e a rotina de interrupção (timers e preparação do DMA) gasta mais alguns
+pub const VIP_FRAME_CYCLES: u32 = 3668;
+pub const VIP_DISPLAY_CYCLES: u32 = 1024 + 54;
+// O que sobra para o interpretador rodar instruções
+pub const VIP_CYCLES_PER_FRAME: u32 = VIP_FRAME_CYCLES - VIP_DISPLAY_CYCLES;
+
+// Busca e decodificação, pagas por toda instrução: ler os dois bytes, separar os nibbles e
+// saltar pela tabela de rotinas
+pub const VIP_FETCH_CYCLES: u32 = 40;
+
+// Ciclos que `instruction` vai gastar, calculados antes de ela rodar (os skips e o Dxyn dependem
+// dos registradores)
+pub fn vip_cycles(chip8: &Chip8, instruction: Instruction) -> u32 {
+    let v = |x: u8| chip8.v[x as usize];
+    // Um skip que pula gasta 4 ciclos a mais para somar 2 ao pc
+    let skip = |taken: bool, cycles: u32| if taken { cycles + 4 } else { cycles };
+    let execute = match instruction {
+        // Apaga os 256 bytes da tela um a um
+        Instruction::Clear => 24 + 3054,
+        Instruction::Return => 10,
+        Instruction::Jump(_) => 12,
+        Instruction::Call(_) => 26,
+        Instruction::SkipEqual(x, kk) => skip(v(x) == kk, 10),
+        Instruction::SkipNotEqual(x, kk) => skip(v(x) != kk, 10),
+        Instruction::SkipEqualRegister(x, y) => skip(v(x) == v(y), 14),
+        Instruction::SkipNotEqualRegister(x, y) => skip(v(x) != v(y), 14),
+        Instruction::Load(..) => 6,
+        Instruction::AddImmediate(..) => 10,
+        Instruction::Move(..) => 12,
+        // O VIP monta uma rotina de 8xyN na memória e salta para ela
+        Instruction::Or(..)
+        | Instruction::And(..)
+        | Instruction::Xor(..)
+        | Instruction::Add(..)
+        | Instruction::Sub(..)
+        | Instruction::ShiftRight(..)
+        | Instruction::SubReverse(..)
+        | Instruction::ShiftLeft(..) => 44,
+        Instruction::LoadI(_) => 12,
+        Instruction::JumpOffset(..) => 22,
+        Instruction::Random(..) => 36,
+        // Fora do alinhamento de 8 pixels cada linha do sprite é deslocada e escrita em dois bytes
+        Instruction::Draw(x, _, height) => {
+            let aligned = v(x).is_multiple_of(8);
+            let per_row = if aligned { 46 } else { 68 };
+            26 + per_row * height as u32
+        }
+        Instruction::SkipKey(x) => skip(chip8.key_down(v(x) as usize & 0xF), 14),
+        Instruction::SkipNotKey(x) => skip(!chip8.key_down(v(x) as usize & 0xF), 14),
+        Instruction::LoadDelay(_) => 10,
+        // Cada volta da espera pela tecla custa o mesmo
+        Instruction::WaitKey(_) => 18,
+        Instruction::SetDelay(_) | Instruction::SetSound(_) => 10,
+        Instruction::AddI(_) => 16,
+        Instruction::Font(_) => 16,
+        // Os dígitos saem por subtrações sucessivas, então valores maiores demoram mais
+        Instruction::Bcd(x) => {
+            let value = v(x) as u32;
+            80 + 16 * (value / 100 + value / 10 % 10 + value % 10)
+        }
+        // Um byte por registrador
+        Instruction::Store(x) | Instruction::Restore(x) => 14 + 14 * (x as u32 + 1),
+        // Opcodes que o VIP não tem
+        Instruction::ScrollDown(_)
+        | Instruction::ScrollUp(_)
+        | Instruction::ScrollRight
+        | Instruction::ScrollLeft
+        | Instruction::LowRes
+        | Instruction::HighRes
+        | Instruction::DrawLarge(..)
+        | Instruction::BigFont(_)
+        | Instruction::SaveFlags(_)
+        | Instruction::LoadFlags(_)
+        | Instruction::LongLoadI
+        | Instruction::Plane(_)
+        | Instruction::Audio
+        | Instruction::Pitch(_)
+        | Instruction::Unknown(_) => 0,
+        // Sem medições dos interpretadores do CHIP-8X e do CHIP-8E, as instruções deles custam o
+        // mesmo que as parecidas do CHIP-8
+        Instruction::Background | Instruction::Halt | Instruction::WaitDelay => 10,
+        Instruction::Skip => skip(true, 10),
+        Instruction::SkipGreater(x, y) => skip(v(x) > v(y), 14),
+        Instruction::SkipKey2(x) => skip(chip8.second_keypad[v(x) as usize & 0xF], 14),
+        Instruction::SkipNotKey2(x) => skip(!chip8.second_keypad[v(x) as usize & 0xF], 14),
+        Instruction::AddNibbles(..) => 44,
+        Instruction::Color(..) => 68,
+        Instruction::Output(_) | Instruction::Input(_) => 10,
+        Instruction::StoreRange(x, y) | Instruction::LoadRange(x, y) => {
+            14 + 14 * (x.abs_diff(y) as u32 + 1)
+        }
+        Instruction::BranchBack(_) | Instruction::BranchForward(_) | Instruction::SkipBytes(_) => {
+            12
+        }
+        Instruction::SetDelayWait(_) => 10,
+    };
+    VIP_FETCH_CYCLES + execute
+}
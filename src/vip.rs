@@ -0,0 +1,275 @@
+use crate::cdp1802::{Bus, Cdp1802};
+use crate::cpu::{Chip8Error, KEYPAD_SIZE, MEMORY_SIZE, START_ADDRESS, VIDEO_HEIGHT, VIDEO_WIDTH};
+use std::ops::Range;
+
+// Modelo do COSMAC VIP inteiro: a CPU CDP1802, o chip de vídeo CDP1861 ("Pixie"), o teclado
+// hexadecimal e o mapa de memória. Em vez de implementar as instruções do CHIP-8, roda o
+// interpretador original (fornecido pelo usuário, 512 bytes em 0x000) como código do 1802, então
+// os tempos, as quirks e até os bugs são os do hardware. O interpretador do VIP usa a rotina de
+// interrupção e a fonte da ROM do monitor (0x8000), então na prática ela também é necessária.
+//
+// O quadro é simulado linha a linha: o 1861 gera 262 linhas de 14 ciclos de máquina; nas 128
+// linhas visíveis ele rouba 8 ciclos com DMA para ler os 8 bytes da linha a partir de R0, e a
+// CPU roda nos 6 que sobram. Duas linhas antes da primeira linha visível o 1861 pede a
+// interrupção, e a rotina do interpretador acerta R0 para o buffer da tela.
+
+pub const LINE_CYCLES: u32 = 14;
+pub const FRAME_LINES: usize = 262;
+pub const FRAME_CYCLES: u32 = FRAME_LINES as u32 * LINE_CYCLES;
+// Linhas da tela do 1861: cada linha do CHIP-8 é repetida 4 vezes pela rotina de interrupção
+pub const PIXIE_LINES: usize = 128;
+const DISPLAY_START: usize = 80;
+const DISPLAY_END: usize = DISPLAY_START + PIXIE_LINES;
+// Bytes lidos por DMA em cada linha visível
+const DMA_BYTES: usize = VIDEO_WIDTH / 8;
+// INT fica ativo nas 2 linhas antes da tela, e EF1 nas 4 antes do começo e nas 4 antes do fim
+const INTERRUPT_LINES: Range<usize> = DISPLAY_START - 2..DISPLAY_START;
+const EF1_LINES: [Range<usize>; 2] = [
+    DISPLAY_START - 4..DISPLAY_START,
+    DISPLAY_END - 4..DISPLAY_END,
+];
+
+// Tamanho do interpretador CHIP-8, que fica abaixo dos programas
+pub const INTERPRETER_SIZE: usize = START_ADDRESS;
+// O interpretador usa o fim da RAM: 0xEA0 a 0xEFF para a pilha e as variáveis, 0xF00 a 0xFFF
+// para o buffer da tela
+pub const RESERVED_SIZE: usize = 0x160;
+// A ROM do monitor aparece de 0x8000 em diante
+pub const MONITOR_ADDRESS: u16 = 0x8000;
+
+// Portas de E/S: OUT 1 desliga o vídeo e INP 1 liga; OUT 2 escolhe a tecla que EF3 mostra
+const DISPLAY_PORT: u8 = 1;
+const KEYPAD_PORT: u8 = 2;
+const KEYPAD_FLAG: u8 = 3;
+const DISPLAY_FLAG: u8 = 1;
+
+#[derive(Clone)]
+struct VipBus {
+    ram: Vec<u8>,
+    monitor: Vec<u8>,
+    // Depois do reset a ROM aparece também em 0x0000, até o primeiro acesso com A15 ligado (o
+    // monitor salta para 0x8000 logo nas primeiras instruções)
+    rom_at_zero: bool,
+    keypad: [bool; KEYPAD_SIZE],
+    // Tecla escolhida pelo último OUT 2
+    key_latch: u8,
+    display_on: bool,
+    ef1: bool,
+}
+
+impl VipBus {
+    fn monitor_byte(&self, addr: u16) -> u8 {
+        match self.monitor.len() {
+            0 => 0,
+            len => self.monitor[addr as usize % len],
+        }
+    }
+}
+
+impl Bus for VipBus {
+    fn read(&mut self, addr: u16) -> u8 {
+        if addr & MONITOR_ADDRESS != 0 {
+            self.rom_at_zero = false;
+            return self.monitor_byte(addr);
+        }
+        if self.rom_at_zero {
+            return self.monitor_byte(addr);
+        }
+        // Endereços sem memória leem 0
+        self.ram.get(addr as usize).copied().unwrap_or(0)
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        if addr & MONITOR_ADDRESS == 0
+            && let Some(byte) = self.ram.get_mut(addr as usize)
+        {
+            *byte = value;
+        }
+    }
+
+    fn output(&mut self, port: u8, value: u8) {
+        match port {
+            DISPLAY_PORT => self.display_on = false,
+            KEYPAD_PORT => self.key_latch = value & 0xF,
+            _ => {}
+        }
+    }
+
+    fn input(&mut self, port: u8) -> u8 {
+        if port == DISPLAY_PORT {
+            self.display_on = true;
+        }
+        0
+    }
+
+    fn flag(&self, ef: u8) -> bool {
+        match ef {
+            DISPLAY_FLAG => self.ef1,
+            KEYPAD_FLAG => self.keypad[self.key_latch as usize],
+            _ => false,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Vip {
+    pub cpu: Cdp1802,
+    bus: VipBus,
+    // Linhas mostradas no último quadro, uma por u64 com a coluna 0 no bit 63 (como o primeiro
+    // plano do Chip8::video na tela de 64 colunas)
+    lines: [u64; PIXIE_LINES],
+    // Ciclos que a última instrução do quadro passou do fim dele
+    cycle_carry: u32,
+    pub frame_count: u64,
+}
+
+impl Vip {
+    // VIP de 4K com o interpretador em 0x000. Sem a ROM do monitor a CPU começa direto em 0x0000
+    pub fn new(interpreter: &[u8]) -> Result<Vip, Chip8Error> {
+        Vip::with_memory_size(interpreter, MEMORY_SIZE)
+    }
+
+    // O VIP saía com 2K e podia chegar a 32K
+    pub fn with_memory_size(interpreter: &[u8], size: usize) -> Result<Vip, Chip8Error> {
+        if interpreter.len() > INTERPRETER_SIZE {
+            return Err(Chip8Error::RomTooLarge {
+                size: interpreter.len(),
+                max: INTERPRETER_SIZE,
+            });
+        }
+        let mut ram = vec![0; size.min(MONITOR_ADDRESS as usize)];
+        ram[..interpreter.len()].copy_from_slice(interpreter);
+        Ok(Vip {
+            cpu: Cdp1802::new(),
+            bus: VipBus {
+                ram,
+                monitor: Vec::new(),
+                rom_at_zero: false,
+                keypad: [false; KEYPAD_SIZE],
+                key_latch: 0,
+                display_on: false,
+                ef1: false,
+            },
+            lines: [0; PIXIE_LINES],
+            cycle_carry: 0,
+            frame_count: 0,
+        })
+    }
+
+    // Instala a ROM do monitor e reinicia a máquina, que passa a ligar pelo monitor como a real:
+    // ele mede a RAM e, sem a tecla C pressionada, salta para o interpretador em 0x0000
+    pub fn load_monitor(&mut self, monitor: &[u8]) {
+        self.bus.monitor = monitor.to_vec();
+        self.reset();
+    }
+
+    // Botão de reset: a CPU volta ao estado inicial e o vídeo desliga. A RAM fica como estava
+    pub fn reset(&mut self) {
+        self.cpu = Cdp1802::new();
+        self.bus.rom_at_zero = !self.bus.monitor.is_empty();
+        self.bus.display_on = false;
+        self.cycle_carry = 0;
+    }
+
+    // Programa CHIP-8 em 0x200, abaixo da área reservada para o interpretador no fim da RAM
+    pub fn load_bytes(&mut self, rom: &[u8]) -> Result<(), Chip8Error> {
+        let max = self
+            .bus
+            .ram
+            .len()
+            .saturating_sub(START_ADDRESS + RESERVED_SIZE);
+        if rom.len() > max {
+            return Err(Chip8Error::RomTooLarge {
+                size: rom.len(),
+                max,
+            });
+        }
+        self.bus.ram[START_ADDRESS..START_ADDRESS + rom.len()].copy_from_slice(rom);
+        Ok(())
+    }
+
+    pub fn memory(&self) -> &[u8] {
+        &self.bus.ram
+    }
+
+    pub fn memory_mut(&mut self) -> &mut [u8] {
+        &mut self.bus.ram
+    }
+
+    pub fn set_key(&mut self, key: usize, down: bool) {
+        self.bus.keypad[key % KEYPAD_SIZE] = down;
+    }
+
+    pub fn display_enabled(&self) -> bool {
+        self.bus.display_on
+    }
+
+    // Linha `n` (0 a 127) da tela do último quadro
+    pub fn line(&self, n: usize) -> u64 {
+        self.lines[n]
+    }
+
+    // Pixel na tela de 64x32 do CHIP-8, que ocupa 4 linhas do 1861
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        let line = self.lines[y * (PIXIE_LINES / VIDEO_HEIGHT)];
+        line & (1 << (VIDEO_WIDTH - 1 - x)) != 0
+    }
+
+    // O beep do VIP é ligado pela saída Q
+    pub fn sound_active(&self) -> bool {
+        self.cpu.q
+    }
+
+    // Um quadro do 1861 (3668 ciclos de máquina), com o DMA de cada linha visível, a
+    // interrupção e o EF1 nos momentos em que o chip real gera cada um
+    pub fn run_frame(&mut self) {
+        let mut frame = [0; PIXIE_LINES];
+        let mut cycle = self.cycle_carry;
+        // Próxima linha visível a receber o DMA
+        let mut dma_line = DISPLAY_START;
+        while cycle < FRAME_CYCLES {
+            // O DMA tem prioridade: entra entre duas instruções assim que a linha começa
+            if dma_line < DISPLAY_END && cycle >= dma_line as u32 * LINE_CYCLES {
+                if self.bus.display_on {
+                    let mut row = 0;
+                    for _ in 0..DMA_BYTES {
+                        row = row << 8 | self.cpu.dma_out(&mut self.bus) as u64;
+                    }
+                    frame[dma_line - DISPLAY_START] = row;
+                    cycle += DMA_BYTES as u32;
+                }
+                dma_line += 1;
+                continue;
+            }
+
+            let line = (cycle / LINE_CYCLES) as usize;
+            self.bus.ef1 = EF1_LINES.iter().any(|lines| lines.contains(&line));
+            let interrupt = self.bus.display_on && INTERRUPT_LINES.contains(&line);
+            if interrupt && self.cpu.ie {
+                cycle += self.cpu.interrupt();
+            } else if self.cpu.idle {
+                cycle = self.wake_up(cycle, dma_line);
+            } else {
+                cycle += self.cpu.step(&mut self.bus);
+            }
+        }
+        self.cycle_carry = cycle - FRAME_CYCLES;
+        self.lines = frame;
+        self.frame_count += 1;
+    }
+
+    // Ciclo em que a CPU parada no IDL volta: o próximo DMA, a interrupção ou o fim do quadro
+    fn wake_up(&self, cycle: u32, dma_line: usize) -> u32 {
+        let mut next = FRAME_CYCLES;
+        if self.bus.display_on {
+            if dma_line < DISPLAY_END {
+                next = next.min(dma_line as u32 * LINE_CYCLES);
+            }
+            let interrupt = INTERRUPT_LINES.start as u32 * LINE_CYCLES;
+            if self.cpu.ie && cycle < interrupt {
+                next = next.min(interrupt);
+            }
+        }
+        next.max(cycle + 1)
+    }
+}
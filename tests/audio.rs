@@ -0,0 +1,233 @@
+// Testes do beep sem placa de som: o áudio é gravado num WAV em memória e conferido amostra a
+// amostra.
+
+use chip8::audio::{AudioSink, Beeper, PatternPlayer, ToneSettings, WavSink, Waveform};
+use chip8::platform::Platform;
+use chip8::{AudioState, Chip8};
+use std::io::Cursor;
+
+const SAMPLE_RATE: u32 = 44_100;
+const SAMPLES_PER_FRAME: usize = 735;
+
+fn decode_wav(bytes: &[u8]) -> (u32, Vec<i16>) {
+    assert_eq!(&bytes[0..4], b"RIFF");
+    assert_eq!(&bytes[8..12], b"WAVE");
+    assert_eq!(&bytes[36..40], b"data");
+    let riff_size = u32::from_le_bytes(bytes[4..8].try_into().unwrap()) as usize;
+    assert_eq!(riff_size + 8, bytes.len());
+    let sample_rate = u32::from_le_bytes(bytes[24..28].try_into().unwrap());
+    let data_size = u32::from_le_bytes(bytes[40..44].try_into().unwrap()) as usize;
+    let samples = bytes[44..44 + data_size]
+        .chunks(2)
+        .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+    (sample_rate, samples)
+}
+
+// Roda a ROM quadro a quadro, como o frontend faz, gravando o beep no WAV
+fn record(rom: &[u8], frames: usize, tone: ToneSettings) -> Vec<i16> {
+    let mut chip8 = Chip8::new();
+    chip8.trace = false;
+    chip8.load_bytes(rom).unwrap();
+
+    let mut sink = WavSink::new(Cursor::new(Vec::new()), SAMPLE_RATE).unwrap();
+    let mut beeper = Beeper::new(tone, sink.sample_rate());
+    for _ in 0..frames {
+        chip8.cycle().unwrap();
+        sink.write(beeper.render_frame(chip8.sound_active()))
+            .unwrap();
+        chip8.tick_timers();
+    }
+    sink.finish().unwrap();
+
+    let (sample_rate, samples) = decode_wav(&sink.into_inner().into_inner());
+    assert_eq!(sample_rate, SAMPLE_RATE);
+    samples
+}
+
+// LD V0, 4; LD ST, V0; JP 0x204
+const BEEP_ROM: [u8; 6] = [0x60, 0x04, 0xF0, 0x18, 0x12, 0x04];
+
+#[test]
+fn beep_lasts_while_sound_timer_is_set() {
+    let samples = record(&BEEP_ROM, 10, ToneSettings::default());
+    assert_eq!(samples.len(), 10 * SAMPLES_PER_FRAME);
+
+    // Quadro 0: LD V0; quadro 1: LD ST (o timer já vale 4 no fim do ciclo, antes do tick)
+    let frame = |n: usize| &samples[n * SAMPLES_PER_FRAME..(n + 1) * SAMPLES_PER_FRAME];
+    assert!(frame(0).iter().all(|&s| s == 0));
+    for n in 1..5 {
+        assert!(frame(n).iter().any(|&s| s != 0), "frame {} is silent", n);
+    }
+    for n in 5..10 {
+        assert!(
+            frame(n).iter().all(|&s| s == 0),
+            "frame {} is not silent",
+            n
+        );
+    }
+}
+
+#[test]
+fn muted_tone_is_silent() {
+    let tone = ToneSettings {
+        muted: true,
+        ..ToneSettings::default()
+    };
+    let samples = record(&BEEP_ROM, 10, tone);
+    assert!(samples.iter().all(|&s| s == 0));
+}
+
+#[test]
+fn tone_has_configured_frequency_and_volume() {
+    for waveform in [Waveform::Square, Waveform::Sine, Waveform::Triangle] {
+        let tone = ToneSettings {
+            frequency: 600.0,
+            waveform,
+            volume: 0.5,
+            muted: false,
+        };
+        let mut beeper = Beeper::new(tone, SAMPLE_RATE);
+        let mut samples = Vec::new();
+        for _ in 0..60 {
+            samples.extend_from_slice(beeper.render_frame(true));
+        }
+        assert_eq!(samples.len(), SAMPLE_RATE as usize);
+
+        // Um segundo de áudio a 600Hz cruza o zero subindo 600 vezes
+        let rising = samples
+            .windows(2)
+            .filter(|pair| pair[0] < 0.0 && pair[1] >= 0.0)
+            .count();
+        assert!((599..=601).contains(&rising), "{:?}: {}", waveform, rising);
+
+        let peak = samples.iter().fold(0.0f32, |max, s| max.max(s.abs()));
+        assert!((0.49..=0.5).contains(&peak), "{:?}: {}", waveform, peak);
+    }
+}
+
+#[test]
+fn frames_add_up_for_rates_not_divisible_by_sixty() {
+    let mut beeper = Beeper::new(ToneSettings::default(), 22_050);
+    let total: usize = (0..120).map(|_| beeper.render_frame(false).len()).sum();
+    assert_eq!(total, 2 * 22_050);
+}
+
+fn run_frames(chip8: &mut Chip8, frames: usize) {
+    for _ in 0..frames {
+        chip8.cycle().unwrap();
+        chip8.tick_timers();
+    }
+}
+
+#[test]
+fn xo_chip_pattern_is_loaded_and_played() {
+    // LD I, 0x20E; AUDIO; LD V0, 112; PITCH V0; LD V1, 2; LD ST, V1; JP 0x20C
+    // 0x20E: 64 bits ligados seguidos de 64 desligados
+    let mut rom = vec![
+        0xA2, 0x0E, 0xF0, 0x02, 0x60, 0x70, 0xF0, 0x3A, 0x61, 0x02, 0xF1, 0x18, 0x12, 0x0C,
+    ];
+    rom.extend_from_slice(&[0xFF; 8]);
+    rom.extend_from_slice(&[0x00; 8]);
+
+    let mut chip8 = Platform::XoChip.machine();
+    chip8.load_bytes(&rom).unwrap();
+    run_frames(&mut chip8, 5);
+    chip8.cycle().unwrap();
+
+    let state = chip8.audio_state();
+    assert!(state.active);
+    assert_eq!(state.pitch, 112);
+    assert_eq!(state.pattern.unwrap()[..8], [0xFF; 8]);
+
+    // Pitch 112 toca 8000 bits por segundo, a mesma taxa do host: um bit por amostra
+    let tone = ToneSettings {
+        volume: 1.0,
+        ..ToneSettings::default()
+    };
+    let mut beeper = Beeper::new(tone, 8_000);
+    let samples = beeper.render(&state);
+    assert_eq!(samples.len(), 133);
+    assert!(samples[..64].iter().all(|&s| s == 1.0));
+    assert!(samples[64..128].iter().all(|&s| s == -1.0));
+    assert!(samples[128..].iter().all(|&s| s == 1.0));
+
+    // Depois que o sound_timer zera o padrão continua carregado, mas em silêncio
+    chip8.tick_timers();
+    run_frames(&mut chip8, 2);
+    let state = chip8.audio_state();
+    assert!(!state.active);
+    assert!(beeper.render(&state).iter().all(|&s| s == 0.0));
+}
+
+#[test]
+fn audio_opcodes_are_xo_chip_only() {
+    // LD I, 0x206; AUDIO; PITCH V0. Fora do XO-CHIP são opcodes desconhecidos e o beep continua
+    let rom = [0xA2, 0x06, 0xF0, 0x02, 0xF0, 0x3A, 0xFF, 0xFF];
+    for platform in [
+        Platform::Chip8,
+        Platform::SuperChip,
+        Platform::Chip8X,
+        Platform::Chip8E,
+    ] {
+        let mut chip8 = platform.machine();
+        chip8.v[0] = 1;
+        chip8.load_bytes_at(&rom, platform.start_address()).unwrap();
+        run_frames(&mut chip8, 3);
+        let state = chip8.audio_state();
+        assert_eq!(state.pattern, None, "{}", platform);
+        assert_eq!(state.pitch, chip8::cpu::DEFAULT_PITCH, "{}", platform);
+    }
+}
+
+#[test]
+fn pattern_is_averaged_when_resampled_down() {
+    // Pitch 160 toca 16000 bits por segundo; a 8000Hz cada amostra cobre dois bits alternados
+    let state = AudioState {
+        active: true,
+        pattern: Some([0xAA; 16]),
+        pitch: 160,
+    };
+    let mut beeper = Beeper::new(ToneSettings::default(), 8_000);
+    for _ in 0..10 {
+        assert!(beeper.render(&state).iter().all(|s| s.abs() < 1e-4));
+    }
+}
+
+#[test]
+fn playback_rate_follows_pitch() {
+    assert_eq!(PatternPlayer::playback_rate(64), 4000.0);
+    assert_eq!(PatternPlayer::playback_rate(112), 8000.0);
+    assert_eq!(PatternPlayer::playback_rate(16), 2000.0);
+}
+
+#[test]
+fn pattern_playback_continues_across_frames() {
+    // Pitch 64 toca 4000 bits por segundo: a 48000Hz cada bit dura 12 amostras e o padrão de 128
+    // bits se repete a cada 1536 amostras, atravessando quadros de 800 amostras
+    let mut pattern = [0u8; 16];
+    pattern[0] = 0x80;
+    let state = AudioState {
+        active: true,
+        pattern: Some(pattern),
+        pitch: 64,
+    };
+    let tone = ToneSettings {
+        volume: 1.0,
+        ..ToneSettings::default()
+    };
+    let mut beeper = Beeper::new(tone, 48_000);
+    let mut samples = Vec::new();
+    for _ in 0..6 {
+        samples.extend_from_slice(beeper.render(&state));
+    }
+
+    let highs: Vec<usize> = samples
+        .iter()
+        .enumerate()
+        .filter(|&(_, &s)| s > 0.0)
+        .map(|(n, _)| n)
+        .collect();
+    assert_eq!(highs.len(), 4 * 12);
+    assert!(highs.iter().all(|n| n % 1536 < 12));
+}
@@ -0,0 +1,147 @@
+use chip8::capture::{RawVideoWriter, Recorder, RecordingFormat, Y4mWriter, write_png};
+use std::io::Cursor;
+
+const W: usize = 4;
+const H: usize = 2;
+const A: [u32; W * H] = [0x000000, 0xFFFFFF, 0xFFB000, 0x1A0F00, 0, 0, 0, 0];
+const B: [u32; W * H] = [0xFFFFFF; W * H];
+
+fn rgb(frame: &[u32]) -> Vec<u8> {
+    frame
+        .iter()
+        .flat_map(|&c| [(c >> 16) as u8, (c >> 8) as u8, c as u8])
+        .collect()
+}
+
+fn record(format: RecordingFormat, frames: &[&[u32]]) -> Vec<u8> {
+    let mut recorder = Recorder::new(Cursor::new(Vec::new()), format, W, H).unwrap();
+    for frame in frames {
+        recorder.push(frame).unwrap();
+    }
+    recorder.finish().unwrap().into_inner()
+}
+
+#[test]
+fn format_comes_from_the_extension() {
+    assert_eq!(
+        RecordingFormat::from_path("run.GIF"),
+        Some(RecordingFormat::Gif)
+    );
+    assert_eq!(
+        RecordingFormat::from_path("run.apng"),
+        Some(RecordingFormat::Apng)
+    );
+    assert_eq!(
+        RecordingFormat::from_path("run.png"),
+        Some(RecordingFormat::Apng)
+    );
+    assert_eq!(RecordingFormat::from_path("run.mp4"), None);
+}
+
+#[test]
+fn screenshot_round_trips_through_png() {
+    let mut bytes = Vec::new();
+    write_png(&mut bytes, &A, W, H).unwrap();
+
+    let mut reader = png::Decoder::new(Cursor::new(bytes)).read_info().unwrap();
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).unwrap();
+    assert_eq!((info.width, info.height), (W as u32, H as u32));
+    assert_eq!(info.color_type, png::ColorType::Rgb);
+    assert_eq!(&buf[..info.buffer_size()], rgb(&A));
+}
+
+#[test]
+fn gif_merges_repeated_frames_and_keeps_60hz() {
+    // 3 quadros de A e 3 de B: 50ms cada, ou seja, 5 centésimos
+    let bytes = record(RecordingFormat::Gif, &[&A, &A, &A, &B, &B, &B]);
+
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = options.read_info(Cursor::new(bytes)).unwrap();
+    let mut frames = Vec::new();
+    while let Some(frame) = decoder.read_next_frame().unwrap() {
+        let pixels: Vec<u8> = frame
+            .buffer
+            .chunks(4)
+            .flat_map(|p| [p[0], p[1], p[2]])
+            .collect();
+        frames.push((pixels, frame.delay));
+    }
+    assert_eq!(frames, [(rgb(&A), 5), (rgb(&B), 5)]);
+}
+
+#[test]
+fn gif_delays_do_not_drift() {
+    // Quadros sempre diferentes: atrasos de 1 e 2 centésimos que somam exatamente 1 segundo
+    let frames: Vec<[u32; W * H]> = (0..60).map(|i| [i as u32; W * H]).collect();
+    let refs: Vec<&[u32]> = frames.iter().map(|f| &f[..]).collect();
+    let bytes = record(RecordingFormat::Gif, &refs);
+
+    let mut decoder = gif::DecodeOptions::new()
+        .read_info(Cursor::new(bytes))
+        .unwrap();
+    let mut total = 0;
+    let mut count = 0;
+    while let Some(frame) = decoder.read_next_frame().unwrap() {
+        assert!(frame.delay == 1 || frame.delay == 2);
+        total += frame.delay;
+        count += 1;
+    }
+    assert_eq!((count, total), (60, 100));
+}
+
+#[test]
+fn apng_uses_exact_frame_delays() {
+    let bytes = record(RecordingFormat::Apng, &[&A, &A, &B]);
+
+    let mut reader = png::Decoder::new(Cursor::new(bytes)).read_info().unwrap();
+    assert_eq!(reader.info().animation_control.unwrap().num_frames, 2);
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let mut frames = Vec::new();
+    for _ in 0..2 {
+        let info = reader.next_frame(&mut buf).unwrap();
+        let control = reader.info().frame_control.unwrap();
+        frames.push((
+            buf[..info.buffer_size()].to_vec(),
+            control.delay_num,
+            control.delay_den,
+        ));
+    }
+    assert_eq!(frames, [(rgb(&A), 2, 60), (rgb(&B), 1, 60)]);
+}
+
+#[test]
+fn y4m_frames_are_full_resolution_yuv() {
+    let mut writer = Y4mWriter::new(Vec::new(), W, H).unwrap();
+    writer.write_frame(&A).unwrap();
+    writer.write_frame(&B).unwrap();
+    let bytes = writer.finish().unwrap();
+
+    let header = b"YUV4MPEG2 W4 H2 F60:1 Ip A1:1 C444 XCOLORRANGE=FULL\n";
+    assert!(bytes.starts_with(header));
+    let frame_size = b"FRAME\n".len() + W * H * 3;
+    assert_eq!(bytes.len(), header.len() + 2 * frame_size);
+
+    let planes = &bytes[header.len() + 6..header.len() + frame_size];
+    let (y, chroma) = planes.split_at(W * H);
+    let (u, v) = chroma.split_at(W * H);
+    // Preto e branco ficam sem cor; o âmbar tem Cb baixo e Cr alto
+    assert_eq!((y[0], u[0], v[0]), (0, 128, 128));
+    assert_eq!((y[1], u[1], v[1]), (255, 128, 128));
+    assert!(u[2] < 128 && v[2] > 128);
+}
+
+#[test]
+fn raw_frames_are_exact_rgb24() {
+    let mut writer = RawVideoWriter::new(Vec::new(), W, H);
+    assert_eq!(
+        writer.ffmpeg_input(),
+        "-f rawvideo -pix_fmt rgb24 -s 4x2 -r 60"
+    );
+    writer.write_frame(&A).unwrap();
+    writer.write_frame(&B).unwrap();
+    let bytes = writer.finish().unwrap();
+    // Sem cabeçalho nem conversão: os quadros são os bytes das cores, um atrás do outro
+    assert_eq!(bytes, [rgb(&A), rgb(&B)].concat());
+}
@@ -0,0 +1,76 @@
+// Partes comuns dos testes diferenciais do recompilador e do JIT: os geradores de programas e
+// quirks e a comparação do estado com o interpretador
+
+use chip8::Chip8;
+use chip8::cpu::START_ADDRESS;
+use chip8::quirks::Quirks;
+use proptest::prelude::*;
+
+pub const MAX_PROGRAM_LEN: usize = 48;
+pub const PROGRAM_END: u16 = START_ADDRESS as u16 + 2 * MAX_PROGRAM_LEN as u16;
+
+pub fn program_address() -> impl Strategy<Value = u16> {
+    START_ADDRESS as u16..PROGRAM_END
+}
+
+// Qualquer instrução, com mais saltos para dentro do programa e escritas em cima dele. Os dois
+// lados começam com o mesmo gerador, então até o Cxkk tem que dar o mesmo resultado
+pub fn opcode() -> impl Strategy<Value = u16> {
+    let x = 0u16..16;
+    prop_oneof![
+        4 => any::<u16>(),
+        1 => program_address().prop_map(|addr| 0x1000 | (addr & !1)),
+        1 => program_address().prop_map(|addr| 0x2000 | (addr & !1)),
+        // I apontando para o programa, seguido de escritas com Fx33 e Fx55
+        2 => program_address().prop_map(|addr| 0xA000 | addr),
+        2 => (x.clone(), prop::sample::select(vec![0x33u16, 0x55]))
+            .prop_map(|(x, op)| 0xF000 | (x << 8) | op),
+        1 => x.prop_map(|x| 0xF00A | (x << 8)),
+    ]
+}
+
+pub fn quirks() -> impl Strategy<Value = Quirks> {
+    prop::array::uniform7(any::<bool>()).prop_map(|flags| {
+        let mut quirks = Quirks::default();
+        for (quirk, flag) in quirks.flags_mut().into_iter().zip(flags) {
+            *quirk = flag;
+        }
+        quirks
+    })
+}
+
+// Compara uma máquina que rodou no interpretador (`a`) com outra que rodou em `engine`
+pub fn same_state(engine: &str, a: &Chip8, b: &Chip8) -> Result<(), String> {
+    let fields = [
+        ("v", format!("{:?}", a.v), format!("{:?}", b.v)),
+        ("i", a.i.to_string(), b.i.to_string()),
+        ("pc", format!("{:#05X}", a.pc), format!("{:#05X}", b.pc)),
+        ("sp", a.sp.to_string(), b.sp.to_string()),
+        ("stack", format!("{:?}", a.stack), format!("{:?}", b.stack)),
+        ("timers", format!("{} {}", a.delay_timer, a.sound_timer), {
+            format!("{} {}", b.delay_timer, b.sound_timer)
+        }),
+        (
+            "key_wait",
+            format!("{:?}", a.key_wait),
+            format!("{:?}", b.key_wait),
+        ),
+        (
+            "frame_count",
+            a.frame_count.to_string(),
+            b.frame_count.to_string(),
+        ),
+    ];
+    for (name, a, b) in fields {
+        if a != b {
+            return Err(format!("{}: interpreter={} {}={}", name, a, engine, b));
+        }
+    }
+    if a.memory != b.memory {
+        return Err("memory differs".to_string());
+    }
+    if a.video != b.video {
+        return Err("video differs".to_string());
+    }
+    Ok(())
+}
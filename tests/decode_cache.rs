@@ -0,0 +1,97 @@
+// O cache de decodificação não pode mudar o resultado de nenhuma ROM: código que se modifica pelo
+// Fx33 / Fx55 tem que ser decodificado de novo
+
+use chip8::Chip8;
+use chip8::instruction::{Instruction, InstructionSet, decode};
+use proptest::prelude::*;
+
+fn load(program: &[u8]) -> Chip8 {
+    let mut chip8 = Chip8::new();
+    chip8.trace = false;
+    chip8.load_bytes(program).unwrap();
+    chip8
+}
+
+#[test]
+fn opcodes_are_decoded_with_their_operands() {
+    assert_eq!(decode(0x00E0), Instruction::Clear);
+    assert_eq!(decode(0x0123), Instruction::Unknown(0x0123));
+    assert_eq!(decode(0x1ABC), Instruction::Jump(0xABC));
+    assert_eq!(decode(0x5120), Instruction::SkipEqualRegister(1, 2));
+    assert_eq!(decode(0x5121), Instruction::Unknown(0x5121));
+    assert_eq!(decode(0x8A3E), Instruction::ShiftLeft(0xA, 3));
+    assert_eq!(decode(0xB2F0), Instruction::JumpOffset(2, 0x2F0));
+    assert_eq!(decode(0xD12F), Instruction::Draw(1, 2, 0xF));
+    assert_eq!(decode(0xF002), Instruction::Unknown(0xF002));
+    assert_eq!(InstructionSet::XoChip.decode(0xF002), Instruction::Audio);
+    assert_eq!(InstructionSet::XoChip.decode(0xF53A), Instruction::Pitch(5));
+    assert_eq!(decode(0xF102), Instruction::Unknown(0xF102));
+    assert_eq!(decode(0xF733), Instruction::Bcd(7));
+    assert_eq!(std::mem::size_of::<Option<Instruction>>(), 4);
+}
+
+#[test]
+fn store_over_executed_code_is_seen() {
+    // 0x200: LD V0, 0x11 (sobrescrito pelo laço)
+    // 0x202: LD V1, 0x60; LD V2, 0x22; LD I, 0x1FF; LD [I], V2 -> 0x200 vira LD V0, 0x22
+    // 0x20A: SE V0, 0x22; JP 0x200; JP 0x20E
+    let mut chip8 = load(&[
+        0x60, 0x11, 0x61, 0x60, 0x62, 0x22, 0xA1, 0xFF, 0xF2, 0x55, 0x30, 0x22, 0x12, 0x00, 0x12,
+        0x0E,
+    ]);
+    chip8.run_frame(20).unwrap();
+    assert_eq!(chip8.v[0], 0x22);
+    assert_eq!(chip8.pc, 0x20E);
+}
+
+#[test]
+fn bcd_over_the_second_byte_is_seen() {
+    // 0x200: LD V3, 0 e depois LD V3, 2 (o 0x02 vem do BCD de 200)
+    // 0x202: CLS (vira 0x0000, ignorado); LD V0, 200; LD I, 0x201; LD B, V0; JP 0x200
+    let mut chip8 = load(&[
+        0x63, 0x00, 0x00, 0xE0, 0x60, 0xC8, 0xA2, 0x01, 0xF0, 0x33, 0x12, 0x00,
+    ]);
+    chip8.run_frame(6).unwrap();
+    assert_eq!(chip8.v[3], 0x00);
+    chip8.run_frame(1).unwrap();
+    assert_eq!(chip8.v[3], 0x02);
+}
+
+#[test]
+fn direct_memory_writes_need_a_flush() {
+    let mut chip8 = load(&[0x60, 0x01, 0x12, 0x00]);
+    chip8.run_frame(2).unwrap();
+    chip8.memory[0x201] = 0x02;
+    chip8.run_frame(2).unwrap();
+    assert_eq!(chip8.v[0], 0x01);
+    chip8.flush_decode_cache();
+    chip8.run_frame(2).unwrap();
+    assert_eq!(chip8.v[0], 0x02);
+}
+
+proptest! {
+    // Com ou sem cache, qualquer programa termina no mesmo estado. O clone leva o mesmo gerador,
+    // então até o Cxkk sorteia os mesmos números
+    #[test]
+    fn cache_does_not_change_execution(
+        program in prop::collection::vec(any::<u8>(), 0..64),
+        cycles in 0usize..300,
+    ) {
+        let mut cached = load(&program);
+        let mut uncached = cached.clone();
+        uncached.decode_cache = false;
+        for _ in 0..cycles {
+            let a = cached.cycle();
+            let b = uncached.cycle();
+            prop_assert_eq!(&a, &b);
+            prop_assert_eq!(cached.v, uncached.v);
+            prop_assert_eq!(cached.pc, uncached.pc);
+            prop_assert_eq!(cached.i, uncached.i);
+            if a.is_err() {
+                break;
+            }
+        }
+        prop_assert_eq!(&cached.memory, &uncached.memory);
+        prop_assert_eq!(cached.video, uncached.video);
+    }
+}
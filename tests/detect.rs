@@ -0,0 +1,69 @@
+use chip8::Chip8;
+use chip8::cpu::START_ADDRESS;
+use chip8::detect::{Confidence, detect};
+use chip8::platform::Platform;
+
+#[test]
+fn plain_chip8_rom_is_chip8() {
+    let rom = std::fs::read("roms/IBM Logo.ch8").unwrap();
+    let detection = detect(&rom);
+    assert_eq!(detection.platform, Platform::Chip8);
+    assert_eq!(detection.confidence, Confidence::Medium);
+    assert!(detection.evidence.is_empty());
+}
+
+#[test]
+fn data_after_the_code_is_ignored() {
+    // 1200 prende o programa; o 00FF depois dele é um sprite
+    let detection = detect(&[0x12, 0x00, 0x00, 0xFF]);
+    assert_eq!(detection.platform, Platform::Chip8);
+}
+
+#[test]
+fn reachable_schip_opcodes_give_high_confidence() {
+    // 00FF (hires), 2206 (CALL 0x206), 1204, D120 (sprite 16x16), 00EE
+    let rom = [0x00, 0xFF, 0x22, 0x06, 0x12, 0x04, 0xD1, 0x20, 0x00, 0xEE];
+    let detection = detect(&rom);
+    assert_eq!(detection.platform, Platform::SuperChip);
+    assert_eq!(detection.confidence, Confidence::High);
+    assert_eq!(detection.evidence, ["00FF at 0x200", "D120 at 0x206"]);
+}
+
+#[test]
+fn xochip_wins_over_schip_and_follows_long_loads() {
+    // 3000 pula o F000 inteiro (4 bytes) e cai no F201 (plano 2); 00FF também está no fluxo
+    let rom = [
+        0x30, 0x00, 0xF0, 0x00, 0x12, 0x34, 0xF2, 0x01, 0x00, 0xFF, 0x12, 0x0A,
+    ];
+    let detection = detect(&rom);
+    assert_eq!(detection.platform, Platform::XoChip);
+    assert_eq!(detection.confidence, Confidence::High);
+    assert_eq!(detection.evidence, ["F000 at 0x202", "F201 at 0x206"]);
+}
+
+#[test]
+fn bytes_outside_the_flow_only_give_low_confidence() {
+    // O destino do B202 depende do V0, então o 00FF pode ser código ou só um sprite
+    let rom = [0xB2, 0x02, 0x00, 0xFF];
+    let detection = detect(&rom);
+    assert_eq!(detection.platform, Platform::SuperChip);
+    assert_eq!(detection.confidence, Confidence::Low);
+    assert_eq!(
+        detection.to_string(),
+        "schip (low confidence: 00FF at 0x202)"
+    );
+}
+
+#[test]
+fn large_roms_are_xochip_and_get_64k_of_memory() {
+    let rom = vec![0x12; 8000];
+    let detection = detect(&rom);
+    assert_eq!(detection.platform, Platform::XoChip);
+    assert_eq!(detection.confidence, Confidence::High);
+
+    assert!(Chip8::new().load_bytes(&rom).is_err());
+    let mut chip8 = Chip8::with_memory_size(detection.platform.memory_size());
+    chip8.load_bytes(&rom).unwrap();
+    assert_eq!(chip8.memory[START_ADDRESS + 7999], 0x12);
+    assert_eq!(chip8.memory.len(), 0x10000);
+}
@@ -0,0 +1,251 @@
+// Testes diferenciais: gera programas e estados aleatórios, executa o modelo de referência e o
+// `Chip8::cycle()` lado a lado e falha na primeira divergência de registrador, memória ou pixel.
+// O proptest se encarrega de encolher a falha até o menor programa que ainda reproduz o erro.
+
+mod reference;
+
+use chip8::cpu::{KEYPAD_SIZE, REGISTER_COUNT, STACK_SIZE, START_ADDRESS};
+use chip8::{Chip8, Chip8Error, Quirks, VIDEO_HEIGHT, VIDEO_WIDTH};
+use proptest::prelude::*;
+use reference::{Fault, Reference, compare};
+
+const MAX_PROGRAM_LEN: usize = 32;
+const PROGRAM_END: u16 = START_ADDRESS as u16 + 2 * MAX_PROGRAM_LEN as u16;
+
+#[derive(Debug, Clone)]
+struct MachineState {
+    v: [u8; REGISTER_COUNT],
+    i: u16,
+    stack: Vec<u16>,
+    delay_timer: u8,
+    sound_timer: u8,
+    keypad: [bool; KEYPAD_SIZE],
+    // Passo em que todas as teclas são soltas, para exercitar o Fx0A que espera a tecla subir
+    release_at: usize,
+    quirks: Quirks,
+    low_memory: Vec<u8>,
+    video: Vec<bool>,
+}
+
+fn program_address() -> impl Strategy<Value = u16> {
+    (START_ADDRESS as u16..PROGRAM_END).prop_map(|addr| addr & !1)
+}
+
+fn opcode() -> impl Strategy<Value = u16> {
+    let x = 0u16..16;
+    let y = 0u16..16;
+    prop_oneof![
+        Just(0x00E0),
+        Just(0x00EE),
+        program_address().prop_map(|addr| 0x1000 | addr),
+        program_address().prop_map(|addr| 0x2000 | addr),
+        (0x3u16..=0x4, x.clone(), any::<u8>())
+            .prop_map(|(op, x, kk)| (op << 12) | (x << 8) | kk as u16),
+        (
+            prop::sample::select(vec![0x5u16, 0x9]),
+            x.clone(),
+            y.clone()
+        )
+            .prop_map(|(op, x, y)| (op << 12) | (x << 8) | (y << 4)),
+        (0x6u16..=0x7, x.clone(), any::<u8>())
+            .prop_map(|(op, x, kk)| (op << 12) | (x << 8) | kk as u16),
+        (
+            x.clone(),
+            y.clone(),
+            prop::sample::select(vec![0x0u16, 0x1, 0x2, 0x3, 0x4, 0x5, 0x6, 0x7, 0xE])
+        )
+            .prop_map(|(x, y, n)| 0x8000 | (x << 8) | (y << 4) | n),
+        (0u16..0x1000).prop_map(|nnn| 0xA000 | nnn),
+        (0u16..0x1000).prop_map(|nnn| 0xB000 | nnn),
+        (x.clone(), any::<u8>()).prop_map(|(x, kk)| 0xC000 | (x << 8) | kk as u16),
+        (x.clone(), y.clone(), 0u16..16).prop_map(|(x, y, n)| 0xD000 | (x << 8) | (y << 4) | n),
+        (x.clone(), prop::sample::select(vec![0x9Eu16, 0xA1]))
+            .prop_map(|(x, kk)| 0xE000 | (x << 8) | kk),
+        // F002 e Fx3A são do XO-CHIP: no CHIP-8 não podem mexer no áudio
+        (
+            x,
+            prop::sample::select(vec![
+                0x02u16, 0x07, 0x0A, 0x15, 0x18, 0x1E, 0x29, 0x33, 0x3A, 0x55, 0x65
+            ])
+        )
+            .prop_map(|(x, kk)| 0xF000 | (x << 8) | kk),
+        // Também joga opcodes totalmente aleatórios para cobrir os desconhecidos
+        any::<u16>(),
+    ]
+}
+
+// Cada caso sorteia uma combinação de quirks, assim os dois lados de cada uma são comparados
+fn quirks() -> impl Strategy<Value = Quirks> {
+    any::<[bool; 7]>().prop_map(
+        |[
+            vf_reset,
+            memory_increment_i,
+            shift_vx,
+            jump_vx,
+            clip_sprites,
+            key_wait_release,
+            display_wait,
+        ]| {
+            Quirks {
+                vf_reset,
+                memory_increment_i,
+                shift_vx,
+                jump_vx,
+                clip_sprites,
+                key_wait_release,
+                display_wait,
+            }
+        },
+    )
+}
+
+fn machine_state() -> impl Strategy<Value = MachineState> {
+    (
+        any::<[u8; REGISTER_COUNT]>(),
+        any::<u16>(),
+        prop::collection::vec(program_address(), 0..=STACK_SIZE),
+        any::<u8>(),
+        any::<u8>(),
+        (
+            any::<[bool; KEYPAD_SIZE]>(),
+            0..2 * MAX_PROGRAM_LEN,
+            quirks(),
+        ),
+        prop::collection::vec(any::<u8>(), START_ADDRESS),
+        prop::collection::vec(any::<bool>(), VIDEO_WIDTH * VIDEO_HEIGHT),
+    )
+        .prop_map(
+            |(
+                v,
+                i,
+                stack,
+                delay_timer,
+                sound_timer,
+                (keypad, release_at, quirks),
+                low_memory,
+                video,
+            )| MachineState {
+                v,
+                i,
+                stack,
+                delay_timer,
+                sound_timer,
+                keypad,
+                release_at,
+                quirks,
+                low_memory,
+                video,
+            },
+        )
+}
+
+fn build_chip8(program: &[u16], state: &MachineState) -> Chip8 {
+    let mut chip8 = Chip8::new();
+    chip8.memory[..START_ADDRESS].copy_from_slice(&state.low_memory);
+    for (n, opcode) in program.iter().enumerate() {
+        let addr = START_ADDRESS + 2 * n;
+        chip8.memory[addr..addr + 2].copy_from_slice(&opcode.to_be_bytes());
+    }
+    chip8.v = state.v;
+    chip8.i = state.i;
+    for (n, &addr) in state.stack.iter().enumerate() {
+        chip8.stack[n] = addr;
+    }
+    chip8.sp = state.stack.len() as u8;
+    chip8.delay_timer = state.delay_timer;
+    chip8.sound_timer = state.sound_timer;
+    chip8.keypad = state.keypad;
+    chip8.quirks = state.quirks;
+    for (index, &on) in state.video.iter().enumerate() {
+        chip8.set_pixel(index % VIDEO_WIDTH, index / VIDEO_WIDTH, on);
+    }
+    chip8
+}
+
+// Executa os dois modelos em paralelo e devolve a descrição da primeira divergência
+fn run(program: &[u16], state: &MachineState, steps: usize) -> Result<(), String> {
+    let mut core = build_chip8(program, state);
+    core.trace = false;
+    let mut reference = Reference::from_chip8(&core);
+    assert!(compare(&reference, &core).is_none());
+
+    for step in 0..steps {
+        if step == state.release_at {
+            for key in 0..KEYPAD_SIZE {
+                core.set_key(key, false);
+            }
+            reference.keypad = [false; KEYPAD_SIZE];
+        }
+        let pc = reference.pc as usize;
+        let opcode = u16::from_be_bytes([core.read_byte(pc), core.read_byte(pc + 1)]);
+        let result = core.cycle();
+
+        // Para o Cxkk o byte aleatório é o que o core sorteou. Se o core ignorar a máscara kk o
+        // registrador diverge mesmo assim.
+        let random = core.v[((opcode >> 8) & 0xF) as usize];
+        let expected = reference.step(random);
+
+        // Estouro de pilha encerra a execução: os dois lados precisam concordar que é um erro
+        match (expected, result) {
+            (Ok(()), Ok(())) => {}
+            (Err(Fault::StackOverflow), Err(Chip8Error::StackOverflow { .. }))
+            | (Err(Fault::StackUnderflow), Err(Chip8Error::StackUnderflow { .. })) => break,
+            (expected, result) => {
+                return Err(format!(
+                    "step {} at {:#05X} (opcode {:04X}): reference={:?} core={:?}",
+                    step, pc, opcode, expected, result
+                ));
+            }
+        }
+
+        if let Some(divergence) = compare(&reference, &core) {
+            return Err(format!(
+                "step {} at {:#05X} (opcode {:04X}): {}",
+                step, pc, opcode, divergence
+            ));
+        }
+    }
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(512))]
+
+    #[test]
+    fn core_matches_reference(
+        program in prop::collection::vec(opcode(), 1..MAX_PROGRAM_LEN),
+        state in machine_state(),
+    ) {
+        let steps = program.len() * 2;
+        if let Err(report) = run(&program, &state, steps) {
+            prop_assert!(false, "{}", report);
+        }
+    }
+}
+
+#[test]
+fn call_and_return_resume_after_call() {
+    // 0x200: CALL 0x206; 0x202: LD V1, 0x22; 0x204: JP 0x204; 0x206: LD V0, 0x11; 0x208: RET
+    let program = [0x2206, 0x6122, 0x1204, 0x6011, 0x00EE];
+    let state = MachineState {
+        v: [0; REGISTER_COUNT],
+        i: 0,
+        stack: Vec::new(),
+        delay_timer: 0,
+        sound_timer: 0,
+        keypad: [false; KEYPAD_SIZE],
+        release_at: 0,
+        quirks: Quirks::default(),
+        low_memory: vec![0; START_ADDRESS],
+        video: vec![false; VIDEO_WIDTH * VIDEO_HEIGHT],
+    };
+    assert_eq!(run(&program, &state, 8), Ok(()));
+
+    let mut chip8 = build_chip8(&program, &state);
+    for _ in 0..4 {
+        chip8.cycle().unwrap();
+    }
+    assert_eq!(chip8.v[0], 0x11);
+    assert_eq!(chip8.v[1], 0x22);
+    assert_eq!(chip8.sp, 0);
+}
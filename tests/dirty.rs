@@ -0,0 +1,83 @@
+// Linhas sujas: o core marca as linhas que o 00E0, o Dxyn e as rolagens alteraram, e o frontend
+// redesenha só essas
+
+use chip8::Chip8;
+use chip8::cpu::ALL_ROWS;
+use chip8::instruction::Instruction;
+use chip8::platform::Platform;
+use chip8::recompiler::Recompiler;
+
+fn machine(program: &[u8]) -> Chip8 {
+    let mut chip8 = Chip8::new();
+    chip8.trace = false;
+    chip8.quirks.clip_sprites = false;
+    chip8.load_bytes(program).unwrap();
+    chip8
+}
+
+#[test]
+fn draw_marks_the_rows_it_touches() {
+    // Sprite de 4 linhas em 0x300 com a segunda zerada
+    let mut chip8 = machine(&[]);
+    chip8.memory[0x300..0x304].copy_from_slice(&[0xF0, 0x00, 0x90, 0xF0]);
+    // Uma máquina nova começa com a tela toda para desenhar
+    assert_eq!(chip8.take_dirty_rows(), ALL_ROWS);
+    assert_eq!(chip8.dirty_rows(), 0);
+
+    chip8.i = 0x300;
+    chip8.v[0] = 10;
+    chip8.v[1] = 30;
+    chip8.execute(Instruction::Draw(0, 1, 4)).unwrap();
+    // Linhas 30, 0 e 1 (dando a volta); a 31 recebeu uma linha vazia do sprite
+    assert_eq!(chip8.take_dirty_rows(), 1 << 30 | 1 << 0 | 1 << 1);
+
+    chip8.execute(Instruction::Clear).unwrap();
+    assert_eq!(chip8.take_dirty_rows(), ALL_ROWS);
+    // Limpar uma tela já apagada não muda nada
+    chip8.execute(Instruction::Clear).unwrap();
+    assert_eq!(chip8.take_dirty_rows(), 0);
+}
+
+#[test]
+fn recompiled_clear_marks_the_screen() {
+    // 0x200: LD I, 0x300; DRW V0, V0, 5; CLS; JP 0x206
+    let mut chip8 = machine(&[0xA3, 0x00, 0xD0, 0x05, 0x00, 0xE0, 0x12, 0x06]);
+    chip8.memory[0x300..0x305].fill(0xFF);
+    let mut recompiler = Recompiler::new(chip8.memory.len());
+    chip8.take_dirty_rows();
+
+    recompiler.run_frame(&mut chip8, 2).unwrap();
+    assert_eq!(chip8.take_dirty_rows(), 0b11111);
+    recompiler.run_frame(&mut chip8, 1).unwrap();
+    assert_eq!(chip8.take_dirty_rows(), ALL_ROWS);
+    recompiler.run_frame(&mut chip8, 10).unwrap();
+    assert_eq!(chip8.take_dirty_rows(), 0);
+}
+
+#[test]
+fn scrolling_marks_the_whole_screen() {
+    let mut chip8 = Platform::XoChip.machine();
+    chip8.execute(Instruction::HighRes).unwrap();
+    chip8.take_dirty_rows();
+    // Rolar uma tela apagada não muda nada
+    chip8.execute(Instruction::ScrollDown(4)).unwrap();
+    assert_eq!(chip8.take_dirty_rows(), 0);
+
+    chip8.set_pixel(3, 5, true);
+    chip8.take_dirty_rows();
+    let scrolls = [
+        Instruction::ScrollDown(4),
+        Instruction::ScrollUp(2),
+        Instruction::ScrollRight,
+        Instruction::ScrollLeft,
+    ];
+    for scroll in scrolls {
+        chip8.execute(scroll).unwrap();
+        assert_eq!(chip8.take_dirty_rows(), ALL_ROWS, "{:?}", scroll);
+    }
+    assert!(chip8.pixel(3, 7));
+
+    // Trocar a resolução apaga a tela
+    chip8.execute(Instruction::LowRes).unwrap();
+    assert_eq!(chip8.take_dirty_rows(), ALL_ROWS);
+}
@@ -0,0 +1,101 @@
+use chip8::Chip8;
+use chip8::flicker::{FlickerFilter, FlickerMode};
+use chip8::palette::{Palette, blend};
+
+const ON: u32 = 0xFFFFFF;
+const OFF: u32 = 0x000000;
+
+// Um pixel que pisca: aceso, apagado, aceso, apagado...
+fn run(mode: FlickerMode, frames: &[u8]) -> Vec<u32> {
+    let palette = Palette::default();
+    let mut filter = FlickerFilter::new(mode);
+    let mut out = [0u32; 1];
+    frames
+        .iter()
+        .map(|&pixel| {
+            filter.process(&[pixel], &palette, &mut out);
+            out[0]
+        })
+        .collect()
+}
+
+#[test]
+fn modes_parse_from_strings() {
+    assert_eq!("off".parse(), Ok(FlickerMode::Off));
+    assert_eq!("OR".parse(), Ok(FlickerMode::OrLastTwo));
+    assert_eq!("blend".parse(), Ok(FlickerMode::Blend(3)));
+    assert_eq!("blend:2".parse(), Ok(FlickerMode::Blend(2)));
+    assert_eq!("decay:0.5".parse(), Ok(FlickerMode::Decay(0.5)));
+    assert!("blend:0".parse::<FlickerMode>().is_err());
+    assert!("decay:1.5".parse::<FlickerMode>().is_err());
+    assert!("smear".parse::<FlickerMode>().is_err());
+    assert_eq!(
+        FlickerMode::Blend(4).to_string().parse(),
+        Ok(FlickerMode::Blend(4))
+    );
+}
+
+#[test]
+fn off_shows_the_raw_frame() {
+    assert_eq!(run(FlickerMode::Off, &[1, 0, 1]), [ON, OFF, ON]);
+}
+
+#[test]
+fn or_keeps_a_blinking_pixel_lit() {
+    assert_eq!(
+        run(FlickerMode::OrLastTwo, &[1, 0, 1, 0, 0]),
+        [ON, ON, ON, ON, OFF]
+    );
+}
+
+#[test]
+fn blend_averages_the_last_frames() {
+    assert_eq!(run(FlickerMode::Blend(2), &[1, 0, 0]), [ON, 0x7F7F7F, OFF]);
+}
+
+#[test]
+fn decay_turns_on_instantly_and_fades_slowly() {
+    let out = run(FlickerMode::Decay(0.5), &[0, 1, 0, 0]);
+    assert_eq!(out[0], OFF);
+    assert_eq!(out[1], ON);
+    assert_eq!(out[2], 0x808080);
+    assert_eq!(out[3], 0x404040);
+}
+
+#[test]
+fn decay_fades_towards_a_brighter_background() {
+    // Na paleta lcd o fundo é mais claro que o pixel aceso: o pixel ainda tem que acender na hora
+    // e apagar aos poucos
+    let palette = Palette::preset("lcd").unwrap();
+    let (off, on) = (palette.color(0), palette.color(1));
+    let mut filter = FlickerFilter::new(FlickerMode::Decay(0.5));
+    let mut out = [0u32; 1];
+    let frames: Vec<u32> = [0, 1, 0, 0, 1]
+        .iter()
+        .map(|&pixel| {
+            filter.process(&[pixel], &palette, &mut out);
+            out[0]
+        })
+        .collect();
+    assert_eq!(
+        frames,
+        [off, on, blend(off, on, 0.5), blend(off, on, 0.25), on]
+    );
+}
+
+#[test]
+fn run_frame_ticks_timers_then_runs_instructions() {
+    let mut chip8 = Chip8::new();
+    chip8.trace = false;
+    // 6005: V0 = 5, F015: delay = V0, 1200: loop
+    chip8
+        .load_bytes(&[0x60, 0x05, 0xF0, 0x15, 0x12, 0x04])
+        .unwrap();
+    chip8.run_frame(3).unwrap();
+    // O timer foi decrementado antes do F015, então ainda está cheio no vblank
+    assert_eq!(chip8.delay_timer, 5);
+    assert_eq!(chip8.frame_count, 1);
+    chip8.run_frame(10).unwrap();
+    assert_eq!(chip8.delay_timer, 4);
+    assert_eq!(chip8.frame_count, 2);
+}
@@ -0,0 +1,74 @@
+use chip8::Chip8;
+use chip8::cpu::START_ADDRESS;
+
+fn load(program: &[u8]) -> Chip8 {
+    let mut chip8 = Chip8::new();
+    chip8.trace = false;
+    chip8.load_bytes(program).unwrap();
+    chip8
+}
+
+// F30A: LD V3, K; 1202: JP 0x202
+const WAIT_KEY: [u8; 4] = [0xF3, 0x0A, 0x12, 0x02];
+
+#[test]
+fn set_key_records_edges_until_the_end_of_the_frame() {
+    let mut chip8 = load(&[0x12, 0x00]);
+    chip8.set_key(0x5, true);
+    assert!(chip8.keypad[0x5] && chip8.key_pressed(0x5));
+    // Continuar pressionada não é uma nova borda
+    chip8.run_frame(1).unwrap();
+    chip8.set_key(0x5, true);
+    assert!(!chip8.key_pressed(0x5));
+
+    chip8.set_key(0x5, false);
+    assert!(chip8.key_released(0x5) && !chip8.keypad[0x5]);
+    chip8.run_frame(1).unwrap();
+    assert!(!chip8.key_released(0x5));
+}
+
+#[test]
+fn key_wait_stores_the_key_when_it_is_released() {
+    let mut chip8 = load(&WAIT_KEY);
+    chip8.run_frame(5).unwrap();
+    assert_eq!(chip8.pc, START_ADDRESS as u16);
+
+    chip8.set_key(0xB, true);
+    chip8.run_frame(5).unwrap();
+    // Pressionada não basta, o VIP espera a tecla subir
+    assert_eq!(chip8.pc, START_ADDRESS as u16);
+    assert_eq!(chip8.key_wait, Some(0xB));
+
+    chip8.set_key(0xB, false);
+    chip8.run_frame(1).unwrap();
+    assert_eq!(chip8.v[3], 0xB);
+    assert_eq!(chip8.pc, START_ADDRESS as u16 + 2);
+    assert_eq!(chip8.key_wait, None);
+}
+
+#[test]
+fn key_wait_on_press_when_the_quirk_is_off() {
+    let mut chip8 = load(&WAIT_KEY);
+    chip8.quirks.key_wait_release = false;
+    chip8.set_key(0x7, true);
+    chip8.run_frame(1).unwrap();
+    assert_eq!(chip8.v[3], 0x7);
+    assert_eq!(chip8.pc, START_ADDRESS as u16 + 2);
+}
+
+#[test]
+fn a_tap_shorter_than_a_frame_is_not_lost() {
+    // 6004: LD V0, 4; E09E: SKP V0; 6101: LD V1, 1; 1206: JP 0x206
+    let mut chip8 = load(&[0x60, 0x04, 0xE0, 0x9E, 0x61, 0x01, 0x12, 0x06]);
+    chip8.set_key(0x4, true);
+    chip8.set_key(0x4, false);
+    chip8.run_frame(4).unwrap();
+    assert_eq!(chip8.v[1], 0);
+
+    // O mesmo vale para o Fx0A: o toque é lembrado e aceito porque a tecla já subiu
+    let mut chip8 = load(&WAIT_KEY);
+    chip8.set_key(0x9, true);
+    chip8.set_key(0x9, false);
+    chip8.run_frame(2).unwrap();
+    assert_eq!(chip8.v[3], 0x9);
+}
@@ -0,0 +1,134 @@
+// Teste diferencial do JIT (só com `cargo test --features jit`): o mesmo programa roda no
+// interpretador e no código nativo, e o estado tem que ser igual no fim de cada quadro. As quirks
+// também mudam entre um quadro e outro, já que vf_reset e shift_vx ficam fixadas no código gerado
+#![cfg(feature = "jit")]
+
+mod common;
+
+use chip8::Chip8;
+use chip8::cpu::KEYPAD_SIZE;
+use chip8::jit::Jit;
+use chip8::platform::Platform;
+use chip8::quirks::Quirks;
+use common::{MAX_PROGRAM_LEN, opcode, quirks, same_state};
+use proptest::prelude::*;
+
+fn run(
+    program: &[u16],
+    quirks: Quirks,
+    other_quirks: Quirks,
+    instructions_per_frame: usize,
+    keys: &[u16],
+) -> Result<(), String> {
+    let bytes: Vec<u8> = program.iter().flat_map(|op| op.to_be_bytes()).collect();
+    let mut interpreted = Chip8::new();
+    interpreted.trace = false;
+    interpreted.quirks = quirks;
+    interpreted.load_bytes(&bytes).unwrap();
+    let mut compiled = interpreted.clone();
+    let mut jit = Jit::new(compiled.memory.len());
+
+    for (frame, &held) in keys.iter().enumerate() {
+        if frame == keys.len() / 2 {
+            interpreted.quirks = other_quirks;
+            compiled.quirks = other_quirks;
+        }
+        for key in 0..KEYPAD_SIZE {
+            interpreted.set_key(key, held & (1 << key) != 0);
+            compiled.set_key(key, held & (1 << key) != 0);
+        }
+        let a = interpreted.run_frame(instructions_per_frame);
+        let b = jit.run_frame(&mut compiled, instructions_per_frame);
+        if a != b {
+            return Err(format!("frame {}: interpreter={:?} jit={:?}", frame, a, b));
+        }
+        same_state("jit", &interpreted, &compiled)
+            .map_err(|e| format!("frame {}: {}", frame, e))?;
+        if a.is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(512))]
+
+    #[test]
+    fn jit_matches_interpreter(
+        program in prop::collection::vec(opcode(), 1..MAX_PROGRAM_LEN),
+        quirks in quirks(),
+        other_quirks in quirks(),
+        instructions_per_frame in 1usize..40,
+        keys in prop::collection::vec(any::<u16>(), 1..12),
+    ) {
+        if let Err(report) = run(&program, quirks, other_quirks, instructions_per_frame, &keys) {
+            prop_assert!(false, "{}", report);
+        }
+    }
+}
+
+#[test]
+fn blocks_stop_at_the_frame_budget() {
+    // 0x200: ADD V0, 1 (x4); JP 0x200. O bloco tem 5 instruções e o quadro só 3
+    let program: [u16; 5] = [0x7001, 0x7001, 0x7001, 0x7001, 0x1200];
+    let bytes: Vec<u8> = program.iter().flat_map(|op| op.to_be_bytes()).collect();
+    let mut chip8 = Chip8::new();
+    chip8.trace = false;
+    chip8.load_bytes(&bytes).unwrap();
+    chip8.delay_timer = 10;
+    let mut jit = Jit::new(chip8.memory.len());
+
+    jit.run_frame(&mut chip8, 3).unwrap();
+    assert_eq!((chip8.v[0], chip8.pc), (3, 0x206));
+    // ADD e JP no bloco de 0x206, o bloco de 0x200 inteiro e mais um ADD pelo interpretador
+    jit.run_frame(&mut chip8, 8).unwrap();
+    assert_eq!((chip8.v[0], chip8.pc), (9, 0x202));
+    assert_eq!(chip8.delay_timer, 8);
+    assert_eq!(jit.compilations, 2);
+}
+
+#[test]
+fn code_written_by_the_interpreter_is_recompiled() {
+    // 0x200: ADD V0, 1; SE V0, 0x10; JP 0x200
+    // 0x206: LD I, 0x200; LD V0, 0x71; LD V1, 0x02; LD [I], V1 -> 0x200 vira ADD V1, 2
+    // 0x20E: JP 0x200
+    let program: [u16; 8] = [
+        0x7001, 0x3010, 0x1200, 0xA200, 0x6071, 0x6102, 0xF155, 0x1200,
+    ];
+    let bytes: Vec<u8> = program.iter().flat_map(|op| op.to_be_bytes()).collect();
+    let mut chip8 = Chip8::new();
+    chip8.trace = false;
+    chip8.load_bytes(&bytes).unwrap();
+    let mut jit = Jit::new(chip8.memory.len());
+
+    jit.run_frame(&mut chip8, 3 * 15).unwrap();
+    assert_eq!(chip8.v[0], 0x0F);
+    assert_eq!(jit.compilations, 2);
+
+    jit.run_frame(&mut chip8, 2 + 4 + 1 + 1).unwrap();
+    assert_eq!(chip8.memory[0x200..0x202], [0x71, 0x02]);
+    assert_eq!(chip8.v[1], 0x04);
+}
+
+#[test]
+fn xochip_skips_over_long_loads() {
+    // 0x200: ADD V0, 1; SE V0, 1; LD I, LONG 0x1234; ADD V1, 1; JP 0x200. O skip do XO-CHIP pula os
+    // 4 bytes do F000 NNNN, então fica com o interpretador
+    let program: [u16; 6] = [0x7001, 0x3001, 0xF000, 0x1234, 0x7101, 0x1200];
+    let bytes: Vec<u8> = program.iter().flat_map(|op| op.to_be_bytes()).collect();
+    let mut chip8 = Platform::XoChip.machine();
+    chip8.load_bytes(&bytes).unwrap();
+    let mut interpreted = chip8.clone();
+    let mut jit = Jit::new(chip8.memory.len());
+
+    for _ in 0..4 {
+        jit.run_frame(&mut chip8, 9).unwrap();
+        interpreted.run_frame(9).unwrap();
+        assert_eq!(
+            (chip8.pc, chip8.i, chip8.v),
+            (interpreted.pc, interpreted.i, interpreted.v)
+        );
+    }
+    assert_eq!(chip8.i, 0x1234);
+}
@@ -0,0 +1,129 @@
+use chip8::config::Config;
+use chip8::keymap::{KEYPAD_LAYOUT, Keymap, Rebinder};
+
+#[test]
+fn every_preset_maps_all_sixteen_keys() {
+    for name in Keymap::preset_names() {
+        let keymap = Keymap::preset(name).unwrap();
+        for key in 0..16 {
+            let hosts = keymap.host_keys(key);
+            assert_eq!(hosts.len(), 1, "{} {:X}", name, key);
+            // Os nomes dos presets são nomes válidos de tecla do host
+            assert_eq!(keymap.get(hosts[0]), Some(key), "{} {}", name, hosts[0]);
+        }
+    }
+    assert_eq!(Keymap::default(), Keymap::preset("QWERTY").unwrap());
+    assert!(Keymap::preset("colemak").is_none());
+}
+
+#[test]
+fn presets_follow_the_keyboard_layout() {
+    let azerty = Keymap::preset("azerty").unwrap();
+    assert_eq!(azerty.get("A"), Some(0x4));
+    assert_eq!(azerty.get("W"), Some(0xA));
+    let numpad = Keymap::preset("numpad").unwrap();
+    assert_eq!(numpad.get("NumPad7"), Some(0x7));
+    assert_eq!(numpad.get("NumPadDot"), Some(0xF));
+    // "1" é atalho para "Key1" e os nomes não diferenciam maiúsculas
+    assert_eq!(Keymap::default().get("1"), Some(0x1));
+    assert_eq!(Keymap::default().get("q"), Some(0x4));
+}
+
+#[test]
+fn a_chip8_key_can_have_several_host_keys() {
+    let mut keymap = Keymap::default();
+    keymap.bind(0x5, &["W", "Up"]).unwrap();
+    keymap.bind(0x8, &["S", "Down"]).unwrap();
+    assert_eq!(keymap.get("Up"), Some(0x5));
+    assert_eq!(keymap.get("W"), Some(0x5));
+    assert_eq!(keymap.host_keys(0x8), ["Down", "S"]);
+
+    // Uma tecla do host só aciona uma tecla do CHIP-8
+    keymap.bind(0x6, &["Up"]).unwrap();
+    assert_eq!(keymap.host_keys(0x5), ["W"]);
+    assert_eq!(keymap.host_keys(0x6), ["Up"]);
+
+    assert!(keymap.bind(0x1, &["Hyper"]).is_err());
+}
+
+#[test]
+fn config_picks_preset_and_overrides_keys() {
+    let config = Config::parse(
+        r#"
+        [keymap]
+        preset = "dvorak"
+
+        [keymap.keys]
+        "5" = ["Comma", "Up"]
+        "a" = ["Space"]
+        "#,
+    )
+    .unwrap();
+    let keymap = config.keymap(None).unwrap();
+    assert_eq!(keymap.get("Up"), Some(0x5));
+    assert_eq!(keymap.get("Space"), Some(0xA));
+    assert_eq!(keymap.get("Semicolon"), None);
+    assert_eq!(keymap.get("O"), Some(0x8));
+
+    // A opção da linha de comando troca só o preset
+    let keymap = config.keymap(Some("qwerty")).unwrap();
+    assert_eq!(keymap.get("Up"), Some(0x5));
+    assert_eq!(keymap.get("S"), Some(0x8));
+
+    assert!(config.keymap(Some("colemak")).is_err());
+    let bad = Config::parse("[keymap.keys]\n\"G\" = [\"A\"]").unwrap();
+    assert!(bad.keymap(None).is_err());
+}
+
+#[test]
+fn a_host_key_in_two_chip8_keys_is_rejected() {
+    // Com a ordem da HashMap uma das duas ganharia ao acaso
+    let config = Config::parse("[keymap.keys]\n\"5\" = [\"Up\"]\n\"8\" = [\"up\", \"S\"]").unwrap();
+    let error = config.keymap(None).unwrap_err().to_string();
+    assert_eq!(error, "host key 'Up' is bound to both CHIP-8 keys 5 and 8");
+
+    // Repetir a tecla na mesma lista não é ambíguo
+    let config = Config::parse("[keymap.keys]\n\"5\" = [\"Up\", \"Up\"]").unwrap();
+    assert_eq!(config.keymap(None).unwrap().get("Up"), Some(0x5));
+}
+
+#[test]
+fn rebinder_asks_for_each_key_in_keypad_order() {
+    let mut rebinder = Rebinder::new(&Keymap::default());
+    let hosts = [
+        "NumPad7",
+        "NumPad8",
+        "NumPad9",
+        "NumPadSlash",
+        "NumPad4",
+        "NumPad5",
+        "NumPad6",
+        "NumPadAsterisk",
+        "NumPad1",
+        "NumPad2",
+        "NumPad3",
+        "NumPadMinus",
+        "NumPad0",
+    ];
+    for (index, host) in hosts.iter().enumerate() {
+        assert_eq!(rebinder.pending(), Some(KEYPAD_LAYOUT[index]));
+        rebinder.press(host).unwrap();
+    }
+    // As três últimas mantêm as teclas atuais
+    rebinder.skip();
+    rebinder.skip();
+    rebinder.skip();
+    assert!(rebinder.is_done());
+
+    let keymap = rebinder.finish();
+    assert_eq!(keymap.get("NumPad7"), Some(0x1));
+    assert_eq!(keymap.get("NumPad0"), Some(0xA));
+    assert_eq!(keymap.get("1"), None);
+    assert_eq!(keymap.get("X"), Some(0x0));
+    assert_eq!(keymap.get("V"), Some(0xF));
+
+    // O trecho gerado para o chip8.toml recria o mesmo mapeamento
+    let text = keymap.to_toml();
+    let config = Config::parse(&format!("[keymap]\npreset = \"qwerty\"\n{}", text)).unwrap();
+    assert_eq!(config.keymap(None).unwrap(), keymap);
+}
@@ -0,0 +1,157 @@
+use chip8::Quirks;
+use chip8::font::{self, GLYPH_HEIGHT};
+use chip8::menu::{
+    BrowserAction, MenuKey, PauseAction, PauseItem, PauseMenu, RomBrowser, SAVE_SLOTS, draw_menu,
+};
+use chip8::platform::Platform;
+use chip8::source::RomSource;
+use std::fs;
+use std::path::PathBuf;
+
+// Pasta temporária com algumas ROMs, apagada no fim do teste
+struct RomDir(PathBuf);
+
+impl RomDir {
+    fn new(name: &str, files: &[(&str, &[u8])]) -> RomDir {
+        let dir = std::env::temp_dir().join(format!("chip8-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for (file, contents) in files {
+            fs::write(dir.join(file), contents).unwrap();
+        }
+        RomDir(dir)
+    }
+}
+
+impl Drop for RomDir {
+    fn drop(&mut self) {
+        fs::remove_dir_all(&self.0).ok();
+    }
+}
+
+#[test]
+fn browser_lists_roms_by_title_from_sidecars() {
+    let dir = RomDir::new(
+        "browser",
+        &[
+            ("zeta.ch8", &[0x12, 0x00]),
+            ("pong.sc8", &[0x12, 0x00]),
+            ("pong.toml", b"title = \"Pong\"\nauthor = \"Paul\"\n"),
+            ("notes.txt", b"not a rom"),
+        ],
+    );
+    let mut browser = RomBrowser::scan(&dir.0).unwrap();
+    let titles: Vec<&str> = browser.entries().iter().map(|e| e.title.as_str()).collect();
+    assert_eq!(titles, ["Pong (Paul)", "zeta.ch8"]);
+
+    assert_eq!(browser.handle(MenuKey::Up), None);
+    assert_eq!(browser.selected().unwrap().title, "zeta.ch8");
+    browser.handle(MenuKey::Down);
+    assert_eq!(
+        browser.handle(MenuKey::Select),
+        Some(BrowserAction::Open(dir.0.join("pong.sc8")))
+    );
+    assert_eq!(browser.handle(MenuKey::Back), Some(BrowserAction::Close));
+}
+
+#[test]
+fn sidecar_settings_apply_when_loading() {
+    let dir = RomDir::new(
+        "sidecar",
+        &[
+            ("ant.sc8", &[0x00, 0xE0]),
+            (
+                "ant.toml",
+                b"title = \"Ant\"\ninstructions_per_frame = 40\n",
+            ),
+            ("bad.ch8", &[0x00, 0xE0]),
+            ("bad.toml", b"speed = 1\n"),
+        ],
+    );
+    let rom = RomSource::File(dir.0.join("ant.sc8"))
+        .load(|_| None)
+        .unwrap();
+    assert_eq!(rom.settings.display_title().as_deref(), Some("Ant"));
+    assert_eq!(rom.settings.instructions_per_frame, Some(40));
+    // A plataforma continua vindo da extensão
+    assert_eq!(rom.settings.platform(), Ok(Some(Platform::SuperChip)));
+
+    let error = RomSource::File(dir.0.join("bad.ch8"))
+        .load(|_| None)
+        .unwrap_err();
+    assert!(error.to_string().contains("bad.toml"), "{}", error);
+}
+
+#[test]
+fn pause_menu_changes_quirks_speed_and_slot() {
+    let mut menu = PauseMenu::new(0);
+    let mut quirks = Quirks::default();
+    let mut ipf = 10;
+    let mut press = |menu: &mut PauseMenu, key| menu.handle(key, &mut quirks, &mut ipf);
+
+    assert_eq!(press(&mut menu, MenuKey::Select), Some(PauseAction::Resume));
+    assert_eq!(press(&mut menu, MenuKey::Back), Some(PauseAction::Resume));
+
+    while menu.selected() != PauseItem::Slot {
+        press(&mut menu, MenuKey::Down);
+    }
+    press(&mut menu, MenuKey::Left);
+    assert_eq!(menu.slot, SAVE_SLOTS - 1);
+    press(&mut menu, MenuKey::Up);
+    assert_eq!(
+        press(&mut menu, MenuKey::Select),
+        Some(PauseAction::LoadState(SAVE_SLOTS - 1))
+    );
+
+    press(&mut menu, MenuKey::Down);
+    press(&mut menu, MenuKey::Down);
+    assert_eq!(menu.selected(), PauseItem::Speed);
+    press(&mut menu, MenuKey::Right);
+    press(&mut menu, MenuKey::Right);
+    press(&mut menu, MenuKey::Down);
+    press(&mut menu, MenuKey::Down);
+    press(&mut menu, MenuKey::Down);
+    assert_eq!(menu.selected(), PauseItem::Quirk(2));
+    assert_eq!(press(&mut menu, MenuKey::Select), None);
+
+    assert_eq!(ipf, 20);
+    assert!(quirks.shift_vx);
+    let lines = menu.lines(&quirks, ipf, &[false; SAVE_SLOTS]);
+    let selected: Vec<&str> = lines
+        .iter()
+        .filter(|line| line.selected)
+        .map(|line| line.text.as_str())
+        .collect();
+    assert_eq!(selected, ["shift_vx: on"]);
+}
+
+#[test]
+fn menu_text_is_drawn_over_a_dimmed_screen() {
+    let width = 200;
+    let mut buffer = vec![0xFFFFFF; width * 100];
+    let lines = PauseMenu::new(0).lines(&Quirks::default(), 10, &[false; SAVE_SLOTS]);
+    draw_menu(&mut buffer, width, 1, "Paused", &lines, "hint");
+    // O fundo fica escuro e sobra texto em branco
+    assert_eq!(buffer[width * 99 + 199], 0x333333);
+    assert!(buffer.contains(&0xFFFFFF));
+
+    // Minúsculas usam o glifo maiúsculo; o que não existe vira '?'
+    assert_eq!(font::glyph('a'), font::glyph('A'));
+    assert_eq!(font::glyph('~'), font::glyph('?'));
+    assert_eq!(font::text_width("ab", 2), 22);
+    assert_eq!(GLYPH_HEIGHT, 7);
+}
+
+#[test]
+fn save_state_is_a_full_copy() {
+    let mut chip8 = chip8::Chip8::with_memory_size(Platform::XoChip.memory_size());
+    chip8.trace = false;
+    chip8.load_bytes(&[0x60, 0x2A, 0x12, 0x02]).unwrap();
+    chip8.run_frame(1).unwrap();
+    let saved = chip8.clone();
+    chip8.run_frame(5).unwrap();
+    chip8.v[0] = 0;
+
+    let chip8 = saved.clone();
+    assert_eq!((chip8.v[0], chip8.pc, chip8.frame_count), (0x2A, 0x202, 1));
+    assert_eq!(chip8.memory.len(), 0x10000);
+}
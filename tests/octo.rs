@@ -0,0 +1,156 @@
+// Assembler do Octo dos cartuchos: os programas são montados e conferidos byte a byte, e os
+// blocos de controle rodam de verdade no interpretador
+
+use chip8::Chip8;
+use chip8::octo::assemble;
+
+fn words(rom: &[u8]) -> Vec<u16> {
+    rom.chunks(2)
+        .map(|pair| u16::from_be_bytes([pair[0], *pair.get(1).unwrap_or(&0)]))
+        .collect()
+}
+
+fn run(source: &str, cycles: usize) -> Chip8 {
+    let mut chip8 = Chip8::new();
+    chip8.trace = false;
+    chip8.load_bytes(&assemble(source).unwrap()).unwrap();
+    for _ in 0..cycles {
+        chip8.cycle().unwrap();
+    }
+    chip8
+}
+
+#[test]
+fn instructions_and_operators() {
+    let source = "
+        : main
+            clear
+            v0 := 5  v1 := v0  va += 0x10  v2 -= 1  v3 := random 0b111
+            v4 |= v5  v4 &= v5  v4 ^= v5  v4 += v5  v4 -= v5  v4 >>= v5  v4 =- v5  v4 <<= v5
+            i := 0x300  i += v2  i := hex v1  i := bighex v1
+            sprite v0 v1 15  bcd v3  save v7  load v7  delay := v1  buzzer := v2
+            v6 := key  v6 := delay
+            return
+    ";
+    assert_eq!(
+        words(&assemble(source).unwrap()),
+        [
+            0x00E0, 0x6005, 0x8100, 0x7A10, 0x72FF, 0xC307, 0x8451, 0x8452, 0x8453, 0x8454, 0x8455,
+            0x8456, 0x8457, 0x845E, 0xA300, 0xF21E, 0xF129, 0xF130, 0xD01F, 0xF333, 0xF755, 0xF765,
+            0xF115, 0xF218, 0xF60A, 0xF607, 0x00EE,
+        ]
+    );
+}
+
+#[test]
+fn labels_work_as_addresses_and_calls() {
+    let source = "
+        :const SPEED 3
+        :alias x v4
+        : main
+            i := sprite     # rótulo ainda não visto
+            x := SPEED
+            draw
+            :call draw
+            jump main
+        : draw
+            sprite x x 1
+            ;
+        : sprite
+            0xFF 0b1 -1
+    ";
+    assert_eq!(
+        assemble(source).unwrap(),
+        [
+            0xA2, 0x0E, 0x64, 0x03, 0x22, 0x0A, 0x22, 0x0A, 0x12, 0x00, 0xD4, 0x41, 0x00, 0xEE,
+            0xFF, 0x01, 0xFF,
+        ]
+    );
+}
+
+#[test]
+fn main_gets_a_jump_when_it_is_not_first() {
+    let source = ": data 1 2\n: main\n  jump main";
+    assert_eq!(words(&assemble(source).unwrap()), [0x1204, 0x0102, 0x1204]);
+    assert!(assemble(": start clear").unwrap_err().contains("no 'main'"));
+}
+
+#[test]
+fn directives_place_and_unpack_data() {
+    let source = "
+        : main
+            :unpack 0xA table
+            :byte 7
+        :org 0x300
+        : table
+            i := long table
+    ";
+    let rom = assemble(source).unwrap();
+    assert_eq!(rom[..5], [0x60, 0xA3, 0x61, 0x00, 0x07]);
+    assert_eq!(rom.len(), 0x104);
+    assert_eq!(rom[0x100..], [0xF0, 0x00, 0x03, 0x00]);
+}
+
+#[test]
+fn if_then_skips_the_next_instruction() {
+    let source = "
+        : main
+            v0 := 3
+            if v0 == 3 then v1 := 1
+            if v0 != 3 then v2 := 1
+            if v0 > 2 then v3 := 1
+            if v0 < 3 then v4 := 1
+            if v0 >= 3 then v5 := 1
+            if v0 <= v1 then v6 := 1
+            if v0 -key then v7 := 1
+    ";
+    // Cada if é uma instrução (as desigualdades são três) mais a pulada ou não
+    let chip8 = run(source, 1 + 2 * 2 + 4 * 4 + 2);
+    assert_eq!(chip8.v[1..8], [1, 0, 1, 0, 1, 0, 1]);
+}
+
+#[test]
+fn blocks_and_loops() {
+    // Soma 1 + 2 + ... + 5 em v1 e escolhe um ramo por paridade
+    let source = "
+        : main
+            v0 := 0
+            loop
+                v0 += 1
+                v1 += v0
+                while v0 != 5
+            again
+            if v1 == 15 begin
+                v2 := 1
+            else
+                v2 := 2
+            end
+            if v1 == 16 begin
+                v3 := 1
+            else
+                v3 := 2
+            end
+        : halt
+            jump halt
+    ";
+    let chip8 = run(source, 100);
+    assert_eq!(chip8.v[..4], [5, 15, 1, 2]);
+}
+
+#[test]
+fn errors_point_at_the_line() {
+    let error = assemble(": main\n  clear\n  :macro twice { clear }").unwrap_err();
+    assert_eq!(
+        error,
+        "Octo source, line 3: unsupported Octo directive ':macro' (export this cartridge from \
+         Octo as a .ch8)"
+    );
+    let error = assemble(": main\n  jump nowhere").unwrap_err();
+    assert_eq!(error, "Octo source, line 2: undefined name 'nowhere'");
+    assert!(
+        assemble(": main\n  v0 := 300")
+            .unwrap_err()
+            .contains("byte")
+    );
+    assert!(assemble(": main\n  loop").unwrap_err().contains("'again'"));
+}
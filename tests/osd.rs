@@ -0,0 +1,116 @@
+use chip8::Chip8;
+use chip8::osd::{
+    FADE_TIME, MESSAGE_TIME, Osd, RunState, Stats, format_rate, opacity, register_lines,
+};
+use std::time::{Duration, Instant};
+
+const WIDTH: usize = 640;
+const HEIGHT: usize = 320;
+
+#[test]
+fn messages_fade_out_and_expire() {
+    let start = Instant::now();
+    let mut osd = Osd::new(start, 2);
+    osd.message("Saved state to slot 1", start);
+    osd.message("shift_vx: on", start + Duration::from_secs(1));
+
+    assert_eq!(opacity(MESSAGE_TIME), 1.0);
+    assert_eq!(opacity(MESSAGE_TIME + FADE_TIME / 2), 0.5);
+    let later = start + MESSAGE_TIME + FADE_TIME / 2;
+    assert_eq!(
+        osd.messages(later),
+        [("Saved state to slot 1", 0.5), ("shift_vx: on", 1.0)]
+    );
+    let gone = start + MESSAGE_TIME + FADE_TIME;
+    assert_eq!(osd.messages(gone), [("shift_vx: on", 1.0)]);
+
+    // Só as mais recentes ficam
+    for i in 0..6 {
+        osd.message(format!("message {}", i), gone);
+    }
+    let texts: Vec<&str> = osd.messages(gone).iter().map(|&(text, _)| text).collect();
+    assert_eq!(texts, ["message 2", "message 3", "message 4", "message 5"]);
+}
+
+#[test]
+fn stats_count_frames_and_instructions_per_second() {
+    let start = Instant::now();
+    let mut stats = Stats::new(start);
+    for frame in 1..=30 {
+        stats.frame(start + Duration::from_millis(frame * 1000 / 60), 700);
+    }
+    assert_eq!(stats.fps.round(), 60.0);
+    assert_eq!(stats.ips.round(), 42000.0);
+
+    assert_eq!(format_rate(540.0), "540");
+    assert_eq!(format_rate(42000.0), "42.0K");
+    assert_eq!(format_rate(2_500_000.0), "2.50M");
+}
+
+#[test]
+fn status_line_shows_counters_and_state() {
+    let mut osd = Osd::new(Instant::now(), 2);
+    assert_eq!(osd.status_line(RunState::Running, 10), "");
+    assert_eq!(osd.status_line(RunState::Turbo, 10), "TURBO");
+    osd.show_stats = true;
+    osd.stats.fps = 59.9;
+    osd.stats.ips = 599.0;
+    assert_eq!(
+        osd.status_line(RunState::Paused, 10),
+        "60 FPS  599 IPS  10 IPF  PAUSED"
+    );
+    assert_eq!(
+        osd.status_line(RunState::Menu, 10),
+        "60 FPS  599 IPS  10 IPF"
+    );
+}
+
+#[test]
+fn register_hud_lists_every_register() {
+    let mut chip8 = Chip8::new();
+    chip8.v[0xA] = 0x5C;
+    chip8.i = 0x123;
+    chip8.delay_timer = 0x3C;
+    let lines = register_lines(&chip8);
+    assert_eq!(lines[0], "PC 0200  I 0123  SP 0  DT 3C  ST 00");
+    assert_eq!(lines[1], "V0 00 V1 00 V2 00 V3 00 V4 00 V5 00 V6 00 V7 00");
+    assert!(lines[2].contains("VA 5C"));
+
+    // O painel fica embaixo à esquerda e some nos menus
+    let drawn = |state| {
+        let mut buffer = vec![0; WIDTH * HEIGHT];
+        let mut osd = Osd::new(Instant::now(), 2);
+        osd.show_registers = true;
+        osd.draw(&mut buffer, WIDTH, Instant::now(), state, &chip8, 10);
+        buffer[WIDTH * HEIGHT / 2..].iter().any(|&pixel| pixel != 0)
+    };
+    assert!(drawn(RunState::Running));
+    assert!(!drawn(RunState::Menu));
+}
+
+#[test]
+fn messages_are_drawn_in_the_top_right_corner() {
+    let now = Instant::now();
+    let mut osd = Osd::new(now, 2);
+    let mut buffer = vec![0x202020; WIDTH * HEIGHT];
+    osd.message("Sound: off", now);
+    osd.draw(
+        &mut buffer,
+        WIDTH,
+        now,
+        RunState::Running,
+        &Chip8::new(),
+        10,
+    );
+
+    let lit: Vec<(usize, usize)> = buffer
+        .iter()
+        .enumerate()
+        .filter(|&(_, &pixel)| pixel == 0xFFFFFF)
+        .map(|(i, _)| (i % WIDTH, i / WIDTH))
+        .collect();
+    assert!(!lit.is_empty());
+    assert!(lit.iter().all(|&(x, y)| x > WIDTH / 2 && y < 20));
+    // O fundo atrás do texto é escurecido
+    assert_ne!(buffer[2 * WIDTH + WIDTH - 3], 0x202020);
+}
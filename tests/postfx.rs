@@ -0,0 +1,98 @@
+use chip8::config::Config;
+use chip8::postfx::{PostFxSettings, PostProcessor};
+
+const W: usize = 40;
+const H: usize = 20;
+
+fn run(settings: PostFxSettings, frame: &mut [u32], scale: usize) {
+    PostProcessor::new(settings).apply(frame, W, H, scale);
+}
+
+#[test]
+fn disabled_pipeline_leaves_frame_untouched() {
+    let mut frame: Vec<u32> = (0..(W * H) as u32).collect();
+    let original = frame.clone();
+    run(PostFxSettings::default(), &mut frame, 4);
+    assert_eq!(frame, original);
+}
+
+#[test]
+fn scanlines_darken_every_other_row() {
+    let mut frame = vec![0xC8C8C8; W * H];
+    let settings = PostFxSettings {
+        scanlines: true,
+        scanline_strength: 0.5,
+        ..PostFxSettings::default()
+    };
+    run(settings, &mut frame, 4);
+    assert!(frame[..W].iter().all(|&p| p == 0xC8C8C8));
+    assert!(frame[W..2 * W].iter().all(|&p| p == 0x646464));
+}
+
+#[test]
+fn pixel_gap_darkens_cell_borders() {
+    let mut frame = vec![0xFFFFFF; W * H];
+    let settings = PostFxSettings {
+        pixel_gap: 1,
+        ..PostFxSettings::default()
+    };
+    run(settings, &mut frame, 4);
+    assert_eq!(frame[0], 0xFFFFFF);
+    assert_ne!(frame[3], 0xFFFFFF);
+    assert_ne!(frame[3 * W], 0xFFFFFF);
+    assert_eq!(frame[4], 0xFFFFFF);
+}
+
+#[test]
+fn bloom_spreads_light_to_neighbours() {
+    let mut frame = vec![0; W * H];
+    frame[10 * W + 20] = 0xFFFFFF;
+    let settings = PostFxSettings {
+        bloom: true,
+        bloom_radius: 2,
+        ..PostFxSettings::default()
+    };
+    run(settings, &mut frame, 4);
+    assert_eq!(frame[10 * W + 20], 0xFFFFFF);
+    assert_ne!(frame[11 * W + 21], 0);
+    assert_eq!(frame[10 * W + 24], 0);
+    assert_eq!(frame[0], 0);
+}
+
+#[test]
+fn curvature_keeps_center_and_blanks_corners() {
+    let mut frame = vec![0xFFFFFF; W * H];
+    frame[(H / 2) * W + W / 2] = 0x123456;
+    let settings = PostFxSettings {
+        curvature: true,
+        curvature_amount: 0.2,
+        ..PostFxSettings::default()
+    };
+    run(settings, &mut frame, 4);
+    assert_eq!(frame[(H / 2) * W + W / 2], 0x123456);
+    assert_eq!(frame[0], 0);
+    assert_eq!(frame[W * H - 1], 0);
+}
+
+#[test]
+fn vignette_darkens_edges_more_than_center() {
+    let mut frame = vec![0xFFFFFF; W * H];
+    let settings = PostFxSettings {
+        vignette: true,
+        ..PostFxSettings::default()
+    };
+    run(settings, &mut frame, 4);
+    let center = frame[(H / 2) * W + W / 2] & 0xFF;
+    let corner = frame[0] & 0xFF;
+    assert!(center > 0xF0, "{:X}", center);
+    assert!(corner < center, "{:X} {:X}", corner, center);
+}
+
+#[test]
+fn postfx_is_read_from_config() {
+    let config = Config::parse("[postfx]\nscanlines = true\nbloom_radius = 6").unwrap();
+    assert!(config.postfx.scanlines);
+    assert_eq!(config.postfx.bloom_radius, 6);
+    assert!(!config.postfx.bloom);
+    assert!(Config::default().postfx == PostFxSettings::default());
+}
@@ -0,0 +1,90 @@
+// Teste diferencial do recompilador: o mesmo programa roda no interpretador (Chip8::run_frame) e
+// nos blocos recompilados, e o estado tem que ser igual no fim de cada quadro. Os programas
+// escrevem de propósito em cima do próprio código para exercitar a invalidação dos blocos
+
+mod common;
+
+use chip8::Chip8;
+use chip8::cpu::KEYPAD_SIZE;
+use chip8::quirks::Quirks;
+use chip8::recompiler::Recompiler;
+use common::{MAX_PROGRAM_LEN, opcode, quirks, same_state};
+use proptest::prelude::*;
+
+fn run(
+    program: &[u16],
+    quirks: Quirks,
+    instructions_per_frame: usize,
+    keys: &[u16],
+) -> Result<(), String> {
+    let bytes: Vec<u8> = program.iter().flat_map(|op| op.to_be_bytes()).collect();
+    let mut interpreted = Chip8::new();
+    interpreted.trace = false;
+    interpreted.quirks = quirks;
+    interpreted.load_bytes(&bytes).unwrap();
+    let mut recompiled = interpreted.clone();
+    let mut recompiler = Recompiler::new(recompiled.memory.len());
+
+    for (frame, &held) in keys.iter().enumerate() {
+        for key in 0..KEYPAD_SIZE {
+            interpreted.set_key(key, held & (1 << key) != 0);
+            recompiled.set_key(key, held & (1 << key) != 0);
+        }
+        let a = interpreted.run_frame(instructions_per_frame);
+        let b = recompiler.run_frame(&mut recompiled, instructions_per_frame);
+        if a != b {
+            return Err(format!(
+                "frame {}: interpreter={:?} recompiler={:?}",
+                frame, a, b
+            ));
+        }
+        same_state("recompiler", &interpreted, &recompiled)
+            .map_err(|e| format!("frame {}: {}", frame, e))?;
+        if a.is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(512))]
+
+    #[test]
+    fn recompiler_matches_interpreter(
+        program in prop::collection::vec(opcode(), 1..MAX_PROGRAM_LEN),
+        quirks in quirks(),
+        instructions_per_frame in 1usize..40,
+        keys in prop::collection::vec(any::<u16>(), 1..12),
+    ) {
+        if let Err(report) = run(&program, quirks, instructions_per_frame, &keys) {
+            prop_assert!(false, "{}", report);
+        }
+    }
+}
+
+#[test]
+fn blocks_are_reused_until_code_is_overwritten() {
+    // 0x200: ADD V0, 1; SE V0, 0x10; JP 0x200
+    // 0x206: LD I, 0x200; LD V0, 0x71; LD V1, 0x02; LD [I], V1 -> 0x200 vira ADD V1, 2
+    // 0x20E: JP 0x200
+    let program: [u16; 8] = [
+        0x7001, 0x3010, 0x1200, 0xA200, 0x6071, 0x6102, 0xF155, 0x1200,
+    ];
+    let bytes: Vec<u8> = program.iter().flat_map(|op| op.to_be_bytes()).collect();
+    let mut chip8 = Chip8::new();
+    chip8.trace = false;
+    chip8.load_bytes(&bytes).unwrap();
+    let mut recompiler = Recompiler::new(chip8.memory.len());
+
+    recompiler.run_frame(&mut chip8, 3 * 15).unwrap();
+    assert_eq!(chip8.v[0], 0x0F);
+    // Dois blocos: 0x200-0x202 (que termina no SE) e o JP em 0x204
+    assert_eq!(recompiler.translations, 2);
+
+    // ADD e SE; os 3 LD e o LD [I]; o JP; e o ADD V1, 2 que acabou de ser escrito
+    recompiler.run_frame(&mut chip8, 2 + 4 + 1 + 1).unwrap();
+    assert_eq!(chip8.memory[0x200..0x202], [0x71, 0x02]);
+    assert_eq!(chip8.v[1], 0x04);
+    assert_eq!(chip8.frame_count, 2);
+}
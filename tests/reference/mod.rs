@@ -0,0 +1,438 @@
+// Modelo de referência do conjunto de instruções CHIP-8.
+//
+// Propositalmente simples: decodifica pelos quatro nibbles, usa uma pilha em Vec e desenha pixel
+// por pixel. Não tenta ser rápido, só fácil de conferir contra a especificação. Endereços dão a
+// volta nos 4K de memória e as teclas usam só o nibble baixo de Vx; estouro de pilha retorna
+// `Fault`, que o core precisa reportar como erro no mesmo passo.
+
+use chip8::cpu::{AUDIO_PATTERN_SIZE, KEYPAD_SIZE, REGISTER_COUNT, STACK_SIZE};
+use chip8::{Chip8, Quirks, VIDEO_HEIGHT, VIDEO_WIDTH};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    StackOverflow,
+    StackUnderflow,
+}
+
+#[derive(Clone)]
+pub struct Reference {
+    pub memory: Vec<u8>,
+    pub v: [u8; REGISTER_COUNT],
+    pub i: u16,
+    pub pc: u16,
+    pub stack: Vec<u16>,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub audio_pattern: Option<[u8; AUDIO_PATTERN_SIZE]>,
+    pub pitch: u8,
+    pub keypad: [bool; KEYPAD_SIZE],
+    pub quirks: Quirks,
+    pub key_wait: Option<u8>,
+    pub video: Vec<bool>,
+}
+
+impl Reference {
+    pub fn from_chip8(chip8: &Chip8) -> Self {
+        Reference {
+            memory: chip8.memory.to_vec(),
+            v: chip8.v,
+            i: chip8.i,
+            pc: chip8.pc,
+            stack: chip8.stack[..chip8.sp as usize].to_vec(),
+            delay_timer: chip8.delay_timer,
+            sound_timer: chip8.sound_timer,
+            audio_pattern: chip8.audio_pattern,
+            pitch: chip8.pitch,
+            keypad: chip8.keypad,
+            quirks: chip8.quirks,
+            key_wait: chip8.key_wait,
+            video: chip8.pixels().collect(),
+        }
+    }
+
+    fn read(&self, addr: usize) -> u8 {
+        self.memory[addr % self.memory.len()]
+    }
+
+    fn write(&mut self, addr: usize, value: u8) {
+        let len = self.memory.len();
+        self.memory[addr % len] = value;
+    }
+
+    fn key(&self, x: usize) -> bool {
+        self.keypad[self.v[x] as usize % KEYPAD_SIZE]
+    }
+
+    // `random` é o byte sorteado pelo Cxkk. O harness passa o valor que o core produziu para
+    // conseguir comparar o resto do estado mesmo com uma instrução não determinística.
+    pub fn step(&mut self, random: u8) -> Result<(), Fault> {
+        let pc = (self.pc & 0xFFF) as usize;
+        let opcode = ((self.read(pc) as u16) << 8) | self.read(pc + 1) as u16;
+        self.pc = pc as u16 + 2;
+
+        let n1 = (opcode >> 12) as u8;
+        let x = ((opcode >> 8) & 0xF) as usize;
+        let y = ((opcode >> 4) & 0xF) as usize;
+        let n = (opcode & 0xF) as u8;
+        let kk = (opcode & 0xFF) as u8;
+        let nnn = opcode & 0xFFF;
+
+        match (n1, x, y, n) {
+            (0x0, 0x0, 0xE, 0x0) => self.video.iter_mut().for_each(|p| *p = false),
+            (0x0, 0x0, 0xE, 0xE) => {
+                self.pc = self.stack.pop().ok_or(Fault::StackUnderflow)? + 2;
+            }
+            (0x0, _, _, _) => {}
+            (0x1, _, _, _) => self.pc = nnn,
+            (0x2, _, _, _) => {
+                if self.stack.len() == STACK_SIZE {
+                    return Err(Fault::StackOverflow);
+                }
+                self.stack.push(self.pc - 2);
+                self.pc = nnn;
+            }
+            (0x3, _, _, _) => self.skip_if(self.v[x] == kk),
+            (0x4, _, _, _) => self.skip_if(self.v[x] != kk),
+            (0x5, _, _, 0x0) => self.skip_if(self.v[x] == self.v[y]),
+            (0x6, _, _, _) => self.v[x] = kk,
+            (0x7, _, _, _) => self.v[x] = self.v[x].wrapping_add(kk),
+            (0x8, _, _, 0x0) => self.v[x] = self.v[y],
+            (0x8, _, _, 0x1) => self.logic(x, self.v[x] | self.v[y]),
+            (0x8, _, _, 0x2) => self.logic(x, self.v[x] & self.v[y]),
+            (0x8, _, _, 0x3) => self.logic(x, self.v[x] ^ self.v[y]),
+            (0x8, _, _, 0x4) => {
+                let sum = self.v[x] as u16 + self.v[y] as u16;
+                self.set_with_flag(x, sum as u8, (sum > 0xFF) as u8);
+            }
+            (0x8, _, _, 0x5) => {
+                let flag = (self.v[x] >= self.v[y]) as u8;
+                self.set_with_flag(x, self.v[x].wrapping_sub(self.v[y]), flag);
+            }
+            (0x8, _, _, 0x6) => {
+                let source = if self.quirks.shift_vx {
+                    self.v[x]
+                } else {
+                    self.v[y]
+                };
+                self.set_with_flag(x, source >> 1, source & 1);
+            }
+            (0x8, _, _, 0x7) => {
+                let flag = (self.v[y] >= self.v[x]) as u8;
+                self.set_with_flag(x, self.v[y].wrapping_sub(self.v[x]), flag);
+            }
+            (0x8, _, _, 0xE) => {
+                let source = if self.quirks.shift_vx {
+                    self.v[x]
+                } else {
+                    self.v[y]
+                };
+                self.set_with_flag(x, source << 1, source >> 7);
+            }
+            (0x9, _, _, 0x0) => self.skip_if(self.v[x] != self.v[y]),
+            (0xA, _, _, _) => self.i = nnn,
+            (0xB, _, _, _) => {
+                let register = if self.quirks.jump_vx { x } else { 0 };
+                self.pc = (nnn + self.v[register] as u16) & 0xFFF;
+            }
+            (0xC, _, _, _) => self.v[x] = random & kk,
+            (0xD, _, _, _) => self.draw(self.v[x] as usize, self.v[y] as usize, n as usize),
+            (0xE, _, 0x9, 0xE) => self.skip_if(self.key(x)),
+            (0xE, _, 0xA, 0x1) => self.skip_if(!self.key(x)),
+            (0xF, _, 0x0, 0x7) => self.v[x] = self.delay_timer,
+            // Com key_wait_release a primeira tecla pressionada é lembrada e só vai para Vx quando
+            // for solta; até lá o pc fica parado
+            (0xF, _, 0x0, 0xA) => {
+                let pressed = self.keypad.iter().position(|&k| k).map(|key| key as u8);
+                if !self.quirks.key_wait_release {
+                    match pressed {
+                        Some(key) => self.v[x] = key,
+                        None => self.pc = pc as u16,
+                    }
+                } else if let Some(key) = self.key_wait {
+                    if self.keypad[key as usize] {
+                        self.pc = pc as u16;
+                    } else {
+                        self.v[x] = key;
+                        self.key_wait = None;
+                    }
+                } else {
+                    self.key_wait = pressed;
+                    self.pc = pc as u16;
+                }
+            }
+            (0xF, _, 0x1, 0x5) => self.delay_timer = self.v[x],
+            (0xF, _, 0x1, 0x8) => self.sound_timer = self.v[x],
+            (0xF, _, 0x1, 0xE) => self.i = self.i.wrapping_add(self.v[x] as u16),
+            (0xF, _, 0x2, 0x9) => self.i = self.v[x] as u16 * 5,
+            (0xF, _, 0x3, 0x3) => {
+                let base = self.i as usize;
+                let value = self.v[x];
+                self.write(base, value / 100);
+                self.write(base + 1, value / 10 % 10);
+                self.write(base + 2, value % 10);
+            }
+            (0xF, _, 0x5, 0x5) => {
+                for r in 0..=x {
+                    self.write(self.i as usize + r, self.v[r]);
+                }
+                self.increment_i(x);
+            }
+            (0xF, _, 0x6, 0x5) => {
+                for r in 0..=x {
+                    self.v[r] = self.read(self.i as usize + r);
+                }
+                self.increment_i(x);
+            }
+            // Opcodes desconhecidos são ignorados
+            _ => {}
+        }
+
+        // O pc sempre fica dentro dos 12 bits de endereço
+        self.pc &= 0xFFF;
+        Ok(())
+    }
+
+    fn skip_if(&mut self, condition: bool) {
+        if condition {
+            self.pc += 2;
+        }
+    }
+
+    fn logic(&mut self, x: usize, value: u8) {
+        self.v[x] = value;
+        if self.quirks.vf_reset {
+            self.v[0xF] = 0;
+        }
+    }
+
+    fn increment_i(&mut self, x: usize) {
+        if self.quirks.memory_increment_i {
+            self.i = self.i.wrapping_add(x as u16 + 1);
+        }
+    }
+
+    fn set_with_flag(&mut self, x: usize, value: u8, flag: u8) {
+        self.v[x] = value;
+        self.v[0xF] = flag;
+    }
+
+    fn draw(&mut self, x: usize, y: usize, height: usize) {
+        let (x, y) = (x % VIDEO_WIDTH, y % VIDEO_HEIGHT);
+        self.v[0xF] = 0;
+        for row in 0..height {
+            let sprite = self.read(self.i as usize + row);
+            for col in 0..8 {
+                if sprite & (0x80 >> col) == 0 {
+                    continue;
+                }
+                let clipped = x + col >= VIDEO_WIDTH || y + row >= VIDEO_HEIGHT;
+                if clipped && self.quirks.clip_sprites {
+                    continue;
+                }
+                let px = (x + col) % VIDEO_WIDTH;
+                let py = (y + row) % VIDEO_HEIGHT;
+                let index = py * VIDEO_WIDTH + px;
+                if self.video[index] {
+                    self.v[0xF] = 1;
+                }
+                self.video[index] = !self.video[index];
+            }
+        }
+    }
+}
+
+// Primeira diferença encontrada entre o modelo e o core
+#[derive(Debug)]
+pub enum Divergence {
+    Pc {
+        reference: u16,
+        core: u16,
+    },
+    I {
+        reference: u16,
+        core: u16,
+    },
+    Register {
+        index: usize,
+        reference: u8,
+        core: u8,
+    },
+    Stack {
+        reference: Vec<u16>,
+        core: Vec<u16>,
+    },
+    DelayTimer {
+        reference: u8,
+        core: u8,
+    },
+    SoundTimer {
+        reference: u8,
+        core: u8,
+    },
+    AudioPattern {
+        reference: Option<[u8; AUDIO_PATTERN_SIZE]>,
+        core: Option<[u8; AUDIO_PATTERN_SIZE]>,
+    },
+    Pitch {
+        reference: u8,
+        core: u8,
+    },
+    KeyWait {
+        reference: Option<u8>,
+        core: Option<u8>,
+    },
+    Memory {
+        addr: usize,
+        reference: u8,
+        core: u8,
+    },
+    Pixel {
+        x: usize,
+        y: usize,
+        reference: bool,
+        core: bool,
+    },
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Divergence::Pc { reference, core } => {
+                write!(f, "PC: reference={:#05X} core={:#05X}", reference, core)
+            }
+            Divergence::I { reference, core } => {
+                write!(f, "I: reference={:#05X} core={:#05X}", reference, core)
+            }
+            Divergence::Register {
+                index,
+                reference,
+                core,
+            } => write!(
+                f,
+                "V{:X}: reference={:#04X} core={:#04X}",
+                index, reference, core
+            ),
+            Divergence::Stack { reference, core } => {
+                write!(f, "stack: reference={:X?} core={:X?}", reference, core)
+            }
+            Divergence::DelayTimer { reference, core } => {
+                write!(f, "delay timer: reference={} core={}", reference, core)
+            }
+            Divergence::SoundTimer { reference, core } => {
+                write!(f, "sound timer: reference={} core={}", reference, core)
+            }
+            Divergence::AudioPattern { reference, core } => {
+                write!(
+                    f,
+                    "audio pattern: reference={:02X?} core={:02X?}",
+                    reference, core
+                )
+            }
+            Divergence::Pitch { reference, core } => {
+                write!(f, "pitch: reference={} core={}", reference, core)
+            }
+            Divergence::KeyWait { reference, core } => {
+                write!(f, "key wait: reference={:?} core={:?}", reference, core)
+            }
+            Divergence::Memory {
+                addr,
+                reference,
+                core,
+            } => write!(
+                f,
+                "memory[{:#05X}]: reference={:#04X} core={:#04X}",
+                addr, reference, core
+            ),
+            Divergence::Pixel {
+                x,
+                y,
+                reference,
+                core,
+            } => write!(
+                f,
+                "pixel ({}, {}): reference={} core={}",
+                x, y, reference, core
+            ),
+        }
+    }
+}
+
+pub fn compare(reference: &Reference, core: &Chip8) -> Option<Divergence> {
+    for index in 0..REGISTER_COUNT {
+        if reference.v[index] != core.v[index] {
+            return Some(Divergence::Register {
+                index,
+                reference: reference.v[index],
+                core: core.v[index],
+            });
+        }
+    }
+    if reference.pc != core.pc {
+        return Some(Divergence::Pc {
+            reference: reference.pc,
+            core: core.pc,
+        });
+    }
+    if reference.i != core.i {
+        return Some(Divergence::I {
+            reference: reference.i,
+            core: core.i,
+        });
+    }
+    let core_stack = &core.stack[..(core.sp as usize).min(STACK_SIZE)];
+    if reference.stack != core_stack {
+        return Some(Divergence::Stack {
+            reference: reference.stack.clone(),
+            core: core_stack.to_vec(),
+        });
+    }
+    if reference.delay_timer != core.delay_timer {
+        return Some(Divergence::DelayTimer {
+            reference: reference.delay_timer,
+            core: core.delay_timer,
+        });
+    }
+    if reference.sound_timer != core.sound_timer {
+        return Some(Divergence::SoundTimer {
+            reference: reference.sound_timer,
+            core: core.sound_timer,
+        });
+    }
+    if reference.audio_pattern != core.audio_pattern {
+        return Some(Divergence::AudioPattern {
+            reference: reference.audio_pattern,
+            core: core.audio_pattern,
+        });
+    }
+    if reference.pitch != core.pitch {
+        return Some(Divergence::Pitch {
+            reference: reference.pitch,
+            core: core.pitch,
+        });
+    }
+    if reference.key_wait != core.key_wait {
+        return Some(Divergence::KeyWait {
+            reference: reference.key_wait,
+            core: core.key_wait,
+        });
+    }
+    if let Some(addr) = (0..reference.memory.len()).find(|&a| reference.memory[a] != core.memory[a])
+    {
+        return Some(Divergence::Memory {
+            addr,
+            reference: reference.memory[addr],
+            core: core.memory[addr],
+        });
+    }
+    let pixels: Vec<bool> = core.pixels().collect();
+    if let Some(index) = (0..reference.video.len()).find(|&p| reference.video[p] != pixels[p]) {
+        return Some(Divergence::Pixel {
+            x: index % VIDEO_WIDTH,
+            y: index / VIDEO_WIDTH,
+            reference: reference.video[index],
+            core: pixels[index],
+        });
+    }
+    None
+}
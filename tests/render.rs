@@ -0,0 +1,139 @@
+use chip8::config::Config;
+use chip8::palette::{Palette, PaletteError, parse_color};
+use chip8::render::{PixelStyle, scale_buffer, scale_buffer_into, scale_rows_into};
+
+#[test]
+fn presets_are_found_by_name() {
+    for name in Palette::preset_names() {
+        assert!(Palette::preset(name).is_some(), "{}", name);
+    }
+    assert_eq!(Palette::preset("AMBER"), Palette::preset("amber"));
+    assert_eq!(Palette::default().colors[..2], [0x000000, 0xFFFFFF]);
+    assert!(Palette::preset("nope").is_none());
+}
+
+#[test]
+fn custom_palettes_accept_two_or_four_colors() {
+    let two = Palette::from_hex(&["#000000".into(), "#FFFFFF".into()]).unwrap();
+    assert_eq!(two.colors, [0x000000, 0xFFFFFF, 0xA8A8A8, 0x545454]);
+
+    let four: Vec<String> = ["#010203", "040506", "#070809", "#0A0B0C"]
+        .iter()
+        .map(|c| c.to_string())
+        .collect();
+    let four = Palette::from_hex(&four).unwrap();
+    assert_eq!(four.colors, [0x010203, 0x040506, 0x070809, 0x0A0B0C]);
+
+    assert_eq!(
+        Palette::from_hex(&["#000000".into()]),
+        Err(PaletteError::WrongColorCount(1))
+    );
+    assert_eq!(
+        parse_color("#GG0000"),
+        Err(PaletteError::InvalidColor("#GG0000".into()))
+    );
+}
+
+#[test]
+fn colorize_maps_plane_indexes() {
+    let palette = Palette::preset("hotdog").unwrap();
+    let mut out = [0; 4];
+    palette.colorize([0, 1, 2, 3], &mut out);
+    assert_eq!(out, [0x000000, 0xFF0000, 0xFFFF00, 0xFFFFFF]);
+}
+
+#[test]
+fn config_file_defines_palettes_and_style() {
+    let config = Config::parse(
+        r##"
+        [display]
+        palette = "sunset"
+        pixel_style = "rounded"
+
+        [palettes.sunset]
+        colors = ["#1A0F2E", "#FF7F50", "#FFD700", "#FFFFFF"]
+        "##,
+    )
+    .unwrap();
+    let palette = config.display_palette().unwrap();
+    assert_eq!(palette.colors, [0x1A0F2E, 0xFF7F50, 0xFFD700, 0xFFFFFF]);
+    assert_eq!(config.pixel_style().unwrap(), PixelStyle::Rounded);
+    // Presets continuam disponíveis
+    assert_eq!(
+        config.palette("green").unwrap(),
+        Palette::preset("green").unwrap()
+    );
+    assert!(config.palette("missing").is_err());
+
+    assert!(
+        Config::parse("[display]\npixel_style = \"blurry\"")
+            .unwrap()
+            .pixel_style()
+            .is_err()
+    );
+    assert!(Config::parse("[display]\nunknown = 1").is_err());
+}
+
+const FG: u32 = 0xFFFFFF;
+const BG: u32 = 0x000000;
+
+#[test]
+fn solid_style_replicates_pixels() {
+    let palette = Palette::default();
+    let scaled = scale_buffer(&[FG, BG], 2, 1, 3, PixelStyle::Solid, &palette);
+    assert_eq!(scaled.len(), 18);
+    for row in scaled.chunks(6) {
+        assert_eq!(row, [FG, FG, FG, BG, BG, BG]);
+    }
+}
+
+#[test]
+fn grid_style_draws_lines_between_pixels() {
+    let palette = Palette::default();
+    let scaled = scale_buffer(&[FG, BG], 2, 1, 4, PixelStyle::Grid, &palette);
+    let grid = 0x333333;
+    assert_eq!(&scaled[0..8], [FG, FG, FG, grid, BG, BG, BG, grid]);
+    assert_eq!(&scaled[24..32], [grid; 8]);
+}
+
+#[test]
+fn rounded_style_clears_corners() {
+    let palette = Palette::default();
+    let scaled = scale_buffer(&[FG], 1, 1, 9, PixelStyle::Rounded, &palette);
+    let at = |x: usize, y: usize| scaled[y * 9 + x];
+    assert_eq!(at(0, 0), BG);
+    assert_eq!(at(8, 8), BG);
+    assert_eq!(at(4, 4), FG);
+    assert_eq!(at(4, 0), FG);
+    assert_eq!(at(0, 4), FG);
+}
+
+#[test]
+fn scale_into_reuses_the_buffer() {
+    let palette = Palette::default();
+    // Um buffer que sobrou de uma escala maior é reduzido e reescrito por inteiro
+    let mut scaled = vec![0x123456; 200];
+    scale_buffer_into(&[FG, BG], 2, 1, 4, PixelStyle::Grid, &palette, &mut scaled);
+    assert_eq!(
+        scaled,
+        scale_buffer(&[FG, BG], 2, 1, 4, PixelStyle::Grid, &palette)
+    );
+}
+
+#[test]
+fn only_marked_rows_are_scaled() {
+    let palette = Palette::default();
+    let mut scaled = scale_buffer(&[BG, BG, BG], 1, 3, 2, PixelStyle::Solid, &palette);
+    // Linha 1 marcada: a 2 mudou no buffer mas continua como estava
+    scale_rows_into(
+        &[FG, FG, FG],
+        1,
+        3,
+        2,
+        PixelStyle::Solid,
+        &palette,
+        0b010,
+        &mut scaled,
+    );
+    assert_eq!(scaled, [BG, BG, BG, BG, FG, FG, FG, FG, BG, BG, BG, BG]);
+}
@@ -0,0 +1,104 @@
+// ROMs arbitrárias não podem derrubar o interpretador: o ciclo retorna `Chip8Error` ou segue em
+// frente, mas nunca dá panic. É a mesma propriedade que o alvo em fuzz/ verifica com o libFuzzer.
+
+use chip8::cpu::{MEMORY_SIZE, STACK_SIZE, START_ADDRESS};
+use chip8::platform::Platform;
+use chip8::{Chip8, Chip8Error};
+use proptest::prelude::*;
+
+fn load(program: &[u8]) -> Chip8 {
+    let mut chip8 = Chip8::new();
+    chip8.trace = false;
+    chip8.load_bytes(program).unwrap();
+    chip8
+}
+
+#[test]
+fn call_with_full_stack_is_an_error() {
+    // 0x200: CALL 0x200 chama a si mesmo até encher a pilha
+    let mut chip8 = load(&[0x22, 0x00]);
+    for _ in 0..STACK_SIZE {
+        chip8.cycle().unwrap();
+    }
+    assert_eq!(chip8.cycle(), Err(Chip8Error::StackOverflow { pc: 0x200 }));
+}
+
+#[test]
+fn return_with_empty_stack_is_an_error() {
+    let mut chip8 = load(&[0x00, 0xEE]);
+    assert_eq!(chip8.cycle(), Err(Chip8Error::StackUnderflow { pc: 0x200 }));
+}
+
+#[test]
+fn i_relative_access_wraps_around_memory() {
+    // LD I, 0xFFF; LD V0, 0xFE; LD B, V0; LD [I], V2; LD V2, [I]; DRW V0, V0, 15
+    let mut chip8 = load(&[
+        0xAF, 0xFF, 0x60, 0xFE, 0xF0, 0x33, 0xF2, 0x55, 0xF2, 0x65, 0xD0, 0x0F,
+    ]);
+    for _ in 0..6 {
+        chip8.cycle().unwrap();
+    }
+    assert_eq!(chip8.memory[0xFFF], 0xFE);
+    assert_eq!(chip8.memory[0x000], 0x00);
+}
+
+#[test]
+fn fetch_at_end_of_memory_wraps() {
+    let mut chip8 = load(&[0x1F, 0xFF]);
+    chip8.memory[MEMORY_SIZE - 1] = 0x60;
+    chip8.memory[0] = 0x42;
+    chip8.cycle().unwrap();
+    chip8.cycle().unwrap();
+    assert_eq!(chip8.v[0], 0x42);
+    assert_eq!(chip8.pc, 0x001);
+}
+
+#[test]
+fn key_skip_uses_low_nibble() {
+    // LD V0, 0xF3; SKP V0
+    let mut chip8 = load(&[0x60, 0xF3, 0xE0, 0x9E]);
+    chip8.keypad[0x3] = true;
+    chip8.cycle().unwrap();
+    chip8.cycle().unwrap();
+    assert_eq!(chip8.pc, 0x206);
+}
+
+#[test]
+fn trace_is_off_by_default() {
+    // A janela, o modo sem janela e o --dump não podem imprimir uma linha por instrução
+    assert!(!Chip8::new().trace);
+}
+
+#[test]
+fn oversized_rom_is_rejected() {
+    let mut chip8 = Chip8::new();
+    let rom = vec![0; MEMORY_SIZE - START_ADDRESS + 1];
+    assert_eq!(
+        chip8.load_bytes(&rom),
+        Err(Chip8Error::RomTooLarge {
+            size: rom.len(),
+            max: MEMORY_SIZE - START_ADDRESS,
+        })
+    );
+}
+
+proptest! {
+    #[test]
+    fn arbitrary_rom_never_panics(
+        platform in prop::sample::select(Platform::ALL.to_vec()),
+        rom in prop::collection::vec(any::<u8>(), 0..256),
+        keypad in any::<[bool; 16]>(),
+    ) {
+        // Cada variante tem a própria tabela de opcodes e o próprio modelo de tela
+        let mut chip8 = platform.machine();
+        chip8.load_bytes_at(&rom, platform.start_address()).unwrap();
+        chip8.keypad = keypad;
+        chip8.second_keypad = keypad;
+        for _ in 0..2_000 {
+            if chip8.cycle().is_err() {
+                break;
+            }
+            chip8.tick_timers();
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use chip8::Chip8;
+use chip8::config::Config;
+use chip8::platform::Platform;
+use chip8::romdb::{RomDatabase, sha1_hex};
+
+const IBM_LOGO: &str = "1ba58656810b67fd131eb9af3e3987863bf26c90";
+
+fn load(program: &[u8], platform: Platform) -> Chip8 {
+    let mut chip8 = Chip8::new();
+    chip8.trace = false;
+    chip8.quirks = platform.quirks();
+    chip8.load_bytes(program).unwrap();
+    chip8
+}
+
+#[test]
+fn sha1_matches_sha1sum() {
+    assert_eq!(sha1_hex(b"abc"), "a9993e364706816aba3e25717850c26c9cd0d89d");
+    let rom = std::fs::read("roms/IBM Logo.ch8").unwrap();
+    assert_eq!(sha1_hex(&rom), IBM_LOGO);
+}
+
+#[test]
+fn builtin_database_knows_the_bundled_rom() {
+    let rom = std::fs::read("roms/IBM Logo.ch8").unwrap();
+    let database = RomDatabase::builtin();
+    let entry = database.lookup(&rom).unwrap();
+    assert_eq!(entry.display_title().as_deref(), Some("IBM Logo"));
+    assert_eq!(entry.platform(), Ok(Some(Platform::Chip8)));
+    assert!(database.lookup(&[0x12, 0x00]).is_none());
+}
+
+#[test]
+fn config_entries_replace_builtin_ones() {
+    // Hash em maiúsculas também vale
+    let config = Config::parse(&format!(
+        r##"
+        [roms."{}"]
+        title = "My Logo"
+        author = "Me"
+        year = 2024
+        platform = "schip"
+        instructions_per_frame = 50
+        colors = ["#000000", "#00FF00"]
+        keys = {{ "5" = ["Up"] }}
+        quirks = {{ clip_sprites = false }}
+        "##,
+        IBM_LOGO.to_uppercase()
+    ))
+    .unwrap();
+    let database = config.rom_database();
+    let entry = database.get(IBM_LOGO).unwrap();
+    assert_eq!(entry.display_title().as_deref(), Some("My Logo (Me, 2024)"));
+    assert_eq!(entry.platform(), Ok(Some(Platform::SuperChip)));
+    assert_eq!(entry.instructions_per_frame, Some(50));
+    assert_eq!(entry.colors().unwrap().unwrap().colors[1], 0x00FF00);
+
+    let mut keymap = config.keymap(None).unwrap();
+    entry.apply_keys(&mut keymap).unwrap();
+    assert_eq!(keymap.get("Up"), Some(0x5));
+    assert_eq!(keymap.get("W"), None);
+}
+
+#[test]
+fn command_line_keymap_overrides_the_entry() {
+    let config = Config::parse(&format!(
+        r##"
+        [roms."{}"]
+        keymap = "azerty"
+        keys = {{ "5" = ["Up"] }}
+        "##,
+        IBM_LOGO
+    ))
+    .unwrap();
+    let database = config.rom_database();
+    let entry = database.get(IBM_LOGO).unwrap();
+
+    // Sem --keymap vale a entrada: AZERTY com a tecla trocada
+    let keymap = config.rom_keymap(None, entry).unwrap();
+    assert_eq!(keymap.get("A"), Some(0x4));
+    assert_eq!(keymap.get("Up"), Some(0x5));
+
+    // Com --keymap nem o preset nem as teclas da entrada valem
+    let keymap = config.rom_keymap(Some("qwerty"), entry).unwrap();
+    assert_eq!(keymap.get("Q"), Some(0x4));
+    assert_eq!(keymap.get("W"), Some(0x5));
+    assert_eq!(keymap.get("Up"), None);
+}
+
+#[test]
+fn invalid_entries_are_reported() {
+    assert!(Config::parse("[roms.abc]\nspeed = 3").is_err());
+    let config = Config::parse("[roms.abc]\nplatform = \"gameboy\"").unwrap();
+    assert!(
+        config
+            .rom_database()
+            .get("abc")
+            .unwrap()
+            .platform()
+            .is_err()
+    );
+}
+
+#[test]
+fn quirk_overrides_apply_on_top_of_the_platform() {
+    let config = Config::parse("[quirks]\nshift_vx = false\n").unwrap();
+    let mut quirks = Platform::SuperChip.quirks();
+    config.quirks.apply(&mut quirks);
+    assert!(!quirks.shift_vx);
+    // O resto continua como no SCHIP
+    assert!(quirks.jump_vx && !quirks.vf_reset);
+
+    assert_eq!("super-chip".parse(), Ok(Platform::SuperChip));
+    assert_eq!("XO-CHIP".parse(), Ok(Platform::XoChip));
+    assert!("chip48".parse::<Platform>().is_err());
+}
+
+#[test]
+fn platform_quirks_change_execution() {
+    // 6105 6203 8126: V1 = 5, V2 = 3, SHR
+    let shift = [0x61, 0x05, 0x62, 0x03, 0x81, 0x26];
+    let mut vip = load(&shift, Platform::Chip8);
+    vip.run_frame(3).unwrap();
+    assert_eq!((vip.v[1], vip.v[0xF]), (1, 1));
+    let mut schip = load(&shift, Platform::SuperChip);
+    schip.run_frame(3).unwrap();
+    assert_eq!((schip.v[1], schip.v[0xF]), (2, 1));
+
+    // 6F01 6103 8111: VF = 1 antes do OR
+    let or = [0x6F, 0x01, 0x61, 0x03, 0x81, 0x11];
+    let mut vip = load(&or, Platform::Chip8);
+    vip.run_frame(3).unwrap();
+    assert_eq!(vip.v[0xF], 0);
+    let mut xo = load(&or, Platform::XoChip);
+    xo.run_frame(3).unwrap();
+    assert_eq!(xo.v[0xF], 1);
+
+    // 603C A208 D011 1206 + sprite 0xFF: 8 pixels a partir de x = 60
+    let draw = [0x60, 0x3C, 0xA2, 0x08, 0xD0, 0x11, 0x12, 0x06, 0xFF];
+    let mut vip = load(&draw, Platform::Chip8);
+    vip.run_frame(3).unwrap();
+    assert!(vip.pixel(63, 0) && !vip.pixel(0, 0));
+    let mut xo = load(&draw, Platform::XoChip);
+    xo.run_frame(3).unwrap();
+    assert!(xo.pixel(63, 0) && xo.pixel(3, 0));
+}
+
+#[test]
+fn example_config_parses() {
+    let config = Config::load("chip8.example.toml").unwrap();
+    let entry = config.rom_database().get(IBM_LOGO).cloned().unwrap();
+    assert!(entry.colors().unwrap().is_none());
+    assert_eq!(entry.palette.as_deref(), Some("green"));
+    config.palette("green").unwrap();
+}
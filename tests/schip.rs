@@ -0,0 +1,244 @@
+// SUPER-CHIP e XO-CHIP: alta resolução, sprites de 16x16, rolagem, fonte grande, flags, o I de 16
+// bits do F000 NNNN, planos e a memória de 64K
+
+use chip8::Chip8;
+use chip8::cpu::BIG_FONT_ADDRESS;
+use chip8::instruction::{Instruction, InstructionSet};
+use chip8::platform::Platform;
+use chip8::recompiler::Recompiler;
+
+fn machine(platform: Platform, program: &[u16]) -> Chip8 {
+    let bytes: Vec<u8> = program.iter().flat_map(|op| op.to_be_bytes()).collect();
+    let mut chip8 = platform.machine();
+    chip8
+        .load_bytes_at(&bytes, platform.start_address())
+        .unwrap();
+    chip8
+}
+
+fn run(chip8: &mut Chip8, cycles: usize) {
+    for _ in 0..cycles {
+        chip8.cycle().unwrap();
+    }
+}
+
+// Linha y da tela como uma string de '#' e '.', só as `width` primeiras colunas
+fn row(chip8: &Chip8, y: usize, width: usize) -> String {
+    (0..width)
+        .map(|x| if chip8.pixel(x, y) { '#' } else { '.' })
+        .collect()
+}
+
+#[test]
+fn superchip_and_xochip_have_their_own_decoder_tables() {
+    let decode = |set: InstructionSet, opcode| set.decode(opcode);
+    assert_eq!(
+        decode(InstructionSet::SuperChip, 0x00FF),
+        Instruction::HighRes
+    );
+    assert_eq!(
+        decode(InstructionSet::SuperChip, 0x00C3),
+        Instruction::ScrollDown(3)
+    );
+    assert_eq!(
+        decode(InstructionSet::SuperChip, 0xD120),
+        Instruction::DrawLarge(1, 2)
+    );
+    assert_eq!(
+        decode(InstructionSet::SuperChip, 0xF530),
+        Instruction::BigFont(5)
+    );
+    assert_eq!(decode(InstructionSet::SuperChip, 0x00FD), Instruction::Halt);
+    assert_eq!(
+        decode(InstructionSet::Chip8, 0x00FF),
+        Instruction::Unknown(0x00FF)
+    );
+    // O XO-CHIP tem tudo do SUPER-CHIP mais os próprios
+    assert_eq!(decode(InstructionSet::XoChip, 0x00FF), Instruction::HighRes);
+    assert_eq!(
+        decode(InstructionSet::XoChip, 0xF000),
+        Instruction::LongLoadI
+    );
+    assert_eq!(
+        decode(InstructionSet::XoChip, 0xF201),
+        Instruction::Plane(2)
+    );
+    assert_eq!(
+        decode(InstructionSet::XoChip, 0x5122),
+        Instruction::StoreRange(1, 2)
+    );
+    assert_eq!(
+        decode(InstructionSet::SuperChip, 0xF000),
+        Instruction::Unknown(0xF000)
+    );
+    assert_eq!(
+        Platform::SuperChip.instruction_set(),
+        InstructionSet::SuperChip
+    );
+    assert_eq!(Platform::XoChip.instruction_set(), InstructionSet::XoChip);
+}
+
+#[test]
+fn low_resolution_doubles_every_pixel() {
+    // LD V0, 0; LD F, V0; DRW V0, V0, 5 desenha o dígito 0 da fonte
+    let mut chip8 = machine(Platform::SuperChip, &[0x6000, 0xF029, 0xD005]);
+    assert_eq!(chip8.resolution(), (128, 64));
+    assert!(chip8.lores());
+    run(&mut chip8, 3);
+    // 0xF0 vira 8 pixels acesos em duas linhas, 0x90 vira ##....##
+    assert_eq!(row(&chip8, 0, 10), "########..");
+    assert_eq!(row(&chip8, 1, 10), "########..");
+    assert_eq!(row(&chip8, 2, 10), "##....##..");
+    assert_eq!(row(&chip8, 10, 10), "..........");
+}
+
+#[test]
+fn high_resolution_draws_16x16_sprites() {
+    // HIGH; LD V0, 120; LD I, 0x20A; DRW V0, V0, 0 perto do canto, dando a volta na tela
+    let mut chip8 = machine(
+        Platform::SuperChip,
+        &[0x00FF, 0x6078, 0xA20A, 0xD000, 0x1208],
+    );
+    for n in 0..32 {
+        chip8.write_byte(0x20A + n, if n % 2 == 0 { 0x80 } else { 0x01 });
+    }
+    chip8.quirks.clip_sprites = false;
+    run(&mut chip8, 4);
+    assert!(!chip8.lores());
+    // x = 120: a coluna 0 do sprite cai na 120 e a 15 dá a volta até a 7
+    assert!(chip8.pixel(120, 120 % 64));
+    assert!(chip8.pixel(7, 120 % 64));
+    assert!(chip8.pixel(7, (120 + 15) % 64));
+    assert!(!chip8.pixel(121, 120 % 64));
+    assert_eq!(chip8.v[0xF], 0);
+    // O 00FE volta para a baixa resolução com a tela apagada
+    chip8.execute(Instruction::LowRes).unwrap();
+    assert!(chip8.lores());
+    assert!(chip8.pixels().all(|on| !on));
+}
+
+#[test]
+fn scrolling_moves_the_screen() {
+    let mut chip8 = machine(Platform::SuperChip, &[0x00FF]);
+    run(&mut chip8, 1);
+    chip8.set_pixel(10, 5, true);
+    chip8.execute(Instruction::ScrollDown(3)).unwrap();
+    assert!(chip8.pixel(10, 8));
+    assert!(!chip8.pixel(10, 5));
+    chip8.execute(Instruction::ScrollRight).unwrap();
+    assert!(chip8.pixel(14, 8));
+    chip8.execute(Instruction::ScrollLeft).unwrap();
+    chip8.execute(Instruction::ScrollLeft).unwrap();
+    assert!(chip8.pixel(6, 8));
+    assert_eq!(chip8.pixels().filter(|&on| on).count(), 1);
+
+    // Na baixa resolução os deslocamentos são em pixels do programa, o dobro na tela
+    chip8.execute(Instruction::LowRes).unwrap();
+    chip8.set_pixel(0, 0, true);
+    chip8.execute(Instruction::ScrollDown(1)).unwrap();
+    chip8.execute(Instruction::ScrollRight).unwrap();
+    assert!(chip8.pixel(8, 2));
+
+    // O XO-CHIP também rola para cima
+    let mut xo = machine(Platform::XoChip, &[]);
+    xo.set_pixel(0, 10, true);
+    xo.execute(Instruction::ScrollUp(2)).unwrap();
+    assert!(xo.pixel(0, 6));
+}
+
+#[test]
+fn fonts_are_in_memory() {
+    // LD V0, 3; LD HF, V0
+    let mut chip8 = machine(Platform::SuperChip, &[0x6003, 0xF030]);
+    run(&mut chip8, 2);
+    assert_eq!(chip8.i as usize, BIG_FONT_ADDRESS + 30);
+    assert_eq!(chip8.memory[chip8.i as usize], 0xFF);
+    // O dígito 1 da fonte pequena, o que o Fx29 aponta em todas as plataformas
+    let chip8 = Chip8::new();
+    assert_eq!(chip8.memory[5..10], [0x20, 0x60, 0x20, 0x20, 0x70]);
+}
+
+#[test]
+fn flags_keep_registers() {
+    // LD V0, 7; LD V1, 9; LD R, V1; LD V0, 0; LD V1, 0; LD V1, R
+    let mut chip8 = machine(
+        Platform::SuperChip,
+        &[0x6007, 0x6109, 0xF175, 0x6000, 0x6100, 0xF185],
+    );
+    run(&mut chip8, 6);
+    assert_eq!(chip8.v[..2], [7, 9]);
+    assert_eq!(chip8.flags[..2], [7, 9]);
+}
+
+#[test]
+fn long_load_skips_its_address_word() {
+    // LD I, LONG 0x1234; LD V1, 1
+    let mut chip8 = machine(Platform::XoChip, &[0xF000, 0x1234, 0x6101]);
+    run(&mut chip8, 2);
+    assert_eq!(chip8.i, 0x1234);
+    assert_eq!(chip8.v[1], 1);
+    assert_eq!(chip8.pc, 0x206);
+
+    // Um skip pula os 4 bytes do F000 NNNN
+    // SE V0, 0; LD I, LONG 0x1234; LD V1, 1
+    let mut chip8 = machine(Platform::XoChip, &[0x3000, 0xF000, 0x1234, 0x6101]);
+    run(&mut chip8, 2);
+    assert_eq!(chip8.i, 0);
+    assert_eq!(chip8.v[1], 1);
+}
+
+#[test]
+fn xochip_runs_code_above_4k() {
+    let mut chip8 = machine(Platform::XoChip, &[]);
+    chip8.memory[0x2000..0x2002].copy_from_slice(&[0x61, 0x05]);
+    chip8.flush_decode_cache();
+    chip8.pc = 0x2000;
+    run(&mut chip8, 1);
+    assert_eq!(chip8.v[1], 5);
+    assert_eq!(chip8.pc, 0x2002);
+
+    // No fim dos 64K o pc dá a volta para 0
+    chip8.pc = 0xFFFE;
+    run(&mut chip8, 1);
+    assert_eq!(chip8.pc, 0x0000);
+}
+
+#[test]
+fn plane_selects_where_sprites_go() {
+    // PLANE 2; LD I, 0x208; DRW V0, V0, 1; (sprite)
+    let mut chip8 = machine(Platform::XoChip, &[0xF201, 0xA208, 0xD001, 0x1206, 0xFF00]);
+    run(&mut chip8, 3);
+    assert_eq!(chip8.planes, 2);
+    assert!(chip8.video[0].iter().all(|&row| row == 0));
+    assert_ne!(chip8.video[1][0], 0);
+    assert_eq!(chip8.pixel_index(0, 0), 2);
+}
+
+#[test]
+fn xochip_register_ranges_leave_i_alone() {
+    // LD V1, 1; LD V2, 2; LD I, 0x300; LD [I], V1..V2
+    let mut chip8 = machine(Platform::XoChip, &[0x6101, 0x6202, 0xA300, 0x5122]);
+    chip8.quirks.memory_increment_i = true;
+    run(&mut chip8, 4);
+    assert_eq!(chip8.memory[0x300..0x302], [1, 2]);
+    assert_eq!(chip8.i, 0x300);
+}
+
+#[test]
+fn recompiler_matches_interpreter_on_xochip_programs() {
+    // HIGH; SE V0, 0; LD I, LONG 0x0250; LD V1, 1; LD I, LONG 0x020A; DRW V1, V1, 0; SCD 2; JP 0x202
+    let program = [
+        0x00FF, 0x3000, 0xF000, 0x0250, 0x6101, 0xF000, 0x020A, 0xD110, 0x00C2, 0x1202,
+    ];
+    let mut interpreted = machine(Platform::XoChip, &program);
+    let mut recompiled = interpreted.clone();
+    let mut recompiler = Recompiler::new(recompiled.memory.len());
+    for _ in 0..20 {
+        interpreted.run_frame(7).unwrap();
+        recompiler.run_frame(&mut recompiled, 7).unwrap();
+        assert_eq!(interpreted.pc, recompiled.pc);
+        assert_eq!(interpreted.i, recompiled.i);
+        assert_eq!(interpreted.v, recompiled.v);
+        assert_eq!(interpreted.video, recompiled.video);
+    }
+}
@@ -0,0 +1,132 @@
+use chip8::platform::Platform;
+use chip8::source::{Rom, RomError, extension_platform};
+use std::io::{Cursor, Write};
+use zip::write::SimpleFileOptions;
+
+const PROGRAM: [u8; 4] = [0x00, 0xE0, 0x12, 0x02];
+
+fn zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    for (name, bytes) in files {
+        writer
+            .start_file(*name, SimpleFileOptions::default())
+            .unwrap();
+        writer.write_all(bytes).unwrap();
+    }
+    writer.finish().unwrap().into_inner()
+}
+
+// Cartucho como o Octo grava: 2 bits por pixel, tamanho em big-endian e o JSON
+fn cartridge(json: &str) -> Vec<u8> {
+    let mut payload = (json.len() as u32).to_be_bytes().to_vec();
+    payload.extend_from_slice(json.as_bytes());
+    let mut indices: Vec<u8> = payload
+        .iter()
+        .flat_map(|&byte| [byte >> 6, byte >> 4, byte >> 2, byte].map(|bits| bits & 3 | 4))
+        .collect();
+    let width = 64;
+    indices.resize(indices.len().div_ceil(width) * width, 0);
+    let height = indices.len() / width;
+
+    let palette: Vec<u8> = (0..8).flat_map(|i| [i * 30, 0, 0]).collect();
+    let mut gif = Vec::new();
+    let mut encoder = gif::Encoder::new(&mut gif, width as u16, height as u16, &palette).unwrap();
+    let frame = gif::Frame {
+        width: width as u16,
+        height: height as u16,
+        buffer: indices.into(),
+        ..gif::Frame::default()
+    };
+    encoder.write_frame(&frame).unwrap();
+    drop(encoder);
+    gif
+}
+
+fn no_pick(_: &[String]) -> Option<usize> {
+    panic!("there is nothing to choose")
+}
+
+#[test]
+fn extensions_imply_a_platform() {
+    assert_eq!(extension_platform("pong.c8"), Some(Platform::Chip8));
+    assert_eq!(extension_platform("ant.SC8"), Some(Platform::SuperChip));
+    assert_eq!(extension_platform("dir/t8nks.xo8"), Some(Platform::XoChip));
+    assert_eq!(extension_platform("pong.ch8"), None);
+
+    let rom = Rom::from_bytes("ant.sc8", PROGRAM.to_vec(), no_pick).unwrap();
+    assert_eq!(rom.bytes, PROGRAM);
+    assert_eq!(rom.settings.platform(), Ok(Some(Platform::SuperChip)));
+}
+
+#[test]
+fn zip_with_one_rom_loads_it_directly() {
+    let archive = zip(&[("readme.txt", b"hello"), ("games/ant.sc8", &PROGRAM)]);
+    let rom = Rom::from_bytes("pack.zip", archive, no_pick).unwrap();
+    assert_eq!(rom.name, "games/ant.sc8");
+    assert_eq!(rom.bytes, PROGRAM);
+    assert_eq!(rom.settings.platform(), Ok(Some(Platform::SuperChip)));
+}
+
+#[test]
+fn zip_with_several_roms_asks_which_one() {
+    let archive = zip(&[("b.ch8", &[0xB0]), ("a.xo8", &[0xA0]), ("c.ch8", &[0xC0])]);
+    let mut offered = Vec::new();
+    let rom = Rom::from_bytes("pack.zip", archive.clone(), |names| {
+        offered = names.to_vec();
+        Some(1)
+    })
+    .unwrap();
+    assert_eq!(offered, ["a.xo8", "b.ch8", "c.ch8"]);
+    assert_eq!((rom.name.as_str(), rom.bytes), ("b.ch8", vec![0xB0]));
+
+    let cancelled = Rom::from_bytes("pack.zip", archive, |_| None);
+    assert!(matches!(cancelled, Err(RomError::NotSelected)));
+    let empty = Rom::from_bytes("pack.zip", zip(&[("readme.txt", b"hi")]), no_pick);
+    assert!(matches!(empty, Err(RomError::NoRoms)));
+}
+
+#[test]
+fn cartridge_brings_program_and_options() {
+    let json = r##"{
+        "program": ": main\n  0x00 0xE0 # CLS\n  18 0b10\n",
+        "options": {
+            "tickrate": 200,
+            "maxSize": 65024,
+            "backgroundColor": "#000000",
+            "fillColor": "#FF0000",
+            "fillColor2": "#00FF00",
+            "blendColor": "#FFFFFF",
+            "shiftQuirks": true,
+            "loadStoreQuirks": true,
+            "vBlankQuirks": true,
+            "fontStyle": "octo"
+        }
+    }"##;
+    let rom = Rom::from_bytes("game.gif", cartridge(json), no_pick).unwrap();
+    assert_eq!(rom.bytes, PROGRAM);
+    let settings = rom.settings;
+    assert_eq!(settings.platform(), Ok(Some(Platform::XoChip)));
+    assert_eq!(settings.instructions_per_frame, Some(200));
+    assert_eq!(settings.colors().unwrap().unwrap().colors[1], 0xFF0000);
+    assert_eq!(settings.quirks.shift_vx, Some(true));
+    assert_eq!(settings.quirks.memory_increment_i, Some(false));
+    assert_eq!(settings.quirks.display_wait, Some(true));
+    assert_eq!(settings.quirks.clip_sprites, None);
+}
+
+#[test]
+fn cartridge_with_octo_source_is_assembled() {
+    let json = r#"{"program": ": main\n  clear\n  loop again\n", "options": {}}"#;
+    let rom = Rom::from_bytes("game.gif", cartridge(json), no_pick).unwrap();
+    assert_eq!(rom.bytes, [0x00, 0xE0, 0x12, 0x02]);
+
+    // O que o assembler não conhece continua sendo recusado
+    let json = r#"{"program": ": main\n  :stringmode x \"a\" { }\n", "options": {}}"#;
+    let error = Rom::from_bytes("game.gif", cartridge(json), no_pick).unwrap_err();
+    let message = error.to_string();
+    assert!(message.contains("unsupported"), "{}", message);
+    assert!(message.contains("':stringmode'"), "{}", message);
+
+    let not_a_cartridge = Rom::from_bytes("photo.gif", cartridge("nope"), no_pick);
+    assert!(matches!(not_a_cartridge, Err(RomError::Cartridge(_))));
+}
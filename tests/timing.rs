@@ -0,0 +1,108 @@
+// Velocidade do VIP: a quirk display_wait e o run_vip_frame, que conta os ciclos de cada
+// instrução em vez de um número fixo por quadro
+
+use chip8::Chip8;
+use chip8::instruction::Instruction;
+use chip8::recompiler::Recompiler;
+use chip8::timing::{Timing, VIP_CYCLES_PER_FRAME, vip_cycles};
+
+fn machine(program: &[u16]) -> Chip8 {
+    let bytes: Vec<u8> = program.iter().flat_map(|op| op.to_be_bytes()).collect();
+    let mut chip8 = Chip8::new();
+    chip8.trace = false;
+    chip8.quirks.display_wait = true;
+    chip8.load_bytes(&bytes).unwrap();
+    chip8
+}
+
+// 0x200: DRW V0, V0, 1; ADD V1, 1; JP 0x200
+const DRAW_LOOP: [u16; 3] = [0xD001, 0x7101, 0x1200];
+
+#[test]
+fn display_wait_ends_the_frame_after_a_draw() {
+    let mut chip8 = machine(&DRAW_LOOP);
+    let mut recompiled = chip8.clone();
+    let mut recompiler = Recompiler::new(recompiled.memory.len());
+    for _ in 0..3 {
+        chip8.run_frame(10).unwrap();
+        recompiler.run_frame(&mut recompiled, 10).unwrap();
+    }
+    // Um desenho por quadro: o ADD e o JP de cada volta ficam para o quadro seguinte
+    assert_eq!((chip8.v[1], chip8.pc), (2, 0x202));
+    assert_eq!((recompiled.v[1], recompiled.pc), (2, 0x202));
+
+    let mut chip8 = machine(&DRAW_LOOP);
+    chip8.quirks.display_wait = false;
+    chip8.run_frame(10).unwrap();
+    assert_eq!((chip8.v[1], chip8.pc), (3, 0x202));
+}
+
+#[test]
+fn vip_frames_spend_the_cycle_budget() {
+    // 0x200: ADD V0, 1; JP 0x200
+    let mut chip8 = machine(&[0x7001, 0x1200]);
+    let add = vip_cycles(&chip8, Instruction::AddImmediate(0, 1));
+    let jump = vip_cycles(&chip8, Instruction::Jump(0x200));
+    let executed = chip8.run_vip_frame().unwrap();
+    // Roda até passar do limite, então a última instrução já está além dele
+    let pairs = (executed / 2) as u32;
+    let spent = pairs * (add + jump) + (executed as u32 % 2) * add;
+    assert!(spent >= VIP_CYCLES_PER_FRAME);
+    assert!(spent - VIP_CYCLES_PER_FRAME < add.max(jump));
+    assert_eq!(chip8.v[0] as usize, executed.div_ceil(2));
+}
+
+#[test]
+fn long_instructions_borrow_from_the_next_frame() {
+    // 0x200: CLS; ADD V0, 1; JP 0x202
+    let mut chip8 = machine(&[0x00E0, 0x7001, 0x1202]);
+    assert!(vip_cycles(&chip8, Instruction::Clear) > VIP_CYCLES_PER_FRAME);
+    assert_eq!(chip8.run_vip_frame().unwrap(), 1);
+    let after_clear = chip8.run_vip_frame().unwrap();
+    let normal = chip8.run_vip_frame().unwrap();
+    assert!(after_clear < normal);
+}
+
+#[test]
+fn vip_frames_stop_at_the_display_wait() {
+    let mut chip8 = machine(&DRAW_LOOP);
+    assert_eq!(chip8.run_vip_frame().unwrap(), 1);
+    assert_eq!(chip8.run_vip_frame().unwrap(), 3);
+}
+
+#[test]
+fn costs_depend_on_the_operands() {
+    let mut chip8 = Chip8::new();
+    chip8.v[1] = 5;
+    // Skip que pula, sprite fora do alinhamento de 8 pixels e BCD com dígitos maiores
+    assert!(
+        vip_cycles(&chip8, Instruction::SkipEqual(1, 5))
+            > vip_cycles(&chip8, Instruction::SkipEqual(1, 6))
+    );
+    assert!(
+        vip_cycles(&chip8, Instruction::Draw(1, 0, 4))
+            > vip_cycles(&chip8, Instruction::Draw(0, 0, 4))
+    );
+    assert!(vip_cycles(&chip8, Instruction::Bcd(1)) > vip_cycles(&chip8, Instruction::Bcd(0)));
+    assert_eq!("VIP".parse::<Timing>(), Ok(Timing::Vip));
+    assert!("cycles".parse::<Timing>().is_err());
+}
+
+#[test]
+fn key_skip_costs_follow_the_branch_taken() {
+    // Vx = 0x13: só o nibble baixo conta, como no Ex9E
+    let mut chip8 = machine(&[0xE09E]);
+    chip8.v[0] = 0x13;
+    let not_taken = vip_cycles(&chip8, Instruction::SkipKey(0));
+    // Um toque mais curto que um quadro: a tecla já foi solta, mas o Ex9E ainda pula
+    chip8.set_key(3, true);
+    chip8.set_key(3, false);
+    let taken = vip_cycles(&chip8, Instruction::SkipKey(0));
+    assert!(taken > not_taken);
+    assert!(
+        vip_cycles(&chip8, Instruction::SkipNotKey(0))
+            < vip_cycles(&Chip8::new(), Instruction::SkipNotKey(0))
+    );
+    chip8.cycle().unwrap();
+    assert_eq!(chip8.pc, 0x204);
+}
@@ -0,0 +1,214 @@
+// Variantes do CHIP-8 do COSMAC VIP: CHIP-8X (cor e segundo teclado), CHIP-8E (skips e desvios a
+// mais) e CHIP-10 (tela de 128x64). Cada uma é uma plataforma com a própria tabela de opcodes
+
+use chip8::Chip8;
+use chip8::color::{BACKGROUND_COLORS, FOREGROUND_COLORS};
+use chip8::instruction::{Instruction, InstructionSet};
+use chip8::platform::Platform;
+use chip8::recompiler::Recompiler;
+use chip8::source::extension_platform;
+
+fn machine(platform: Platform, program: &[u16]) -> Chip8 {
+    let bytes: Vec<u8> = program.iter().flat_map(|op| op.to_be_bytes()).collect();
+    let mut chip8 = platform.machine();
+    chip8.trace = false;
+    chip8
+        .load_bytes_at(&bytes, platform.start_address())
+        .unwrap();
+    chip8
+}
+
+fn run(chip8: &mut Chip8, cycles: usize) {
+    for _ in 0..cycles {
+        chip8.cycle().unwrap();
+    }
+}
+
+#[test]
+fn each_variant_has_its_own_decoder_table() {
+    let decode = |set: InstructionSet, opcode| set.decode(opcode);
+    assert_eq!(
+        decode(InstructionSet::Chip8, 0x5121),
+        Instruction::Unknown(0x5121)
+    );
+    assert_eq!(
+        decode(InstructionSet::Chip8X, 0x5121),
+        Instruction::AddNibbles(1, 2)
+    );
+    assert_eq!(
+        decode(InstructionSet::Chip8E, 0x5121),
+        Instruction::SkipGreater(1, 2)
+    );
+    assert_eq!(
+        decode(InstructionSet::Chip8X, 0xB123),
+        Instruction::Color(1, 2, 3)
+    );
+    assert_eq!(
+        decode(InstructionSet::Chip8E, 0xB123),
+        Instruction::JumpOffset(1, 0x123)
+    );
+    assert_eq!(
+        decode(InstructionSet::Chip8E, 0xBB10),
+        Instruction::BranchBack(0x10)
+    );
+    assert_eq!(decode(InstructionSet::Chip8E, 0x00ED), Instruction::Halt);
+    assert_eq!(
+        decode(InstructionSet::Chip8X, 0x00ED),
+        Instruction::Unknown(0x00ED)
+    );
+    // O que a variante não muda continua sendo CHIP-8
+    assert_eq!(
+        decode(InstructionSet::Chip8X, 0xD125),
+        Instruction::Draw(1, 2, 5)
+    );
+    assert_eq!(Platform::Chip10.instruction_set(), InstructionSet::Chip8);
+}
+
+#[test]
+fn variants_are_selectable_platforms() {
+    for platform in Platform::ALL {
+        assert_eq!(platform.to_string().parse(), Ok(platform));
+    }
+    assert_eq!("CHIP-8X".parse(), Ok(Platform::Chip8X));
+    assert_eq!("chip-8e".parse(), Ok(Platform::Chip8E));
+    assert_eq!("CHIP-10".parse(), Ok(Platform::Chip10));
+    assert_eq!(extension_platform("ufo.c8x"), Some(Platform::Chip8X));
+
+    assert_eq!(Platform::Chip8X.start_address(), 0x300);
+    assert_eq!(Platform::Chip10.resolution(), (128, 64));
+    assert!(Platform::Chip8X.machine().colors.is_some());
+    assert!(Platform::Chip8E.machine().colors.is_none());
+}
+
+#[test]
+fn chip8x_colors_zones_and_cycles_the_background() {
+    let mut chip8 = machine(
+        Platform::Chip8X,
+        &[
+            // Zonas das colunas 2 e 3 na segunda faixa de 4 linhas, em amarelo
+            0x6012, 0x6101, 0x6205, 0xB020,
+            // 3 linhas a partir da 30 (dando a volta) no bloco da coluna 33, em ciano, e a
+            // próxima cor de fundo
+            0x6A21, 0x6B1E, 0x6C06, 0xBAC3, 0x02A0,
+        ],
+    );
+    assert_eq!(chip8.pc, 0x300);
+    run(&mut chip8, 9);
+    let colors = chip8.colors.as_ref().unwrap();
+    assert_eq!((colors.zones[4][2], colors.zones[7][3]), (5, 5));
+    assert_eq!(colors.zones[3][2], 1);
+    assert_eq!(colors.zones[8][2], 1);
+    assert_eq!(colors.zones[4][4], 1);
+    assert_eq!(
+        (colors.zones[30][4], colors.zones[31][4], colors.zones[0][4]),
+        (6, 6, 6)
+    );
+    assert_eq!(colors.zones[1][4], 1);
+    assert_eq!(colors.background, 1);
+
+    assert_eq!(colors.color(16, 4, true), FOREGROUND_COLORS[5]);
+    assert_eq!(colors.color(16, 4, false), BACKGROUND_COLORS[1]);
+}
+
+#[test]
+fn chip8x_reads_the_second_keypad() {
+    // LD V0, 5; SKP2 V0; LD V1, 1; SKNP2 V0; LD V2, 1
+    let mut chip8 = machine(Platform::Chip8X, &[0x6005, 0xE0F2, 0x6101, 0xE0F5, 0x6201]);
+    chip8.second_keypad[5] = true;
+    run(&mut chip8, 4);
+    assert_eq!((chip8.v[1], chip8.v[2]), (0, 1));
+
+    // O primeiro teclado não conta
+    let mut chip8 = machine(Platform::Chip8X, &[0x6005, 0xE0F2, 0x6101]);
+    chip8.set_key(5, true);
+    run(&mut chip8, 3);
+    assert_eq!(chip8.v[1], 1);
+
+    // 5xy1 soma nibble a nibble sem vai-um entre eles
+    let mut chip8 = machine(Platform::Chip8X, &[0x6036, 0x6125, 0x5011]);
+    run(&mut chip8, 3);
+    assert_eq!(chip8.v[0], 0x53);
+}
+
+#[test]
+fn chip8e_skips_and_branches() {
+    let mut chip8 = machine(
+        Platform::Chip8E,
+        &[
+            0x6005, // 0x200: LD V0, 5
+            0x6103, // 0x202: LD V1, 3
+            0x5011, // 0x204: SGT V0, V1
+            0x6201, // 0x206
+            0x0188, // 0x208: SKIP
+            0x6301, // 0x20A
+            0xBF02, // 0x20C: JF 2
+            0x6401, // 0x20E
+            0x6602, // 0x210: LD V6, 2
+            0xF61B, // 0x212: SKIP V6
+            0x6701, // 0x214
+            0x00ED, // 0x216: STOP
+        ],
+    );
+    run(&mut chip8, 12);
+    assert_eq!(chip8.pc, 0x216);
+    assert_eq!(
+        (chip8.v[2], chip8.v[3], chip8.v[4], chip8.v[7]),
+        (0, 0, 0, 0)
+    );
+
+    // 0x200: LD V0, 1; 0x202: ADD V0, 1; 0x204: JB 4 (volta para 0x202)
+    let mut chip8 = machine(Platform::Chip8E, &[0x6001, 0x7001, 0xBB04]);
+    let mut recompiled = chip8.clone();
+    run(&mut chip8, 7);
+    assert_eq!((chip8.v[0], chip8.pc), (4, 0x202));
+    // O recompilador decodifica com a mesma tabela
+    Recompiler::new(recompiled.memory.len())
+        .run_frame(&mut recompiled, 7)
+        .unwrap();
+    assert_eq!((recompiled.v[0], recompiled.pc), (4, 0x202));
+}
+
+#[test]
+fn chip8e_stores_register_ranges_and_waits_for_the_delay_timer() {
+    let mut chip8 = machine(
+        Platform::Chip8E,
+        &[
+            0xA300, 0x6311, 0x6422, 0x6533, // V3..V5 em 0x300
+            0x5352, 0xA300, 0x5533, // e de volta em ordem decrescente, V5..V3
+        ],
+    );
+    run(&mut chip8, 5);
+    assert_eq!(chip8.memory[0x300..0x303], [0x11, 0x22, 0x33]);
+    assert_eq!(chip8.i, 0x303);
+    run(&mut chip8, 2);
+    assert_eq!((chip8.v[5], chip8.v[4], chip8.v[3]), (0x11, 0x22, 0x33));
+
+    // LD V0, 3; LD DT, V0 e espera; LD V1, 1
+    let mut chip8 = machine(Platform::Chip8E, &[0x6003, 0xF04F, 0x6101]);
+    for _ in 0..3 {
+        chip8.run_frame(10).unwrap();
+    }
+    assert_eq!((chip8.v[1], chip8.pc), (0, 0x202));
+    chip8.run_frame(10).unwrap();
+    assert_eq!(chip8.v[1], 1);
+}
+
+#[test]
+fn chip10_draws_on_a_128x64_screen() {
+    // Sprite de 2 linhas de 0xFF no canto de baixo à direita (x = 124, y = 63)
+    let program = [0x607C, 0x613F, 0xA208, 0xD012, 0xFFFF];
+    let mut wrapped = machine(Platform::Chip10, &program);
+    wrapped.quirks.clip_sprites = false;
+    run(&mut wrapped, 4);
+    assert_eq!(wrapped.resolution(), (128, 64));
+    assert_eq!(wrapped.pixels().count(), 128 * 64);
+    assert!(wrapped.pixel(124, 63) && wrapped.pixel(127, 63) && !wrapped.pixel(123, 63));
+    // O que passa da borda volta pela esquerda e por cima
+    assert!(wrapped.pixel(0, 63) && wrapped.pixel(3, 0) && !wrapped.pixel(4, 0));
+
+    let mut clipped = machine(Platform::Chip10, &program);
+    clipped.quirks.clip_sprites = true;
+    run(&mut clipped, 4);
+    assert!(clipped.pixel(127, 63) && !clipped.pixel(0, 63));
+    assert_eq!(clipped.video[0][0], 0);
+}
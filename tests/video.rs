@@ -0,0 +1,84 @@
+// Tela empacotada: um array de linhas por plano, com a coluna 0 no bit mais alto da largura
+
+use chip8::Chip8;
+use chip8::instruction::Instruction;
+
+fn draw(chip8: &mut Chip8, x: u8, y: u8) {
+    chip8.i = 0x300;
+    chip8.v[0] = x;
+    chip8.v[1] = y;
+    chip8.execute(Instruction::Draw(0, 1, 1)).unwrap();
+}
+
+fn machine(clip_sprites: bool) -> Chip8 {
+    let mut chip8 = Chip8::new();
+    chip8.trace = false;
+    chip8.quirks.clip_sprites = clip_sprites;
+    chip8.memory[0x300] = 0xFF;
+    chip8
+}
+
+#[test]
+fn sprites_wrap_or_clip_at_the_right_edge() {
+    let mut wrapped = machine(false);
+    draw(&mut wrapped, 60, 5);
+    assert_eq!(wrapped.video[0][5], 0xF000_0000_0000_000F);
+    assert!(wrapped.pixel(63, 5) && wrapped.pixel(3, 5) && !wrapped.pixel(4, 5));
+
+    let mut clipped = machine(true);
+    draw(&mut clipped, 60, 5);
+    assert_eq!(clipped.video[0][5], 0x0000_0000_0000_000F);
+    assert!(!clipped.pixel(0, 5));
+}
+
+#[test]
+fn collision_only_when_a_lit_pixel_is_erased() {
+    let mut chip8 = machine(true);
+    draw(&mut chip8, 0, 0);
+    assert_eq!(chip8.v[0xF], 0);
+    // Sobrepõe só a coluna 7
+    draw(&mut chip8, 7, 0);
+    assert_eq!(chip8.v[0xF], 1);
+    assert_eq!(chip8.video[0][0], 0xFEFE_0000_0000_0000);
+    draw(&mut chip8, 16, 0);
+    assert_eq!(chip8.v[0xF], 0);
+}
+
+#[test]
+fn pixels_are_listed_row_by_row() {
+    let mut chip8 = machine(true);
+    chip8.set_pixel(2, 1, true);
+    let lit: Vec<usize> = chip8
+        .pixels()
+        .enumerate()
+        .filter_map(|(index, on)| on.then_some(index))
+        .collect();
+    assert_eq!(lit, [64 + 2]);
+    chip8.set_pixel(2, 1, false);
+    assert!(chip8.video.iter().flatten().all(|&row| row == 0));
+}
+
+#[test]
+fn planes_are_drawn_and_cleared_separately() {
+    let mut chip8 = machine(true);
+    // Com os dois planos selecionados o sprite do segundo plano vem logo depois do primeiro
+    chip8.memory[0x300] = 0xF0;
+    chip8.memory[0x301] = 0x3C;
+    chip8.planes = 0b11;
+    draw(&mut chip8, 0, 0);
+    assert_eq!(chip8.video[0][0], 0xF000_0000_0000_0000);
+    assert_eq!(chip8.video[1][0], 0x3C00_0000_0000_0000);
+    let indices: Vec<u8> = chip8.pixel_indices().take(8).collect();
+    assert_eq!(indices, [1, 1, 3, 3, 2, 2, 0, 0]);
+    assert!(chip8.pixel(4, 0) && !chip8.pixel(6, 0));
+
+    // Só o segundo plano: a colisão conta nele e o 00E0 não apaga o primeiro
+    chip8.planes = 0b10;
+    chip8.memory[0x300] = 0x20;
+    draw(&mut chip8, 0, 0);
+    assert_eq!(chip8.v[0xF], 1);
+    assert_eq!(chip8.pixel_index(2, 0), 1);
+    chip8.execute(Instruction::Clear).unwrap();
+    assert_eq!(chip8.video[1][0], 0);
+    assert_eq!(chip8.video[0][0], 0xF000_0000_0000_0000);
+}
@@ -0,0 +1,228 @@
+// COSMAC VIP: a CPU 1802 com um barramento de teste, o 1861 com uma rotina de interrupção
+// mínima e, quando as imagens estiverem disponíveis, o interpretador CHIP-8 original rodando a
+// mesma ROM que o core de alto nível.
+//
+// O interpretador e a ROM do monitor não vêm com o emulador. Para o teste diferencial, aponte
+// CHIP8_VIP_INTERPRETER e CHIP8_VIP_MONITOR para os arquivos (512 bytes cada)
+
+use chip8::cdp1802::{Bus, Cdp1802};
+use chip8::machine::Machine;
+use chip8::vip::{PIXIE_LINES, Vip};
+use chip8::{Chip8, VIDEO_HEIGHT};
+
+struct TestBus {
+    memory: Vec<u8>,
+    flags: [bool; 4],
+    outputs: Vec<(u8, u8)>,
+}
+
+impl Bus for TestBus {
+    fn read(&mut self, addr: u16) -> u8 {
+        self.memory[addr as usize]
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        self.memory[addr as usize] = value;
+    }
+
+    fn output(&mut self, port: u8, value: u8) {
+        self.outputs.push((port, value));
+    }
+
+    fn input(&mut self, _port: u8) -> u8 {
+        0x5A
+    }
+
+    fn flag(&self, ef: u8) -> bool {
+        self.flags[ef as usize - 1]
+    }
+}
+
+fn boot(program: &[u8]) -> (Cdp1802, TestBus) {
+    let mut memory = vec![0; 0x10000];
+    memory[..program.len()].copy_from_slice(program);
+    let bus = TestBus {
+        memory,
+        flags: [false; 4],
+        outputs: Vec::new(),
+    };
+    (Cdp1802::new(), bus)
+}
+
+fn steps(cpu: &mut Cdp1802, bus: &mut TestBus, count: usize) -> u32 {
+    (0..count).map(|_| cpu.step(bus)).sum()
+}
+
+#[test]
+fn arithmetic_sets_df() {
+    // LDI F0; ADI 20 -> 0x10 com vai-um; SMI 20 -> 0xF0 com empréstimo (DF = 0)
+    let (mut cpu, mut bus) = boot(&[0xF8, 0xF0, 0xFC, 0x20, 0xFF, 0x20]);
+    steps(&mut cpu, &mut bus, 2);
+    assert_eq!((cpu.d, cpu.df), (0x10, true));
+    steps(&mut cpu, &mut bus, 1);
+    assert_eq!((cpu.d, cpu.df), (0xF0, false));
+    // SHLC (7E) põe o DF antigo no bit 0 e o bit 7 em DF
+    let (mut cpu, mut bus) = boot(&[0xF8, 0x81, 0x7E]);
+    cpu.df = false;
+    steps(&mut cpu, &mut bus, 2);
+    assert_eq!((cpu.d, cpu.df), (0x02, true));
+}
+
+#[test]
+fn branches_and_cycles() {
+    // LBR 0x1234 gasta 3 ciclos; o resto, 2
+    let (mut cpu, mut bus) = boot(&[0xC0, 0x12, 0x34]);
+    assert_eq!(cpu.step(&mut bus), 3);
+    assert_eq!(cpu.pc(), 0x1234);
+
+    // B3 só desvia com EF3 ativo; sem ele o byte do endereço é pulado
+    let (mut cpu, mut bus) = boot(&[0x36, 0x40, 0x36, 0x50]);
+    assert_eq!(cpu.step(&mut bus), 2);
+    assert_eq!(cpu.pc(), 0x0002);
+    bus.flags[2] = true;
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc(), 0x0050);
+}
+
+#[test]
+fn sep_mark_and_return() {
+    // R3 aponta para a subrotina em 0x10; MARK guarda X e P, e RET (com X = R2) volta
+    let mut program = vec![0; 0x20];
+    program[..7].copy_from_slice(&[0xF8, 0x10, 0xA3, 0xE2, 0x79, 0xD3, 0x00]);
+    program[0x10..0x13].copy_from_slice(&[0xE2, 0x12, 0x70]);
+    let (mut cpu, mut bus) = boot(&program);
+    cpu.r[2] = 0x100;
+    steps(&mut cpu, &mut bus, 5);
+    assert_eq!((cpu.p, cpu.x, bus.memory[0x100]), (3, 0, 0x20));
+    // SEX 2, INC R2 (o MARK deixou R2 uma posição abaixo) e RET
+    steps(&mut cpu, &mut bus, 3);
+    assert_eq!((cpu.p, cpu.x, cpu.pc(), cpu.ie), (0, 2, 0x0006, true));
+}
+
+#[test]
+fn io_and_interrupts() {
+    // SEX 2; OUT 2 (o byte de M(R2)); INP 1
+    let (mut cpu, mut bus) = boot(&[0xE2, 0x62, 0x69]);
+    cpu.r[2] = 0x200;
+    bus.memory[0x200] = 0x0C;
+    steps(&mut cpu, &mut bus, 3);
+    assert_eq!(bus.outputs, [(2, 0x0C)]);
+    assert_eq!((cpu.d, bus.memory[0x201]), (0x5A, 0x5A));
+
+    assert_eq!(cpu.interrupt(), 1);
+    assert_eq!((cpu.t, cpu.p, cpu.x, cpu.ie), (0x20, 1, 2, false));
+}
+
+// Liga o vídeo e fica num laço; a interrupção aponta R0 para 0x300, onde ficam as 128 linhas
+// de 8 bytes, sem repetir linhas como o interpretador faz. Como a rotina do interpretador, ela só
+// volta depois que o EF1 marca o fim da tela
+const PIXIE_TEST: [u8; 42] = [
+    0xF8, 0x00, 0xB1, 0xF8, 0x1A, 0xA1, // 0x00: R1 = 0x001A (interrupção)
+    0xF8, 0x00, 0xB2, 0xF8, 0xFF, 0xA2, // 0x06: R2 = 0x00FF (pilha)
+    0xF8, 0x00, 0xB3, 0xF8, 0x13, 0xA3, // 0x0C: R3 = 0x0013
+    0xD3, //                               0x12: SEP R3
+    0xE2, 0x69, 0x7B, //                   0x13: SEX 2; INP 1 (liga o vídeo); SEQ
+    0x30, 0x16, //                         0x16: BR 0x16
+    0x72, 0x70, //                         0x18: LDXA; RET
+    0x22, 0x78, 0x22, 0x52, //             0x1A: salva T e D na pilha
+    0xF8, 0x03, 0xB0, 0xF8, 0x00, 0xA0, // 0x1E: R0 = 0x0300
+    0x34, 0x24, //                         0x24: B1 0x24 (EF1 das linhas antes da tela)
+    0x3C, 0x26, //                         0x26: BN1 0x26 (até o EF1 do fim da tela)
+    0x30, 0x18, //                         0x28: BR 0x18
+];
+
+#[test]
+fn pixie_shows_the_buffer_at_r0() {
+    let mut vip = Vip::new(&PIXIE_TEST).unwrap();
+    vip.memory_mut()[0x300] = 0x80;
+    vip.memory_mut()[0x301] = 0xFF;
+    // Linha 20, a primeira da linha 5 do CHIP-8
+    vip.memory_mut()[0x300 + 20 * 8 + 7] = 0x01;
+    vip.run_frame();
+
+    assert!(vip.display_enabled());
+    assert_eq!(vip.line(0), 0x80FF_0000_0000_0000);
+    assert!(vip.pixel(0, 0) && vip.pixel(15, 0) && !vip.pixel(16, 0));
+    assert!(vip.pixel(63, 5));
+    assert!((1..PIXIE_LINES).all(|n| n == 20 || vip.line(n) == 0));
+    // SEQ liga o beep
+    assert!(vip.sound_active());
+
+    // A interrupção volta para o laço, e o quadro seguinte mostra a mesma coisa
+    vip.run_frame();
+    assert_eq!(vip.cpu.p, 3);
+    assert_eq!(vip.line(0), 0x80FF_0000_0000_0000);
+}
+
+#[test]
+fn no_dma_with_the_display_off() {
+    // R2 = 0xFF; SEX 2; INP 1 seguido de OUT 1: o vídeo liga e desliga antes da primeira linha
+    let program = [0xF8, 0xFF, 0xA2, 0xE2, 0x69, 0x61, 0x30, 0x06];
+    let mut vip = Vip::new(&program).unwrap();
+    vip.run_frame();
+    assert!(!vip.display_enabled());
+    assert!((0..PIXIE_LINES).all(|n| vip.line(n) == 0));
+}
+
+#[test]
+fn keypad_is_read_through_ef3() {
+    // SEX 2; OUT 2 escolhe a tecla 0x7 em M(R2); B3 desvia para 0x40 se estiver pressionada
+    let mut program = vec![0xF8, 0xF0, 0xA2, 0xE2, 0x62, 0x36, 0x40, 0x30, 0x07];
+    program.resize(0x42, 0);
+    program[0x40..0x42].copy_from_slice(&[0x30, 0x40]);
+    let mut vip = Vip::new(&program).unwrap();
+    vip.memory_mut()[0xF0] = 0x07;
+    vip.set_key(7, true);
+    vip.run_frame();
+    assert_eq!(vip.cpu.pc() & 0xFF, 0x40);
+
+    let mut vip = Vip::new(&program).unwrap();
+    vip.memory_mut()[0xF0] = 0x07;
+    vip.set_key(3, true);
+    vip.run_frame();
+    assert_eq!(vip.cpu.pc(), 0x07);
+}
+
+#[test]
+fn the_chip8_core_is_a_machine() {
+    let mut chip8 = Chip8::new();
+    chip8.trace = false;
+    chip8.memory[0x300] = 0xC0;
+    let machine: &mut dyn Machine = &mut chip8;
+    // 0x200: LD I, 0x300; DRW V0, V0, 1
+    machine.load_bytes(&[0xA3, 0x00, 0xD0, 0x01]).unwrap();
+    machine.run_frame(2).unwrap();
+    assert!(machine.pixel(0, 0) && machine.pixel(1, 0) && !machine.pixel(2, 0));
+    let screen = machine.screen().map(u128::from);
+    assert_eq!(screen[..], chip8.video[0][..VIDEO_HEIGHT]);
+}
+
+fn image(variable: &str) -> Option<Vec<u8>> {
+    std::fs::read(std::env::var(variable).ok()?).ok()
+}
+
+#[test]
+fn original_interpreter_matches_the_core() {
+    let (Some(interpreter), Some(monitor)) =
+        (image("CHIP8_VIP_INTERPRETER"), image("CHIP8_VIP_MONITOR"))
+    else {
+        eprintln!("CHIP8_VIP_INTERPRETER / CHIP8_VIP_MONITOR not set, skipping");
+        return;
+    };
+    let rom = std::fs::read("roms/IBM Logo.ch8").unwrap();
+    let mut vip = Vip::new(&interpreter).unwrap();
+    vip.load_monitor(&monitor);
+    let mut core = Chip8::new();
+    core.trace = false;
+
+    let machines: [&mut dyn Machine; 2] = [&mut vip, &mut core];
+    let mut screens = Vec::new();
+    for machine in machines {
+        machine.load_bytes(&rom).unwrap();
+        for _ in 0..120 {
+            machine.run_frame(15).unwrap();
+        }
+        screens.push(machine.screen());
+    }
+    assert_eq!(screens[0], screens[1]);
+}